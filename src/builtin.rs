@@ -1,7 +1,9 @@
 use crate::{
-    c_int, c_value, Block, BuiltinMap, CExp, CExpPtr, CompileFunc, DataKind, EvalEnv, Expr, Rc,
-    Value,
+    c_int, c_value, datetime, json, math, Block, BuiltinMap, CExp, CExpPtr, CompileFunc, DataKind,
+    EvalEnv, Expr, Rc, Value,
 };
+#[cfg(feature = "regex")]
+use crate::{ExprIs, RefCell};
 
 /// Add builtin functions to specified [BuiltinMap].
 pub fn standard_builtins(map: &mut BuiltinMap) {
@@ -30,6 +32,49 @@ pub fn standard_builtins(map: &mut BuiltinMap) {
         ),
         ("LEN", DataKind::Int, CompileFunc::Int(c_len)),
         ("BINLEN", DataKind::Int, CompileFunc::Int(c_bin_len)),
+        ("CHARLEN", DataKind::Int, CompileFunc::Int(c_char_len)),
+        (
+            "CHARSUBSTRING",
+            DataKind::String,
+            CompileFunc::Value(c_char_substring),
+        ),
+        ("UPPER", DataKind::String, CompileFunc::Value(c_upper)),
+        ("LOWER", DataKind::String, CompileFunc::Value(c_lower)),
+        ("TRIM", DataKind::String, CompileFunc::Value(c_trim)),
+        ("LTRIM", DataKind::String, CompileFunc::Value(c_ltrim)),
+        ("RTRIM", DataKind::String, CompileFunc::Value(c_rtrim)),
+        ("LEFT", DataKind::String, CompileFunc::Value(c_left)),
+        ("RIGHT", DataKind::String, CompileFunc::Value(c_right)),
+        ("INDEXOF", DataKind::Int, CompileFunc::Int(c_index_of)),
+        ("PADLEFT", DataKind::String, CompileFunc::Value(c_pad_left)),
+        (
+            "SPLITPART",
+            DataKind::String,
+            CompileFunc::Value(c_split_part),
+        ),
+        ("REVERSE", DataKind::String, CompileFunc::Value(c_reverse)),
+        ("REPEAT", DataKind::String, CompileFunc::Value(c_repeat)),
+        ("ABS", DataKind::None, CompileFunc::Value(math::c_abs)),
+        ("SIGN", DataKind::None, CompileFunc::Value(math::c_sign)),
+        ("FLOOR", DataKind::None, CompileFunc::Value(math::c_floor)),
+        (
+            "CEILING",
+            DataKind::None,
+            CompileFunc::Value(math::c_ceiling),
+        ),
+        ("ROUND", DataKind::None, CompileFunc::Value(math::c_round)),
+        ("MOD", DataKind::None, CompileFunc::Value(math::c_mod)),
+        (
+            "POWER",
+            DataKind::Float,
+            CompileFunc::NullValue(math::c_power),
+        ),
+        (
+            "SQRT",
+            DataKind::Float,
+            CompileFunc::NullValue(math::c_sqrt),
+        ),
+        ("LOG", DataKind::Float, CompileFunc::NullValue(math::c_log)),
         ("PARSEINT", DataKind::Int, CompileFunc::Int(c_parse_int)),
         (
             "PARSEFLOAT",
@@ -50,6 +95,84 @@ pub fn standard_builtins(map: &mut BuiltinMap) {
         #[cfg(feature = "renumber")]
         ("RENUMBER", DataKind::Int, CompileFunc::Int(c_renumber)),
         ("BINTOSTR", DataKind::String, CompileFunc::Value(c_bintostr)),
+        #[cfg(feature = "regex")]
+        (
+            "REGEXMATCH",
+            DataKind::Bool,
+            CompileFunc::Value(c_regex_match),
+        ),
+        #[cfg(feature = "regex")]
+        (
+            "REGEXREPLACE",
+            DataKind::String,
+            CompileFunc::Value(c_regex_replace),
+        ),
+        #[cfg(feature = "regex")]
+        (
+            "REGEXEXTRACT",
+            DataKind::String,
+            CompileFunc::Value(c_regex_extract),
+        ),
+        (
+            "PARSEDATETIME",
+            DataKind::DateTime,
+            CompileFunc::NullValue(datetime::c_parse_datetime),
+        ),
+        (
+            "TODATETIME",
+            DataKind::DateTime,
+            CompileFunc::NullValue(datetime::c_to_datetime),
+        ),
+        (
+            "DATETIMESTR",
+            DataKind::String,
+            CompileFunc::NullValue(datetime::c_datetime_str),
+        ),
+        (
+            "FORMATDATETIME",
+            DataKind::String,
+            CompileFunc::NullValue(datetime::c_format_datetime),
+        ),
+        (
+            "DATEADD",
+            DataKind::DateTime,
+            CompileFunc::NullValue(datetime::c_date_add),
+        ),
+        (
+            "DATEDIFF",
+            DataKind::Int,
+            CompileFunc::NullValue(datetime::c_date_diff),
+        ),
+        (
+            "DATETRUNC",
+            DataKind::DateTime,
+            CompileFunc::NullValue(datetime::c_date_trunc),
+        ),
+        (
+            "DATEPART",
+            DataKind::Int,
+            CompileFunc::NullValue(datetime::c_date_part),
+        ),
+        (
+            "JSONVALUE",
+            DataKind::String,
+            CompileFunc::Value(json::c_json_value),
+        ),
+        (
+            "JSONESCAPE",
+            DataKind::String,
+            CompileFunc::Value(json::c_json_escape),
+        ),
+        (
+            "JSONARRAY",
+            DataKind::String,
+            CompileFunc::Value(json::c_json_array),
+        ),
+        (
+            "JSONOBJECT",
+            DataKind::String,
+            CompileFunc::Value(json::c_json_object),
+        ),
     ];
     for (name, typ, cf) in list {
         map.insert(name.to_string(), (typ, cf));
@@ -73,6 +196,30 @@ pub fn check_types(b: &Block, args: &mut [Expr], dk: &[DataKind]) {
     }
 }
 /////////////////////////////
+/// Compile call of a function f which is applied to the argument values, the result is NULL if an argument is NULL
+/// ( see [CompileFunc::NullValue] ). The argument types should already be checked.
+pub(crate) fn c_null_map(b: &Block, args: &mut [Expr], f: fn(&[Value]) -> Value) -> CExpPtr<Value> {
+    let args = args.iter_mut().map(|e| c_value(b, e)).collect();
+    Box::new(NullMap { args, f })
+}
+struct NullMap {
+    args: Vec<CExpPtr<Value>>,
+    f: fn(&[Value]) -> Value,
+}
+impl CExp<Value> for NullMap {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> Value {
+        let mut values = Vec::with_capacity(self.args.len());
+        for ce in &self.args {
+            let v = ce.eval(e, d);
+            if v.is_null() {
+                return Value::Null;
+            }
+            values.push(v);
+        }
+        (self.f)(&values)
+    }
+}
+/////////////////////////////
 /// Compile call to EXCEPTION().
 fn c_exception(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
     check_types(b, args, &[]);
@@ -81,6 +228,9 @@ fn c_exception(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
 struct Exception {}
 impl CExp<Value> for Exception {
     fn eval(&self, e: &mut EvalEnv, _d: &[u8]) -> Value {
+        if let Some(err) = &e.exception {
+            return Value::String(err.clone());
+        }
         let err = e.tr.get_error();
         Value::String(Rc::new(err))
     }
@@ -282,6 +432,262 @@ impl CExp<Value> for Substring {
     }
 }
 
+/////////////////////////////
+/// Convert an int argument to a count, negative values are treated as zero.
+fn count(x: i64) -> usize {
+    usize::try_from(x).unwrap_or(0)
+}
+
+/// Largest string ( in bytes ) that REPEAT or PADLEFT will create.
+const MAX_STRING: usize = 4 << 20;
+
+/// Check the size of the result of a string function.
+fn check_size(name: &str, size: usize) {
+    if size > MAX_STRING {
+        panic!(
+            "{} result is too long ( limit is {} bytes )",
+            name, MAX_STRING
+        );
+    }
+}
+/////////////////////////////
+/// Compile call to CHARLEN.
+fn c_char_len(b: &Block, args: &mut [Expr]) -> CExpPtr<i64> {
+    check_types(b, args, &[DataKind::String]);
+    let s = c_value(b, &mut args[0]);
+    Box::new(CharLen { s })
+}
+struct CharLen {
+    s: CExpPtr<Value>,
+}
+impl CExp<i64> for CharLen {
+    fn eval(&self, ee: &mut EvalEnv, d: &[u8]) -> i64 {
+        self.s.eval(ee, d).str().chars().count() as i64
+    }
+}
+/////////////////////////////
+/// Compile call to CHARSUBSTRING.
+fn c_char_substring(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_types(b, args, &[DataKind::String, DataKind::Int, DataKind::Int]);
+    let s = c_value(b, &mut args[0]);
+    let f = c_int(b, &mut args[1]);
+    let n = c_int(b, &mut args[2]);
+    Box::new(CharSubstring { s, f, n })
+}
+struct CharSubstring {
+    s: CExpPtr<Value>,
+    f: CExpPtr<i64>,
+    n: CExpPtr<i64>,
+}
+impl CExp<Value> for CharSubstring {
+    fn eval(&self, ee: &mut EvalEnv, d: &[u8]) -> Value {
+        let s = self.s.eval(ee, d).str();
+        let f = self.f.eval(ee, d);
+        let n = self.n.eval(ee, d);
+        // Characters before position 1 are counted but not included.
+        let (skip, n) = if f < 1 {
+            (0, count(n.saturating_add(f - 1)))
+        } else {
+            (count(f - 1), count(n))
+        };
+        let result: String = s.chars().skip(skip).take(n).collect();
+        Value::String(Rc::new(result))
+    }
+}
+/////////////////////////////
+/// Compile call to a string function with one string argument.
+fn c_str_map(b: &Block, args: &mut [Expr], f: fn(&str) -> String) -> CExpPtr<Value> {
+    check_types(b, args, &[DataKind::String]);
+    let s = c_value(b, &mut args[0]);
+    Box::new(StrMap { s, f })
+}
+/// Compile call to UPPER.
+fn c_upper(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    c_str_map(b, args, str::to_uppercase)
+}
+/// Compile call to LOWER.
+fn c_lower(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    c_str_map(b, args, str::to_lowercase)
+}
+/// Compile call to TRIM.
+fn c_trim(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    c_str_map(b, args, |s| s.trim().to_string())
+}
+/// Compile call to LTRIM.
+fn c_ltrim(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    c_str_map(b, args, |s| s.trim_start().to_string())
+}
+/// Compile call to RTRIM.
+fn c_rtrim(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    c_str_map(b, args, |s| s.trim_end().to_string())
+}
+/// Compile call to REVERSE.
+fn c_reverse(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    c_str_map(b, args, |s| s.chars().rev().collect())
+}
+struct StrMap {
+    s: CExpPtr<Value>,
+    f: fn(&str) -> String,
+}
+impl CExp<Value> for StrMap {
+    fn eval(&self, ee: &mut EvalEnv, d: &[u8]) -> Value {
+        let s = self.s.eval(ee, d).str();
+        Value::String(Rc::new((self.f)(&s)))
+    }
+}
+/////////////////////////////
+/// Compile call to LEFT.
+fn c_left(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_types(b, args, &[DataKind::String, DataKind::Int]);
+    let s = c_value(b, &mut args[0]);
+    let n = c_int(b, &mut args[1]);
+    Box::new(Left { s, n })
+}
+struct Left {
+    s: CExpPtr<Value>,
+    n: CExpPtr<i64>,
+}
+impl CExp<Value> for Left {
+    fn eval(&self, ee: &mut EvalEnv, d: &[u8]) -> Value {
+        let s = self.s.eval(ee, d).str();
+        let n = count(self.n.eval(ee, d));
+        let end = s.char_indices().nth(n).map_or(s.len(), |(x, _)| x);
+        Value::String(Rc::new(s[..end].to_string()))
+    }
+}
+/////////////////////////////
+/// Compile call to RIGHT.
+fn c_right(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_types(b, args, &[DataKind::String, DataKind::Int]);
+    let s = c_value(b, &mut args[0]);
+    let n = c_int(b, &mut args[1]);
+    Box::new(Right { s, n })
+}
+struct Right {
+    s: CExpPtr<Value>,
+    n: CExpPtr<i64>,
+}
+impl CExp<Value> for Right {
+    fn eval(&self, ee: &mut EvalEnv, d: &[u8]) -> Value {
+        let s = self.s.eval(ee, d).str();
+        let n = count(self.n.eval(ee, d));
+        let start = match n {
+            0 => s.len(),
+            n => s.char_indices().rev().nth(n - 1).map_or(0, |(x, _)| x),
+        };
+        Value::String(Rc::new(s[start..].to_string()))
+    }
+}
+/////////////////////////////
+/// Compile call to INDEXOF.
+fn c_index_of(b: &Block, args: &mut [Expr]) -> CExpPtr<i64> {
+    check_types(b, args, &[DataKind::String, DataKind::String]);
+    let s = c_value(b, &mut args[0]);
+    let pat = c_value(b, &mut args[1]);
+    Box::new(IndexOf { s, pat })
+}
+struct IndexOf {
+    s: CExpPtr<Value>,
+    pat: CExpPtr<Value>,
+}
+impl CExp<i64> for IndexOf {
+    fn eval(&self, ee: &mut EvalEnv, d: &[u8]) -> i64 {
+        let s = self.s.eval(ee, d).str();
+        let pat = self.pat.eval(ee, d).str();
+        match s.find(pat.as_str()) {
+            Some(x) => s[..x].chars().count() as i64 + 1,
+            None => 0,
+        }
+    }
+}
+/////////////////////////////
+/// Compile call to PADLEFT.
+fn c_pad_left(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_types(
+        b,
+        args,
+        &[DataKind::String, DataKind::Int, DataKind::String],
+    );
+    let s = c_value(b, &mut args[0]);
+    let n = c_int(b, &mut args[1]);
+    let pad = c_value(b, &mut args[2]);
+    Box::new(PadLeft { s, n, pad })
+}
+struct PadLeft {
+    s: CExpPtr<Value>,
+    n: CExpPtr<i64>,
+    pad: CExpPtr<Value>,
+}
+impl CExp<Value> for PadLeft {
+    fn eval(&self, ee: &mut EvalEnv, d: &[u8]) -> Value {
+        let s = self.s.eval(ee, d).str();
+        let n = count(self.n.eval(ee, d));
+        let pad = self.pad.eval(ee, d).str();
+        let len = s.chars().count();
+        if len >= n || pad.is_empty() {
+            return Value::String(s);
+        }
+        let pad_bytes = (n - len).saturating_mul(pad.len()) / pad.chars().count();
+        check_size("PADLEFT", s.len().saturating_add(pad_bytes));
+        let mut result: String = pad.chars().cycle().take(n - len).collect();
+        result.push_str(&s);
+        Value::String(Rc::new(result))
+    }
+}
+/////////////////////////////
+/// Compile call to SPLITPART.
+fn c_split_part(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_types(
+        b,
+        args,
+        &[DataKind::String, DataKind::String, DataKind::Int],
+    );
+    let s = c_value(b, &mut args[0]);
+    let delim = c_value(b, &mut args[1]);
+    let n = c_int(b, &mut args[2]);
+    Box::new(SplitPart { s, delim, n })
+}
+struct SplitPart {
+    s: CExpPtr<Value>,
+    delim: CExpPtr<Value>,
+    n: CExpPtr<i64>,
+}
+impl CExp<Value> for SplitPart {
+    fn eval(&self, ee: &mut EvalEnv, d: &[u8]) -> Value {
+        let s = self.s.eval(ee, d).str();
+        let delim = self.delim.eval(ee, d).str();
+        let n = self.n.eval(ee, d);
+        if delim.is_empty() {
+            panic!("SPLITPART delimiter is empty");
+        }
+        let part = match count(n) {
+            0 => "",
+            n => s.split(delim.as_str()).nth(n - 1).unwrap_or(""),
+        };
+        Value::String(Rc::new(part.to_string()))
+    }
+}
+/////////////////////////////
+/// Compile call to REPEAT.
+fn c_repeat(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_types(b, args, &[DataKind::String, DataKind::Int]);
+    let s = c_value(b, &mut args[0]);
+    let n = c_int(b, &mut args[1]);
+    Box::new(Repeat { s, n })
+}
+struct Repeat {
+    s: CExpPtr<Value>,
+    n: CExpPtr<i64>,
+}
+impl CExp<Value> for Repeat {
+    fn eval(&self, ee: &mut EvalEnv, d: &[u8]) -> Value {
+        let s = self.s.eval(ee, d).str();
+        let n = count(self.n.eval(ee, d));
+        check_size("REPEAT", s.len().saturating_mul(n));
+        Value::String(Rc::new(s.repeat(n)))
+    }
+}
+
 /////////////////////////////
 /// Compile call to BINSUBSTRING.
 fn c_binsubstring(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
@@ -510,3 +916,132 @@ impl CExp<Value> for Bintostr {
         }
     }
 }
+/////////////////////////////
+/// Regular expression argument, compiled once if it is constant.
+#[cfg(feature = "regex")]
+enum RegexArg {
+    Fixed(Rc<regex::Regex>),
+    /// The most recently compiled pattern is kept.
+    Dynamic(
+        CExpPtr<Value>,
+        RefCell<Option<(Rc<String>, Rc<regex::Regex>)>>,
+    ),
+}
+#[cfg(feature = "regex")]
+impl RegexArg {
+    fn new(b: &Block, e: &mut Expr) -> Self {
+        if let ExprIs::Const(Value::String(s)) = &e.exp {
+            Self::Fixed(Rc::new(compile_regex(s)))
+        } else {
+            Self::Dynamic(c_value(b, e), RefCell::new(None))
+        }
+    }
+    fn get(&self, ee: &mut EvalEnv, d: &[u8]) -> Rc<regex::Regex> {
+        match self {
+            Self::Fixed(re) => re.clone(),
+            Self::Dynamic(ce, last) => {
+                let s = ce.eval(ee, d).str();
+                let mut last = last.borrow_mut();
+                match &*last {
+                    Some((ls, re)) if *ls == s => re.clone(),
+                    _ => {
+                        let re = Rc::new(compile_regex(&s));
+                        *last = Some((s, re.clone()));
+                        re
+                    }
+                }
+            }
+        }
+    }
+}
+#[cfg(feature = "regex")]
+fn compile_regex(s: &str) -> regex::Regex {
+    regex::Regex::new(s).unwrap_or_else(|_| panic!("invalid regular expression '{}'", s))
+}
+/////////////////////////////
+/// Compile call to REGEXMATCH.
+#[cfg(feature = "regex")]
+fn c_regex_match(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_types(b, args, &[DataKind::String, DataKind::String]);
+    let s = c_value(b, &mut args[0]);
+    let re = RegexArg::new(b, &mut args[1]);
+    Box::new(RegexMatch { s, re })
+}
+#[cfg(feature = "regex")]
+struct RegexMatch {
+    s: CExpPtr<Value>,
+    re: RegexArg,
+}
+#[cfg(feature = "regex")]
+impl CExp<Value> for RegexMatch {
+    fn eval(&self, ee: &mut EvalEnv, d: &[u8]) -> Value {
+        let s = self.s.eval(ee, d).str();
+        Value::Bool(self.re.get(ee, d).is_match(&s))
+    }
+}
+/////////////////////////////
+/// Compile call to REGEXREPLACE.
+#[cfg(feature = "regex")]
+fn c_regex_replace(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_types(
+        b,
+        args,
+        &[DataKind::String, DataKind::String, DataKind::String],
+    );
+    let s = c_value(b, &mut args[0]);
+    let re = RegexArg::new(b, &mut args[1]);
+    let rep = c_value(b, &mut args[2]);
+    Box::new(RegexReplace { s, re, rep })
+}
+#[cfg(feature = "regex")]
+struct RegexReplace {
+    s: CExpPtr<Value>,
+    re: RegexArg,
+    rep: CExpPtr<Value>,
+}
+#[cfg(feature = "regex")]
+impl CExp<Value> for RegexReplace {
+    fn eval(&self, ee: &mut EvalEnv, d: &[u8]) -> Value {
+        let s = self.s.eval(ee, d).str();
+        let rep = self.rep.eval(ee, d).str();
+        let result = self
+            .re
+            .get(ee, d)
+            .replace_all(&s, rep.as_str())
+            .into_owned();
+        Value::String(Rc::new(result))
+    }
+}
+/////////////////////////////
+/// Compile call to REGEXEXTRACT.
+#[cfg(feature = "regex")]
+fn c_regex_extract(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_types(
+        b,
+        args,
+        &[DataKind::String, DataKind::String, DataKind::Int],
+    );
+    let s = c_value(b, &mut args[0]);
+    let re = RegexArg::new(b, &mut args[1]);
+    let group = c_int(b, &mut args[2]);
+    Box::new(RegexExtract { s, re, group })
+}
+#[cfg(feature = "regex")]
+struct RegexExtract {
+    s: CExpPtr<Value>,
+    re: RegexArg,
+    group: CExpPtr<i64>,
+}
+#[cfg(feature = "regex")]
+impl CExp<Value> for RegexExtract {
+    fn eval(&self, ee: &mut EvalEnv, d: &[u8]) -> Value {
+        let s = self.s.eval(ee, d).str();
+        let group = self.group.eval(ee, d);
+        let re = self.re.get(ee, d);
+        let result = re
+            .captures(&s)
+            .and_then(|c| c.get(usize::try_from(group).ok()?))
+            .map_or("", |m| m.as_str());
+        Value::String(Rc::new(result.to_string()))
+    }
+}
@@ -0,0 +1,131 @@
+//! CAST( x AS type ) and TRY_CAST( x AS type ) : convert a value to another type.
+//!
+//! A string is parsed ( leading and trailing spaces are ignored ), other values convert to a string as when selected.
+//!
+//! Float and decimal convert to int by truncating toward zero, and to decimal by rounding half away from zero.
+//!
+//! Bool converts to 1 or 0, and from 'true', 'false', '1', '0' or a number ( non-zero is true ).
+//!
+//! A datetime converts to and from int and float as microseconds since the epoch.
+//!
+//! Binary converts to and from string as UTF-8, to and from bool as 1 byte,
+//! and to and from int, float, datetime and decimal as 8 bytes little-endian ( a decimal as its scaled value ).
+//!
+//! CAST is an error if the value cannot be converted, TRY_CAST gives the default value for the type instead
+//! ( NULL if the type is nullable, e.g. TRY_CAST( s AS int NULL ) ). NULL converts to NULL.
+
+use crate::*;
+
+/// Convert value v of type from to type to, None if it cannot be converted.
+pub fn convert(v: &Value, from: DataType, to: DataType) -> Option<Value> {
+    if let Value::Null = v {
+        return Some(Value::Null);
+    }
+    if data_kind(from) == DataKind::DateTime && data_kind(to) == DataKind::String {
+        return Some(Value::String(Rc::new(datetime::to_iso(v.int()))));
+    }
+    let r = match data_kind(to) {
+        DataKind::String => match v {
+            Value::RcBinary(b) => Value::String(Rc::new(String::from_utf8(b.to_vec()).ok()?)),
+            Value::ArcBinary(b) => Value::String(Rc::new(String::from_utf8(b.to_vec()).ok()?)),
+            _ => Value::String(v.str()),
+        },
+        DataKind::Binary => Value::RcBinary(Rc::new(match v {
+            Value::String(s) => s.as_bytes().to_vec(),
+            Value::Int(x) => x.to_le_bytes().to_vec(),
+            Value::Float(x) => x.to_le_bytes().to_vec(),
+            Value::Decimal(x, _) => x.to_le_bytes().to_vec(),
+            Value::Bool(x) => vec![*x as u8],
+            _ => return Some(v.clone()),
+        })),
+        DataKind::Int | DataKind::DateTime => Value::Int(match v {
+            Value::String(s) if data_kind(to) == DataKind::DateTime => {
+                datetime::try_parse(s).or_else(|| s.trim().parse().ok())?
+            }
+            Value::String(s) => s.trim().parse().ok()?,
+            Value::Int(x) => *x,
+            Value::Float(x) => float_to_int(*x)?,
+            Value::Decimal(x, s) => decimal::trunc(*x, *s),
+            Value::Bool(x) => *x as i64,
+            _ => i64::from_le_bytes(bytes8(v)?),
+        }),
+        DataKind::Float => Value::Float(match v {
+            Value::String(s) => s.trim().parse().ok()?,
+            Value::Int(x) => *x as f64,
+            Value::Float(x) => *x,
+            Value::Decimal(x, s) => decimal::to_float(*x, *s),
+            Value::Bool(x) => *x as i64 as f64,
+            _ => f64::from_le_bytes(bytes8(v)?),
+        }),
+        DataKind::Bool => Value::Bool(match v {
+            Value::String(s) => match s.trim().to_lowercase().as_str() {
+                "true" | "1" => true,
+                "false" | "0" => false,
+                _ => return None,
+            },
+            Value::Int(x) => *x != 0,
+            Value::Float(x) => *x != 0.0,
+            Value::Decimal(x, _) => *x != 0,
+            Value::Bool(x) => *x,
+            _ => match bytes(v) {
+                [x] => *x != 0,
+                _ => return None,
+            },
+        }),
+        DataKind::Decimal => {
+            let (x, s) = match v {
+                Value::String(s) => decimal::parse(s.trim())?,
+                Value::Int(x) => (*x, 0),
+                Value::Decimal(x, s) => (*x, *s),
+                Value::Bool(x) => (*x as i64, 0),
+                Value::Float(x) => {
+                    let s = data_scale(to) as u8;
+                    (float_to_int((x * 10_f64.powi(s as i32)).round())?, s)
+                }
+                _ => (i64::from_le_bytes(bytes8(v)?), data_scale(to) as u8),
+            };
+            Value::Decimal(decimal::convert(x, s, to)?, data_scale(to) as u8)
+        }
+        DataKind::None => return None,
+    };
+    Some(r)
+}
+
+/// Float truncated to int, None if out of range.
+fn float_to_int(x: f64) -> Option<i64> {
+    // i64::MAX as f64 is 2^63, which is out of range.
+    (x.is_finite() && x >= i64::MIN as f64 && x < i64::MAX as f64).then_some(x as i64)
+}
+
+/// Bytes of a binary value.
+fn bytes(v: &Value) -> &[u8] {
+    match v {
+        Value::RcBinary(b) => b,
+        Value::ArcBinary(b) => b,
+        _ => panic!(),
+    }
+}
+
+/// Bytes of a binary value which must have length 8.
+fn bytes8(v: &Value) -> Option<[u8; 8]> {
+    bytes(v).try_into().ok()
+}
+
+/// CAST or TRY_CAST.
+pub(crate) struct Cast {
+    pub ce: CExpPtr<Value>,
+    pub from: DataType,
+    pub to: DataType,
+    pub try_cast: bool,
+}
+
+impl CExp<Value> for Cast {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> Value {
+        let v = self.ce.eval(e, d);
+        match convert(&v, self.from, self.to) {
+            Some(r) => r,
+            None if self.try_cast => Value::default(self.to),
+            None => panic!("cannot convert '{}' to {:?}", v.str(), data_kind(self.to)),
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{get_bytes, panic, util, CExp, CExpPtr, EvalEnv, Function, Rc, Value};
+use crate::{
+    decimal, get_bytes, panic, util, CExp, CExpPtr, DataType, EvalEnv, Function, Ordering, Rc,
+    Token, Value,
+};
 
 /// Function call.
 pub(crate) struct Call {
@@ -422,10 +425,7 @@ pub(crate) struct ValToInt {
 
 impl CExp<i64> for ValToInt {
     fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> i64 {
-        if let Value::Int(x) = self.ce.eval(e, d) {
-            return x;
-        }
-        panic!();
+        self.ce.eval(e, d).int()
     }
 }
 
@@ -435,10 +435,7 @@ pub(crate) struct ValToFloat {
 
 impl CExp<f64> for ValToFloat {
     fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> f64 {
-        if let Value::Float(x) = self.ce.eval(e, d) {
-            return x;
-        }
-        panic!();
+        self.ce.eval(e, d).float()
     }
 }
 
@@ -483,3 +480,322 @@ impl CExp<Value> for BoolToVal {
         Value::Bool(self.ce.eval(e, d))
     }
 }
+
+/// Column of a derived table. The record holds the row number at off, the rows are held in a local variable.
+/// In a JOIN missing is the offset of the byte which is set for a LEFT JOIN with no matching row ( or usize::MAX ).
+pub(crate) struct DerivedColumn {
+    pub local: usize,
+    pub col: usize,
+    pub off: usize,
+    pub missing: usize,
+}
+
+impl CExp<Value> for DerivedColumn {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> Value {
+        if self.missing != usize::MAX && d[self.missing] != 0 {
+            return Value::Null;
+        }
+        let ix = util::getu64(d, self.off) as usize;
+        if let Value::ForSort(fs) = &e.stack[e.bp + self.local] {
+            fs.borrow().rows[ix][self.col].clone()
+        } else {
+            panic!()
+        }
+    }
+}
+
+pub(crate) struct ColumnNull {
+    pub off: usize,
+    pub typ: DataType,
+    pub bit: usize,
+}
+
+impl CExp<Value> for ColumnNull {
+    fn eval(&self, ee: &mut EvalEnv, data: &[u8]) -> Value {
+        if data[self.bit / 8] & (1 << (self.bit % 8)) != 0 {
+            Value::Null
+        } else {
+            Value::load(&ee.db, self.typ, data, self.off).0
+        }
+    }
+}
+
+pub(crate) struct IsNull {
+    pub ce: CExpPtr<Value>,
+}
+
+impl CExp<bool> for IsNull {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> bool {
+        self.ce.eval(e, d).is_null()
+    }
+}
+
+/// IN list test. A NULL operand gives false.
+pub(crate) struct InList {
+    pub ce: CExpPtr<Value>,
+    pub list: Vec<CExpPtr<Value>>,
+}
+
+impl CExp<bool> for InList {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> bool {
+        let v = self.ce.eval(e, d);
+        if v.is_null() {
+            return false;
+        }
+        for le in &self.list {
+            if le.eval(e, d) == v {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Element of a LIKE pattern.
+#[derive(PartialEq, Eq)]
+enum LikeElem {
+    /// Literal character.
+    Char(char),
+    /// _ matches any single character.
+    One,
+    /// % matches any sequence of characters.
+    Any,
+}
+
+/// Compiled LIKE pattern : literal prefix followed by any remaining elements.
+pub(crate) struct LikeMatcher {
+    pub prefix: String,
+    rest: Vec<LikeElem>,
+}
+
+impl LikeMatcher {
+    /// Compile pattern with optional escape character.
+    pub fn new(pattern: &str, escape: Option<char>) -> Self {
+        let mut prefix = String::new();
+        let mut rest = Vec::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            let elem = if Some(c) == escape {
+                match chars.next() {
+                    Some(n) if n == '%' || n == '_' || Some(n) == escape => LikeElem::Char(n),
+                    _ => panic!("invalid escape in LIKE pattern"),
+                }
+            } else {
+                match c {
+                    '%' => LikeElem::Any,
+                    '_' => LikeElem::One,
+                    c => LikeElem::Char(c),
+                }
+            };
+            match elem {
+                LikeElem::Char(c) if rest.is_empty() => prefix.push(c),
+                LikeElem::Any if rest.last() == Some(&LikeElem::Any) => {}
+                elem => rest.push(elem),
+            }
+        }
+        Self { prefix, rest }
+    }
+
+    /// Does s match the pattern.
+    pub fn matches(&self, s: &str) -> bool {
+        let Some(s) = s.strip_prefix(&self.prefix) else {
+            return false;
+        };
+        match &self.rest[..] {
+            [] => s.is_empty(),
+            [LikeElem::Any] => true,
+            p => {
+                let s: Vec<char> = s.chars().collect();
+                let (mut i, mut j) = (0, 0);
+                // Pattern position after the last %, and the string position it is matched from.
+                let mut star = None;
+                while i < s.len() {
+                    match p.get(j) {
+                        Some(LikeElem::One) => (i, j) = (i + 1, j + 1),
+                        Some(LikeElem::Char(c)) if *c == s[i] => (i, j) = (i + 1, j + 1),
+                        Some(LikeElem::Any) => {
+                            j += 1;
+                            star = Some((j, i));
+                        }
+                        _ => {
+                            // Backtrack : the last % matches one more character.
+                            let Some((sj, si)) = star else {
+                                return false;
+                            };
+                            (i, j) = (si + 1, sj);
+                            star = Some((sj, si + 1));
+                        }
+                    }
+                }
+                p[j..].iter().all(|e| *e == LikeElem::Any)
+            }
+        }
+    }
+}
+
+/// LIKE pattern, compiled once if it is constant.
+pub(crate) enum LikePattern {
+    Fixed(LikeMatcher),
+    Dynamic(CExpPtr<Value>, Option<char>),
+}
+
+/// LIKE ( or NOT LIKE ) test. A NULL operand or pattern gives false.
+pub(crate) struct Like {
+    pub ce: CExpPtr<Value>,
+    pub pattern: LikePattern,
+    pub not: bool,
+}
+
+impl CExp<bool> for Like {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> bool {
+        let v = self.ce.eval(e, d);
+        if v.is_null() {
+            return false;
+        }
+        let matched = match &self.pattern {
+            LikePattern::Fixed(m) => m.matches(&v.str()),
+            LikePattern::Dynamic(pe, escape) => {
+                let p = pe.eval(e, d);
+                if p.is_null() {
+                    return false;
+                }
+                LikeMatcher::new(&p.str(), *escape).matches(&v.str())
+            }
+        };
+        matched != self.not
+    }
+}
+
+/// Converts NULL to false ( for WHERE, IF etc. ).
+pub(crate) struct NullToBool {
+    pub ce: CExpPtr<Value>,
+}
+
+impl CExp<bool> for NullToBool {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> bool {
+        match self.ce.eval(e, d) {
+            Value::Bool(x) => x,
+            _ => false,
+        }
+    }
+}
+
+/// Binary operation where either operand may be NULL, the result is NULL if either operand is NULL.
+pub(crate) struct NullBinary {
+    pub op: Token,
+    pub c1: CExpPtr<Value>,
+    pub c2: CExpPtr<Value>,
+}
+
+impl CExp<Value> for NullBinary {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> Value {
+        let v1 = self.c1.eval(e, d);
+        let v2 = self.c2.eval(e, d);
+        if v1.is_null() || v2.is_null() {
+            return Value::Null;
+        }
+        let cf = || v1.cmp(&v2);
+        match self.op {
+            Token::Equal => Value::Bool(cf() == Ordering::Equal),
+            Token::NotEqual => Value::Bool(cf() != Ordering::Equal),
+            Token::Less => Value::Bool(cf() == Ordering::Less),
+            Token::Greater => Value::Bool(cf() == Ordering::Greater),
+            Token::LessEqual => Value::Bool(cf() != Ordering::Greater),
+            Token::GreaterEqual => Value::Bool(cf() != Ordering::Less),
+            Token::VBar => {
+                if let Value::RcBinary(_) = v1 {
+                    let (b1, b2) = (v1.bin(), v2.bin());
+                    Value::RcBinary(Rc::new([&b1[..], &b2[..]].concat()))
+                } else {
+                    Value::String(Rc::new(format!("{}{}", v1.str(), v2.str())))
+                }
+            }
+            op => match (v1, v2) {
+                (Value::Int(x1), Value::Int(x2)) => Value::Int(match op {
+                    Token::Plus => x1 + x2,
+                    Token::Minus => x1 - x2,
+                    Token::Times => x1 * x2,
+                    Token::Divide => x1 / x2,
+                    Token::Percent => x1 % x2,
+                    _ => panic!(),
+                }),
+                (Value::Float(x1), Value::Float(x2)) => Value::Float(match op {
+                    Token::Plus => x1 + x2,
+                    Token::Minus => x1 - x2,
+                    Token::Times => x1 * x2,
+                    Token::Divide => x1 / x2,
+                    Token::Percent => x1 % x2,
+                    _ => panic!(),
+                }),
+                (v1, v2) => decimal::arithmetic(op, &v1, &v2),
+            },
+        }
+    }
+}
+
+/// AND where either operand may be NULL ( false if either operand is false, otherwise NULL if either is NULL ).
+pub(crate) struct NullAnd {
+    pub c1: CExpPtr<Value>,
+    pub c2: CExpPtr<Value>,
+}
+
+impl CExp<Value> for NullAnd {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> Value {
+        let v1 = self.c1.eval(e, d);
+        if let Value::Bool(false) = v1 {
+            return v1;
+        }
+        let v2 = self.c2.eval(e, d);
+        if let Value::Bool(false) = v2 {
+            return v2;
+        }
+        if v1.is_null() {
+            v1
+        } else {
+            v2
+        }
+    }
+}
+
+/// OR where either operand may be NULL ( true if either operand is true, otherwise NULL if either is NULL ).
+pub(crate) struct NullOr {
+    pub c1: CExpPtr<Value>,
+    pub c2: CExpPtr<Value>,
+}
+
+impl CExp<Value> for NullOr {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> Value {
+        let v1 = self.c1.eval(e, d);
+        if let Value::Bool(true) = v1 {
+            return v1;
+        }
+        let v2 = self.c2.eval(e, d);
+        if let Value::Bool(true) = v2 {
+            return v2;
+        }
+        if v1.is_null() {
+            v1
+        } else {
+            v2
+        }
+    }
+}
+
+/// NOT or unary minus where the operand may be NULL.
+pub(crate) struct NullUnary {
+    pub op: Token,
+    pub ce: CExpPtr<Value>,
+}
+
+impl CExp<Value> for NullUnary {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> Value {
+        match (self.op, self.ce.eval(e, d)) {
+            (_, Value::Null) => Value::Null,
+            (Token::Exclamation, Value::Bool(x)) => Value::Bool(!x),
+            (Token::Minus, Value::Int(x)) => Value::Int(-x),
+            (Token::Minus, Value::Float(x)) => Value::Float(-x),
+            (Token::Minus, Value::Decimal(x, s)) => Value::Decimal(-x, s),
+            _ => panic!(),
+        }
+    }
+}
@@ -10,27 +10,59 @@ pub fn c_check(b: &Block, e: &mut Expr) {
     e.is_constant = true;
     match &mut e.exp {
         ExprIs::BuiltinCall(name, args) => {
-            if let Some((dk, _cf)) = b.db.builtins.get(name) {
+            if let Some((dk, cf)) = b.db.builtins.get(name) {
                 e.data_type = *dk as DataType;
-                for pe in args {
+                for pe in args.iter_mut() {
                     c_check(b, pe);
                     if !pe.is_constant {
                         e.is_constant = false;
                     }
                 }
+                // Result has the type of the first argument, nullable if any argument is.
+                if *dk == DataKind::None {
+                    if let Some(a) = args.first() {
+                        e.data_type = a.data_type;
+                    }
+                }
+                if *dk == DataKind::None || matches!(cf, CompileFunc::NullValue(_)) {
+                    for a in args.iter() {
+                        e.data_type |= a.data_type & NULLABLE;
+                    }
+                }
             } else {
                 panic!("unknown function {}", name);
             }
         }
+        ExprIs::Binary(Token::In, b1, b2) => {
+            c_check(b, b1);
+            let k1 = data_kind(b1.data_type);
+            for le in in_list(b2) {
+                c_check(b, le);
+                if !compatible(data_kind(le.data_type), k1) {
+                    panic!("IN list type mismatch")
+                }
+                if !le.is_constant {
+                    e.is_constant = false;
+                }
+            }
+            e.is_constant = e.is_constant && b1.is_constant;
+            e.data_type = BOOL;
+        }
         ExprIs::Binary(op, b1, b2) => {
             c_check(b, b1);
             c_check(b, b2);
             e.is_constant = b1.is_constant && b2.is_constant;
-            let t1 = b1.data_type;
-            let t2 = b2.data_type;
-            if data_kind(t1) != data_kind(t2) && *op != Token::VBar {
+            let (t1, t2) = (b1.data_type, b2.data_type);
+            let (k1, k2) = (data_kind(t1), data_kind(t2));
+            if !compatible(k1, k2) && *op != Token::VBar {
                 panic!("binary op type mismatch")
             }
+            // NULL literal has DataKind::None and takes the kind of the other operand.
+            let t = if k1 == DataKind::None || k2 == DataKind::Decimal {
+                t2
+            } else {
+                t1
+            };
             e.data_type = match op {
                 Token::Less
                 | Token::LessEqual
@@ -39,20 +71,31 @@ pub fn c_check(b: &Block, e: &mut Expr) {
                 | Token::Equal
                 | Token::NotEqual => BOOL,
                 Token::And | Token::Or => {
-                    if t1 != BOOL {
+                    if data_kind(t) != DataKind::Bool {
                         panic!("AND/OR need bool operands")
                     }
                     BOOL
                 }
-                Token::Plus | Token::Times | Token::Minus | Token::Divide | Token::Percent => t1,
+                Token::Plus | Token::Times | Token::Minus | Token::Divide | Token::Percent => {
+                    match data_kind(t) {
+                        DataKind::DateTime => {
+                            panic!("datetime arithmetic needs DATEADD or DATEDIFF")
+                        }
+                        DataKind::Decimal => decimal::result_type(*op, t1, t2),
+                        _ => t,
+                    }
+                }
                 Token::VBar => {
-                    if data_kind(t1) == DataKind::Binary {
+                    if data_kind(t) == DataKind::Binary {
                         BINARY
                     } else {
                         STRING
                     }
                 }
                 _ => panic!(),
+            };
+            if nullable(t1) || nullable(t2) {
+                e.data_type |= NULLABLE;
             }
         }
         ExprIs::Local(x) => {
@@ -63,9 +106,11 @@ pub fn c_check(b: &Block, e: &mut Expr) {
                 Value::Bool(_) => BOOL,
                 Value::Int(_) => INT,
                 Value::Float(_) => DOUBLE,
+                Value::Decimal(_, s) => decimal_type(decimal::MAX_PRECISION, s as usize),
                 Value::String(_) => STRING,
                 Value::RcBinary(_) => BINARY,
                 Value::ArcBinary(_) => BINARY,
+                Value::Null => NONE | NULLABLE,
                 _ => NONE,
             }
         }
@@ -84,16 +129,45 @@ pub fn c_check(b: &Block, e: &mut Expr) {
                 if !t.is_constant {
                     e.is_constant = false;
                 }
-                if data_kind(e.data_type) != data_kind(t.data_type) {
+                let (k, tk) = (data_kind(e.data_type), data_kind(t.data_type));
+                if k == DataKind::None {
+                    e.data_type = t.data_type | (e.data_type & NULLABLE);
+                } else if !compatible(k, tk) {
                     panic!("CASE branch type mismatch");
                 }
+                e.data_type |= t.data_type & NULLABLE;
             }
         }
         ExprIs::Not(x) => {
+            c_check(b, x);
+            e.is_constant = x.is_constant;
+            e.data_type = BOOL | (x.data_type & NULLABLE);
+        }
+        ExprIs::IsNull(x) => {
             c_check(b, x);
             e.is_constant = x.is_constant;
             e.data_type = BOOL;
         }
+        ExprIs::Like(x, p, _, _) => {
+            c_check(b, x);
+            c_check(b, p);
+            if data_kind(x.data_type) != DataKind::String
+                || data_kind(p.data_type) != DataKind::String
+            {
+                panic!("LIKE needs string operands")
+            }
+            e.is_constant = x.is_constant && p.is_constant;
+            e.data_type = BOOL;
+        }
+        ExprIs::Cast(x, t, _) => {
+            c_check(b, x);
+            e.is_constant = x.is_constant;
+            // NULL literal has DataKind::None.
+            e.data_type = *t | (x.data_type & NULLABLE);
+            if data_kind(x.data_type) == DataKind::None {
+                e.data_type |= NULLABLE;
+            }
+        }
         ExprIs::Minus(x) => {
             c_check(b, x);
             e.is_constant = x.is_constant;
@@ -111,10 +185,7 @@ pub fn c_check(b: &Block, e: &mut Expr) {
             }
             for (i, a) in parms.iter_mut().enumerate() {
                 c_check(b, a);
-                let (t, et) = (data_kind(a.data_type), data_kind(f.local_typ[i]));
-                if t != et {
-                    panic!("function param type mismatch expected {:?} got {:?}", et, t);
-                }
+                c_check_assign(a.data_type, f.local_typ.borrow()[i], "function param");
                 if !a.is_constant {
                     e.is_constant = false;
                 }
@@ -133,25 +204,36 @@ pub fn c_check(b: &Block, e: &mut Expr) {
 
 /// Compile a call to a builtin function that returns a Value.
 fn c_builtin_value(b: &Block, name: &str, args: &mut [Expr]) -> CExpPtr<Value> {
-    if let Some((_dk, CompileFunc::Value(cf))) = b.db.builtins.get(name) {
-        return cf(b, args);
+    match b.db.builtins.get(name) {
+        Some((_dk, CompileFunc::Value(cf) | CompileFunc::NullValue(cf))) => cf(b, args),
+        _ => panic!(),
     }
-    panic!()
 }
 
 /// Compile an expression.
 pub fn c_value(b: &Block, e: &mut Expr) -> CExpPtr<Value> {
-    match b.kind(e) {
+    let k = b.kind(e);
+    if nullable(e.data_type) {
+        return c_nullable(b, e);
+    }
+    match k {
         DataKind::Bool => Box::new(cexp::BoolToVal { ce: c_bool(b, e) }),
-        DataKind::Int => Box::new(cexp::IntToVal { ce: c_int(b, e) }),
+        DataKind::Int | DataKind::DateTime => Box::new(cexp::IntToVal { ce: c_int(b, e) }),
         DataKind::Float => Box::new(cexp::FloatToVal { ce: c_float(b, e) }),
         _ => match &mut e.exp {
             ExprIs::ColName(x) => {
+                if let Some(ce) = c_derived_col(b, x) {
+                    return ce;
+                }
                 let (off, typ) = name_to_col(b, x);
                 let size = data_size(typ);
                 match data_kind(typ) {
                     DataKind::String => Box::new(cexp::ColumnString { off, size }),
                     DataKind::Binary => Box::new(cexp::ColumnBinary { off, size }),
+                    DataKind::Decimal => Box::new(decimal::ColumnDecimal {
+                        off,
+                        scale: data_scale(typ) as u8,
+                    }),
                     _ => panic!(),
                 }
             }
@@ -160,8 +242,8 @@ pub fn c_value(b: &Block, e: &mut Expr) -> CExpPtr<Value> {
             }),
             ExprIs::Local(x) => Box::new(cexp::Local { num: *x }),
             ExprIs::Binary(op, b1, b2) => {
-                let c1 = c_value(b, b1);
-                let c2 = c_value(b, b2);
+                let c1 = c_string(b, b1);
+                let c2 = c_string(b, b2);
                 match op {
                     Token::VBar => {
                         if data_kind(b1.data_type) == DataKind::Binary {
@@ -170,24 +252,49 @@ pub fn c_value(b: &Block, e: &mut Expr) -> CExpPtr<Value> {
                             Box::new(cexp::Concat { c1, c2 })
                         }
                     }
+                    _ if k == DataKind::Decimal => {
+                        Box::new(decimal::Arithmetic { op: *op, c1, c2 })
+                    }
                     _ => panic!("invalid operator {:?}", op),
                 }
             }
+            ExprIs::Minus(x) => Box::new(cexp::NullUnary {
+                op: Token::Minus,
+                ce: c_value(b, x),
+            }),
             ExprIs::FuncCall(name, parms) => c_call(b, name, parms),
             ExprIs::Case(list, els) => c_case(b, list, els, c_value),
             ExprIs::BuiltinCall(name, parms) => c_builtin_value(b, name, parms),
+            ExprIs::Cast(x, t, try_cast) => c_cast(b, x, *t, *try_cast),
             _ => panic!(),
         },
     }
 }
 
-/// Compile int expression.
+/// Compile expression for concatenation, a datetime is converted to an ISO 8601 string.
+fn c_string(b: &Block, e: &mut Expr) -> CExpPtr<Value> {
+    if b.kind(e) == DataKind::DateTime {
+        if nullable(e.data_type) {
+            return Box::new(datetime::NullDateTimeStr { ce: c_value(b, e) });
+        }
+        return Box::new(datetime::DateTimeStr { t: c_int(b, e) });
+    }
+    c_value(b, e)
+}
+
+/// Compile int expression ( or datetime ).
 pub fn c_int(b: &Block, e: &mut Expr) -> CExpPtr<i64> {
-    if b.kind(e) != DataKind::Int {
+    if !matches!(b.kind(e), DataKind::Int | DataKind::DateTime) {
         panic!("int type expected")
     }
+    if nullable(e.data_type) {
+        return Box::new(cexp::ValToInt { ce: c_value(b, e) });
+    }
     match &mut e.exp {
         ExprIs::ColName(x) => {
+            if let Some(ce) = c_derived_col(b, x) {
+                return Box::new(cexp::ValToInt { ce });
+            }
             let (off, typ) = name_to_col(b, x);
             let size = data_size(typ);
             match size {
@@ -205,6 +312,9 @@ pub fn c_int(b: &Block, e: &mut Expr) -> CExpPtr<i64> {
             ce: c_call(b, n, a),
         }),
         ExprIs::BuiltinCall(n, a) => c_builtin_int(b, n, a),
+        ExprIs::Cast(x, t, try_cast) => Box::new(cexp::ValToInt {
+            ce: c_cast(b, x, *t, *try_cast),
+        }),
         _ => panic!(),
     }
 }
@@ -214,8 +324,14 @@ pub fn c_float(b: &Block, e: &mut Expr) -> CExpPtr<f64> {
     if b.kind(e) != DataKind::Float {
         panic!("float type expected")
     }
+    if nullable(e.data_type) {
+        return Box::new(cexp::ValToFloat { ce: c_value(b, e) });
+    }
     match &mut e.exp {
         ExprIs::ColName(x) => {
+            if let Some(ce) = c_derived_col(b, x) {
+                return Box::new(cexp::ValToFloat { ce });
+            }
             let (off, typ) = name_to_col(b, x);
             match data_size(typ) {
                 8 => Box::new(cexp::ColumnF64 { off }),
@@ -231,6 +347,9 @@ pub fn c_float(b: &Block, e: &mut Expr) -> CExpPtr<f64> {
             ce: c_call(b, n, a),
         }),
         ExprIs::BuiltinCall(n, a) => c_builtin_float(b, n, a),
+        ExprIs::Cast(x, t, try_cast) => Box::new(cexp::ValToFloat {
+            ce: c_cast(b, x, *t, *try_cast),
+        }),
         _ => panic!(),
     }
 }
@@ -240,13 +359,24 @@ pub fn c_bool(b: &Block, e: &mut Expr) -> CExpPtr<bool> {
     if b.kind(e) != DataKind::Bool {
         panic!("bool type expected")
     }
+    if nullable(e.data_type) {
+        // NULL is treated as false.
+        return Box::new(cexp::NullToBool { ce: c_value(b, e) });
+    }
     match &mut e.exp {
         ExprIs::ColName(x) => {
+            if let Some(ce) = c_derived_col(b, x) {
+                return Box::new(cexp::ValToBool { ce });
+            }
             let (off, _typ) = name_to_col(b, x);
             Box::new(cexp::ColumnBool { off })
         }
         ExprIs::Const(Value::Bool(b)) => Box::new(cexp::Const::<bool> { value: *b }),
         ExprIs::Local(x) => Box::new(cexp::Local { num: *x }),
+        ExprIs::Binary(Token::In, b1, b2) => Box::new(cexp::InList {
+            ce: c_value(b, b1),
+            list: in_list(b2).iter_mut().map(|le| c_value(b, le)).collect(),
+        }),
         ExprIs::Binary(op, b1, b2) => {
             if *op == Token::Or || *op == Token::And {
                 let c1 = c_bool(b, b1);
@@ -257,23 +387,130 @@ pub fn c_bool(b: &Block, e: &mut Expr) -> CExpPtr<bool> {
                     _ => panic!(),
                 }
             } else {
+                if b.kind(b2) == DataKind::Decimal {
+                    return c_compare(b, *op, b1, b2, c_value);
+                }
                 match b.kind(b1) {
                     DataKind::Bool => c_compare(b, *op, b1, b2, c_bool),
-                    DataKind::Int => c_compare(b, *op, b1, b2, c_int),
+                    DataKind::Int | DataKind::DateTime => c_compare(b, *op, b1, b2, c_int),
                     DataKind::Float => c_compare(b, *op, b1, b2, c_float),
                     _ => c_compare(b, *op, b1, b2, c_value),
                 }
             }
         }
         ExprIs::Not(x) => Box::new(cexp::Not { ce: c_bool(b, x) }),
+        ExprIs::IsNull(x) => Box::new(cexp::IsNull { ce: c_value(b, x) }),
+        ExprIs::Like(x, p, escape, not) => {
+            let pattern = if let ExprIs::Const(Value::String(ps)) = &p.exp {
+                cexp::LikePattern::Fixed(cexp::LikeMatcher::new(ps, *escape))
+            } else {
+                cexp::LikePattern::Dynamic(c_value(b, p), *escape)
+            };
+            Box::new(cexp::Like {
+                ce: c_value(b, x),
+                pattern,
+                not: *not,
+            })
+        }
         ExprIs::FuncCall(name, parms) => Box::new(cexp::ValToBool {
             ce: c_call(b, name, parms),
         }),
+        ExprIs::BuiltinCall(name, parms) => Box::new(cexp::ValToBool {
+            ce: c_builtin_value(b, name, parms),
+        }),
         ExprIs::Case(list, els) => c_case(b, list, els, c_bool),
+        ExprIs::Cast(x, t, try_cast) => Box::new(cexp::ValToBool {
+            ce: c_cast(b, x, *t, *try_cast),
+        }),
         _ => panic!(),
     }
 }
 
+/// Get the list of expressions which are the right operand of IN.
+pub fn in_list(e: &mut Expr) -> &mut [Expr] {
+    if !matches!(e.exp, ExprIs::List(_)) {
+        return std::slice::from_mut(e);
+    }
+    match &mut e.exp {
+        ExprIs::List(list) => list,
+        _ => panic!(),
+    }
+}
+
+/// Compile expression of nullable type.
+fn c_nullable(b: &Block, e: &mut Expr) -> CExpPtr<Value> {
+    match &mut e.exp {
+        ExprIs::ColName(x) => {
+            if let Some(ce) = c_derived_col(b, x) {
+                return ce;
+            }
+            let (_, _, off, typ, bit) = lookup_col(b, x).unwrap();
+            Box::new(cexp::ColumnNull { off, typ, bit })
+        }
+        ExprIs::Const(x) => Box::new(cexp::Const {
+            value: (*x).clone(),
+        }),
+        ExprIs::Local(x) => Box::new(cexp::Local { num: *x }),
+        ExprIs::Binary(op, b1, b2) => {
+            let (c1, c2) = if *op == Token::VBar {
+                (c_string(b, b1), c_string(b, b2))
+            } else {
+                (c_value(b, b1), c_value(b, b2))
+            };
+            match op {
+                Token::And => Box::new(cexp::NullAnd { c1, c2 }),
+                Token::Or => Box::new(cexp::NullOr { c1, c2 }),
+                _ => Box::new(cexp::NullBinary { op: *op, c1, c2 }),
+            }
+        }
+        ExprIs::Not(x) => Box::new(cexp::NullUnary {
+            op: Token::Exclamation,
+            ce: c_value(b, x),
+        }),
+        ExprIs::Minus(x) => Box::new(cexp::NullUnary {
+            op: Token::Minus,
+            ce: c_value(b, x),
+        }),
+        ExprIs::Case(list, els) => c_case(b, list, els, c_value),
+        ExprIs::FuncCall(name, parms) => c_call(b, name, parms),
+        ExprIs::BuiltinCall(name, parms) => c_builtin_value(b, name, parms),
+        ExprIs::Cast(x, t, try_cast) => c_cast(b, x, *t, *try_cast),
+        _ => panic!(),
+    }
+}
+
+/// Compile CAST or TRY_CAST.
+fn c_cast(b: &Block, x: &mut Expr, t: DataType, try_cast: bool) -> CExpPtr<Value> {
+    Box::new(cast::Cast {
+        ce: c_value(b, x),
+        from: x.data_type,
+        to: t,
+        try_cast,
+    })
+}
+
+/// Can operands of the specified kinds be compared or combined ( NULL has DataKind::None, int mixes with decimal ).
+fn compatible(k1: DataKind, k2: DataKind) -> bool {
+    k1 == k2
+        || k1 == DataKind::None
+        || k2 == DataKind::None
+        || matches!(
+            (k1, k2),
+            (DataKind::Int, DataKind::Decimal) | (DataKind::Decimal, DataKind::Int)
+        )
+}
+
+/// Check an expression can be assigned to a variable ( or parameter ) of type t.
+fn c_check_assign(et: DataType, t: DataType, what: &str) {
+    let (ek, k) = (data_kind(et), data_kind(t));
+    if ek != k && ek != DataKind::None && !(ek == DataKind::Int && k == DataKind::Decimal) {
+        panic!("{} type mismatch expected {:?} got {:?}", what, k, ek);
+    }
+    if nullable(et) && !nullable(t) {
+        panic!("{} is not nullable", what);
+    }
+}
+
 /// Compile arithmetic.
 fn c_arithmetic<T>(
     b: &Block,
@@ -348,18 +585,24 @@ where
 
 /// Compile a call to a builtin function that returns an integer.
 fn c_builtin_int(b: &Block, name: &str, args: &mut [Expr]) -> CExpPtr<i64> {
-    if let Some((_dk, CompileFunc::Int(cf))) = b.db.builtins.get(name) {
-        return cf(b, args);
+    match b.db.builtins.get(name) {
+        Some((_dk, CompileFunc::Int(cf))) => cf(b, args),
+        Some((_dk, CompileFunc::Value(cf) | CompileFunc::NullValue(cf))) => {
+            Box::new(cexp::ValToInt { ce: cf(b, args) })
+        }
+        _ => panic!(),
     }
-    panic!()
 }
 
 /// Compile a call to a builtin function that returns a float.
 fn c_builtin_float(b: &Block, name: &str, args: &mut [Expr]) -> CExpPtr<f64> {
-    if let Some((_dk, CompileFunc::Float(cf))) = b.db.builtins.get(name) {
-        return cf(b, args);
+    match b.db.builtins.get(name) {
+        Some((_dk, CompileFunc::Float(cf))) => cf(b, args),
+        Some((_dk, CompileFunc::Value(cf) | CompileFunc::NullValue(cf))) => {
+            Box::new(cexp::ValToFloat { ce: cf(b, args) })
+        }
+        _ => panic!(),
     }
-    panic!()
 }
 
 /// Compile UPDATE statement.
@@ -381,7 +624,7 @@ pub fn c_update(
             panic!("update column name not found");
         }
     }
-    let (w, index_from) = c_where(b, Some(t), wher);
+    let (w, index_from, _) = c_where(b, Some(t), wher, None);
     let mut from = mem::replace(&mut b.from, save);
     if index_from.is_some() {
         from = index_from;
@@ -394,7 +637,7 @@ pub fn c_delete(b: &mut Block, tname: &ObjRef, wher: &mut Option<Expr>) {
     let t = c_table(b, tname);
     let from = Some(CTableExpression::Base(t.clone()));
     let save = mem::replace(&mut b.from, from);
-    let (w, index_from) = c_where(b, Some(t), wher);
+    let (w, index_from, _) = c_where(b, Some(t), wher, None);
     let mut from = mem::replace(&mut b.from, save);
     if index_from.is_some() {
         from = index_from;
@@ -402,6 +645,30 @@ pub fn c_delete(b: &mut Block, tname: &ObjRef, wher: &mut Option<Expr>) {
     b.dop(DO::Delete(from.unwrap(), w));
 }
 
+/// Compile DEFAULT or CHECK constraint, adding it to the table.
+pub fn c_constraint(db: &DB, t: &Rc<Table>, col: &str, kind: ConstraintKind, source: &str) {
+    let mut p = Parser::new(source, db);
+    let mut e = p.parse_constraint();
+    let b = &mut p.b;
+    match kind {
+        ConstraintKind::Default => {
+            let Some(&cnum) = t.info.colmap.get(col) else {
+                panic!("DEFAULT column {} not found", col);
+            };
+            let ce = c_value(b, &mut e);
+            c_check_assign(e.data_type, t.info.typ[cnum], "DEFAULT value");
+            t.defaults.borrow_mut().push((cnum, ce));
+        }
+        ConstraintKind::Check => {
+            b.from = Some(CTableExpression::Base(t.clone()));
+            let ce = c_bool(b, &mut e);
+            t.checks
+                .borrow_mut()
+                .push((Rc::new(source.to_string()), ce));
+        }
+    }
+}
+
 /// Compile FromExpression in Set context.
 pub fn c_set(b: &mut Block, mut se: FromExpression) {
     if se.from.is_none() {
@@ -409,12 +676,9 @@ pub fn c_set(b: &mut Block, mut se: FromExpression) {
         for (i, e) in se.exps.iter_mut().enumerate() {
             // Check data kind of assigned local matches data kind of expression.
             let (lnum, op) = se.assigns[i];
-            let ek = data_kind(b.local_typ[lnum]);
             let ce = c_value(b, e);
-            let ak = b.kind(e);
-            if ek != ak {
-                panic!("cannot assign {:?} to {:?}", ak, ek);
-            }
+            c_check_assign(e.data_type, b.local_typ[lnum], "assigned variable");
+            let ce = decimal::c_assign(ce, e.data_type, b.local_typ[lnum]);
             match op {
                 AssignOp::Assign => b.add(AssignLocal(lnum, ce)),
                 AssignOp::Append => b.add(AppendLocal(lnum, ce)),
@@ -430,27 +694,40 @@ pub fn c_set(b: &mut Block, mut se: FromExpression) {
 
 /// Compile FromExpression to CFromExpression.
 pub fn c_select(b: &mut Block, mut x: FromExpression) -> CFromExpression {
-    let mut from = x.from.map(|mut te| c_te(b, &mut te));
+    let mut join_where = None;
+    let mut from = x.from.map(|te| match *te {
+        TableExpression::Join(mut list) => {
+            if list.len() == 1 {
+                if let Some(cte) = c_view(b, &list[0].name, &list[0].alias) {
+                    return cte;
+                }
+            }
+            let (cte, w) = c_join(b, &mut list);
+            join_where = w;
+            cte
+        }
+        TableExpression::Select(se, alias) => c_derived(b, *se, alias),
+        mut te => c_te(b, &mut te),
+    });
     let table = match &from {
         Some(CTableExpression::Base(t)) => Some(t.clone()),
+        Some(CTableExpression::Join(j)) if j.steps.is_empty() => Some(j.tables[0].1.clone()),
         _ => None,
     };
     // Is the save necessary?
     let save = mem::replace(&mut b.from, from);
     let mut exps = Vec::new();
     for (i, e) in x.exps.iter_mut().enumerate() {
-        exps.push(c_value(b, e));
-        if !x.assigns.is_empty() {
+        let ce = c_value(b, e);
+        if x.assigns.is_empty() {
+            exps.push(ce);
+        } else {
             // Check data kind of assigned local matches data kind of expression.
             let (lnum, _) = x.assigns[i];
-            let ek = data_kind(b.local_typ[lnum]);
-            let ak = data_kind(e.data_type);
-            if ek != ak {
-                panic!("cannot assign {:?} to {:?}", ak, ek);
-            }
+            c_check_assign(e.data_type, b.local_typ[lnum], "assigned variable");
+            exps.push(decimal::c_assign(ce, e.data_type, b.local_typ[lnum]));
         }
     }
-    let (wher, index_from) = c_where(b, table, &mut x.wher);
     let mut orderby = Vec::new();
     let mut desc = Vec::new();
     for (e, a) in &mut x.orderby {
@@ -458,11 +735,29 @@ pub fn c_select(b: &mut Block, mut x: FromExpression) -> CFromExpression {
         orderby.push(e);
         desc.push(*a);
     }
+    let order = index_order(&x.orderby);
+    let order = order.as_ref().map(|(cols, d)| (&cols[..], *d));
+    let (mut wher, index_from, ordered) = c_where(b, table, &mut x.wher, order);
+    if ordered {
+        // Records are fetched in the required order, no sort is needed.
+        orderby.clear();
+        desc.clear();
+    }
+    if let Some(c1) = join_where {
+        wher = Some(if let Some(c2) = wher {
+            Box::new(cexp::And { c1, c2 })
+        } else {
+            c1
+        });
+    }
+    let limit = x.limit.as_mut().map(|e| c_limit(b, e));
+    let offset = x.offset.as_mut().map(|e| c_limit(b, e));
     from = mem::replace(&mut b.from, save);
     if index_from.is_some() {
         from = index_from;
     }
     CFromExpression {
+        types: x.exps.iter().map(|e| e.data_type).collect(),
         colnames: x.colnames,
         assigns: x.assigns,
         exps,
@@ -470,31 +765,148 @@ pub fn c_select(b: &mut Block, mut x: FromExpression) -> CFromExpression {
         wher,
         orderby,
         desc,
+        limit,
+        offset,
+        json: false,
+    }
+}
+
+/// Compile SELECT which supplies the rows for INSERT, checking the values are compatible with the specified columns.
+pub fn c_insert_select(
+    b: &mut Block,
+    t: &Table,
+    cnums: &[usize],
+    se: FromExpression,
+) -> CTableExpression {
+    let cse = c_select(b, se);
+    for (i, cn) in cnums.iter().enumerate() {
+        let typ = if *cn == usize::MAX {
+            INT
+        } else {
+            t.info.typ[*cn]
+        };
+        c_check_assign(cse.types[i], typ, "inserted value");
+    }
+    CTableExpression::Select(Box::new(cse))
+}
+
+/// Compile derived table ( SELECT in FROM clause ).
+fn c_derived(b: &mut Block, se: FromExpression, alias: Option<String>) -> CTableExpression {
+    CTableExpression::Derived(Box::new(c_derived_select(b, se, alias)))
+}
+
+/// Compile the SELECT of a derived table.
+fn c_derived_select(b: &mut Block, se: FromExpression, alias: Option<String>) -> CDerived {
+    let select = c_select(b, se);
+    let local = b.alloc_local();
+    CDerived {
+        alias,
+        local,
+        select,
+    }
+}
+
+/// Compile view as a derived table, if the name is not a table but is a view.
+fn c_view(b: &mut Block, name: &ObjRef, alias: &str) -> Option<CTableExpression> {
+    let d = c_view_select(b, name, alias)?;
+    Some(CTableExpression::Derived(Box::new(d)))
+}
+
+/// Compile the SELECT of a view, if the name is not a table but is a view.
+fn c_view_select(b: &mut Block, name: &ObjRef, alias: &str) -> Option<CDerived> {
+    if b.db.get_table(name).is_some() {
+        return None;
+    }
+    let se = sys::get_view(&b.db, name)?;
+    Some(c_derived_select(b, se, Some(alias.to_string())))
+}
+
+/// Compile reference to a column of a derived table, if the FROM clause is a derived table.
+fn c_derived_col(b: &Block, name: &str) -> Option<CExpPtr<Value>> {
+    match &b.from {
+        Some(CTableExpression::Derived(d)) => {
+            let (col, _) = name_to_colnum(b, name);
+            Some(Box::new(cexp::DerivedColumn {
+                local: d.local,
+                col,
+                off: 0,
+                missing: usize::MAX,
+            }))
+        }
+        Some(CTableExpression::Join(j)) => {
+            let (tnum, col, _, _, _) = lookup_col(b, name)?;
+            let d = j.derived[tnum].as_ref()?;
+            Some(Box::new(cexp::DerivedColumn {
+                local: d.local,
+                col,
+                off: j.tables[tnum].2,
+                missing: j.missing[tnum],
+            }))
+        }
+        _ => None,
     }
 }
 
+/// Is table t of the JOIN in scope a view.
+fn join_derived(b: &Block, t: usize) -> bool {
+    matches!(&b.from, Some(CTableExpression::Join(j)) if j.derived[t].is_some())
+}
+
+/// Compile LIMIT or OFFSET expression.
+fn c_limit(b: &Block, e: &mut Expr) -> CExpPtr<i64> {
+    c_check(b, e);
+    if !e.is_constant {
+        panic!("LIMIT/OFFSET cannot depend on FROM clause");
+    }
+    c_int(b, e)
+}
+
 /// Compile WHERE clause, using table index if possible.
+/// If order is specified ( column numbers and descending flag ) an index which delivers records in that order is used if possible,
+/// in which case the result includes true.
 pub fn c_where(
     b: &Block,
     table: Option<Rc<Table>>,
     wher: &mut Option<Expr>,
-) -> (Option<CExpPtr<bool>>, Option<CTableExpression>) {
+    order: Option<(&[usize], bool)>,
+) -> (Option<CExpPtr<bool>>, Option<CTableExpression>, bool) {
     if let Some(we) = wher {
         if b.kind(we) != DataKind::Bool {
             panic!("WHERE expression must be bool")
         }
+    }
+    if let (Some(table), Some((cols, desc))) = (&table, order) {
+        if let Some((w, te)) = table.order_from(b, wher.as_mut(), cols, desc) {
+            return (w, Some(te), true);
+        }
+    }
+    if let Some(we) = wher {
         if let Some(table) = table {
-            table.index_from(b, we)
+            let (w, te) = table.index_from(b, we);
+            (w, te, false)
         } else {
-            (Some(c_bool(b, we)), None)
+            (Some(c_bool(b, we)), None, false)
         }
     } else {
-        (None, None)
+        (None, None, false)
+    }
+}
+
+/// Get ORDER BY column numbers and direction, if ORDER BY is a list of table columns all in the same direction.
+fn index_order(orderby: &[(Expr, bool)]) -> Option<(Vec<usize>, bool)> {
+    let desc = orderby.first()?.1;
+    let mut cols = Vec::new();
+    for (e, d) in orderby {
+        match &e.exp {
+            ExprIs::ColName(_) if e.col != usize::MAX && *d == desc => cols.push(e.col),
+            _ => return None,
+        }
     }
+    Some((cols, desc))
 }
 
 /// Compile a TableExpression to CTableExpression.
-pub fn c_te(b: &Block, te: &mut TableExpression) -> CTableExpression {
+pub fn c_te(b: &mut Block, te: &mut TableExpression) -> CTableExpression {
     match te {
         TableExpression::Values(x) => {
             let mut cm = Vec::new();
@@ -509,10 +921,183 @@ pub fn c_te(b: &Block, te: &mut TableExpression) -> CTableExpression {
             CTableExpression::Values(cm)
         }
         TableExpression::Base(x) => {
+            if let Some(cte) = c_view(b, x, &x.name) {
+                return cte;
+            }
             let t = c_table(b, x);
             CTableExpression::Base(t)
         }
+        TableExpression::Join(_) => panic!("unexpected JOIN"),
+        TableExpression::Select(_, _) => panic!("unexpected SELECT"),
+    }
+}
+
+/// Compile a JOIN. Result is the compiled join and any ON conditions that have to be checked by the WHERE clause.
+pub fn c_join(b: &mut Block, list: &mut [JoinTable]) -> (CTableExpression, Option<CExpPtr<bool>>) {
+    let mut tables: Vec<(String, Rc<Table>, usize)> = Vec::new();
+    let mut derived = Vec::new();
+    let mut total = 0;
+    for jt in list.iter() {
+        if tables.iter().any(|(alias, _, _)| *alias == jt.alias) {
+            panic!("duplicate table alias {}", jt.alias);
+        }
+        let t = if let Some(d) = c_view_select(b, &jt.name, &jt.alias) {
+            derived.push(Some(Rc::new(d)));
+            // Placeholder table, the record is a row number.
+            let info = Rc::new(ColInfo::empty(jt.name.clone()));
+            Table::new(-1, u64::MAX, 0, info)
+        } else {
+            derived.push(None);
+            c_table(b, &jt.name)
+        };
+        let size = t.info.total;
+        tables.push((jt.alias.clone(), t, total));
+        total += size;
+    }
+    let mut missing = Vec::new();
+    for jt in list.iter() {
+        if jt.outer {
+            missing.push(total);
+            total += 1;
+        } else {
+            missing.push(usize::MAX);
+        }
+    }
+    let save = b.from.take();
+    let mut steps = Vec::new();
+    let mut consts = Vec::new();
+    let mut rest = Vec::new();
+    for (t, jt) in list.iter_mut().enumerate().skip(1) {
+        // Only the preceding tables are in scope for the ON condition.
+        b.from = Some(CTableExpression::Join(Rc::new(CJoin {
+            tables: tables[0..=t].to_vec(),
+            steps: Vec::new(),
+            consts: Vec::new(),
+            derived: derived.clone(),
+            missing: missing.clone(),
+            total,
+        })));
+        let on = jt.on.as_mut().unwrap();
+        if b.kind(on) != DataKind::Bool {
+            panic!("ON expression must be bool");
+        }
+        let mut keys = Vec::new();
+        let mut pred = Vec::new();
+        // Other ON conditions of a LEFT JOIN are checked by the join step, otherwise by the WHERE clause.
+        let other = if jt.outer { &mut pred } else { &mut rest };
+        c_join_cond(b, on, t, &mut keys, &mut consts, other);
+        // Choose Id lookup, index lookup or scan.
+        let table = &tables[t].1;
+        let access = if let Some(pos) = keys.iter().position(|(c, _)| *c == usize::MAX) {
+            JoinAccess::Id(keys.remove(pos).1)
+        } else {
+            let mut kc = SmallSet::default();
+            for (c, _) in &keys {
+                kc.insert(*c);
+            }
+            if let Some((index, n)) = table.best_index(&kc) {
+                let cols = table.ixlist.borrow()[index].cols.clone();
+                let mut ikeys = Vec::new();
+                for col in cols.iter().take(n) {
+                    let pos = keys.iter().position(|(c, _)| c == col).unwrap();
+                    ikeys.push(keys.remove(pos).1);
+                }
+                JoinAccess::Index(index, ikeys)
+            } else {
+                JoinAccess::Scan
+            }
+        };
+        steps.push(JoinStep {
+            access,
+            filter: keys,
+            outer: jt.outer,
+            pred: and_all(pred),
+        });
+    }
+    b.from = save;
+    let wher = and_all(rest);
+    let join = CJoin {
+        tables,
+        steps,
+        consts,
+        derived,
+        missing,
+        total,
+    };
+    (CTableExpression::Join(Rc::new(join)), wher)
+}
+
+/// Combine a list of conditions with AND.
+fn and_all(list: Vec<CExpPtr<bool>>) -> Option<CExpPtr<bool>> {
+    let mut result: Option<CExpPtr<bool>> = None;
+    for c2 in list {
+        result = Some(if let Some(c1) = result {
+            Box::new(cexp::And { c1, c2 })
+        } else {
+            c2
+        });
+    }
+    result
+}
+
+/// Analyse ON condition for joined table t. Equalities between a column of table t and a column of a
+/// preceding table ( or a constant ) are added to keys, other conditions are compiled and added to rest.
+fn c_join_cond(
+    b: &Block,
+    e: &mut Expr,
+    t: usize,
+    keys: &mut Vec<(usize, JoinKey)>,
+    consts: &mut Vec<CExpPtr<Value>>,
+    rest: &mut Vec<CExpPtr<bool>>,
+) {
+    match &mut e.exp {
+        ExprIs::Binary(Token::And, e1, e2) => {
+            c_join_cond(b, e1, t, keys, consts, rest);
+            c_join_cond(b, e2, t, keys, consts, rest);
+            return;
+        }
+        ExprIs::Binary(Token::Equal, e1, e2) => {
+            if let Some(k) = c_join_key(b, e1, e2, t, consts) {
+                keys.push(k);
+                return;
+            }
+            if let Some(k) = c_join_key(b, e2, e1, t, consts) {
+                keys.push(k);
+                return;
+            }
+        }
+        _ => {}
     }
+    rest.push(c_bool(b, e));
+}
+
+/// Check for equality between a column of table t and a column of a preceding table or a constant.
+/// Result is column number and key.
+fn c_join_key(
+    b: &Block,
+    col: &Expr,
+    other: &mut Expr,
+    t: usize,
+    consts: &mut Vec<CExpPtr<Value>>,
+) -> Option<(usize, JoinKey)> {
+    // A NULL key never matches, so a key that may be NULL is not used.
+    if let ExprIs::ColName(name) = &col.exp {
+        let (ct, colnum, _, _, _) = lookup_col(b, name).unwrap();
+        // A view has no Id or index, so its columns are not keys.
+        if ct == t && !nullable(other.data_type) && !join_derived(b, t) {
+            if other.is_constant {
+                consts.push(c_value(b, other));
+                return Some((colnum, JoinKey::Const(consts.len() - 1)));
+            }
+            if let ExprIs::ColName(oname) = &other.exp {
+                let (ot, _, off, typ, _) = lookup_col(b, oname).unwrap();
+                if ot < t && !join_derived(b, ot) {
+                    return Some((colnum, JoinKey::Col(off, typ)));
+                }
+            }
+        }
+    }
+    None
 }
 
 /// Look for named table in database.
@@ -548,6 +1133,8 @@ pub fn c_function(db: &DB, name: &ObjRef) -> Rc<Function> {
                 });
             }
             *r.ilist.borrow_mut() = p.b.ilist;
+            *r.lines.borrow_mut() = Rc::new(p.b.lines);
+            *r.local_typ.borrow_mut() = p.b.local_typ;
         }
         r
     } else {
@@ -555,32 +1142,87 @@ pub fn c_function(db: &DB, name: &ObjRef) -> Rc<Function> {
     }
 }
 
-/// Lookup the column offset and DataType of a named column.
-pub fn name_to_col(b: &Block, name: &str) -> (usize, DataType) {
-    if let Some(CTableExpression::Base(t)) = &b.from {
-        let info = &t.info;
-        if let Some(num) = info.get(name) {
-            let colnum = *num;
-            if colnum == usize::MAX {
-                return (0, INT);
+/// Lookup a column name ( which may be qualified by table name or alias ) in the current FROM clause.
+/// Result is table number, column number ( usize::MAX for Id ), offset of column in record, DataType and
+/// position of null bit in record ( usize::MAX if column is not nullable ).
+fn lookup_col(b: &Block, name: &str) -> Option<(usize, usize, usize, DataType, usize)> {
+    let (qual, cname) = match name.split_once('.') {
+        Some((q, c)) => (Some(q), c),
+        None => (None, name),
+    };
+    let find = |info: &ColInfo, off: usize| match info.get(cname) {
+        Some(&usize::MAX) => Some((usize::MAX, off, INT, usize::MAX)),
+        Some(&colnum) => {
+            let bit = info.nullbit[colnum];
+            let bit = if bit == usize::MAX {
+                bit
+            } else {
+                bit + off * 8
+            };
+            Some((colnum, off + info.off[colnum], info.typ[colnum], bit))
+        }
+        None => None,
+    };
+    match &b.from {
+        Some(CTableExpression::Base(t)) => {
+            if qual.is_some() && qual != Some(&t.info.name.name) {
+                return None;
             }
-            return (info.off[colnum], info.typ[colnum]);
+            let (colnum, off, typ, bit) = find(&t.info, 0)?;
+            Some((0, colnum, off, typ, bit))
         }
+        Some(CTableExpression::Join(j)) => {
+            let mut result = None;
+            for (tnum, (alias, t, toff)) in j.tables.iter().enumerate() {
+                if qual.is_some() && qual != Some(alias) {
+                    continue;
+                }
+                let found = match &j.derived[tnum] {
+                    Some(d) => {
+                        let s = &d.select;
+                        let c = s.colnames.iter().position(|n| n == cname);
+                        c.map(|c| (c, *toff, s.types[c], usize::MAX))
+                    }
+                    None => find(&t.info, *toff),
+                };
+                if let Some((colnum, off, mut typ, mut bit)) = found {
+                    if result.is_some() {
+                        panic!("ambiguous column name '{}'", name);
+                    }
+                    // Column of LEFT JOIN table is NULL if there is no matching record.
+                    let m = j.missing[tnum];
+                    if m != usize::MAX && bit == usize::MAX {
+                        typ |= NULLABLE;
+                        bit = m * 8;
+                    }
+                    result = Some((tnum, colnum, off, typ, bit));
+                }
+            }
+            result
+        }
+        Some(CTableExpression::Derived(d)) => {
+            if qual.is_some() && qual != d.alias.as_deref() {
+                return None;
+            }
+            let colnum = d.select.colnames.iter().position(|n| n == cname)?;
+            Some((0, colnum, 0, d.select.types[colnum], usize::MAX))
+        }
+        _ => None,
+    }
+}
+
+/// Lookup the column offset and DataType of a named column.
+pub fn name_to_col(b: &Block, name: &str) -> (usize, DataType) {
+    if let Some((_, _, off, typ, _)) = lookup_col(b, name) {
+        return (off, typ);
     }
     panic!("Name '{}' not found", name)
 }
 
 /// Lookup the column number and DataType of a named column.
 pub fn name_to_colnum(b: &Block, name: &str) -> (usize, DataType) {
-    if let Some(CTableExpression::Base(t)) = &b.from {
-        let info = &t.info;
-        if let Some(num) = info.get(name) {
-            let colnum = *num;
-            if colnum == usize::MAX {
-                return (colnum, INT);
-            }
-            return (colnum, info.typ[colnum]);
-        }
+    if let Some((_, colnum, _, typ, _)) = lookup_col(b, name) {
+        return (colnum, typ);
     }
     panic!("Name '{}' not found", name)
 }
@@ -590,30 +1232,51 @@ pub fn c_call(b: &Block, name: &ObjRef, parms: &mut Vec<Expr>) -> CExpPtr<Value>
     let fp = c_function(&b.db, name);
     let mut pv = Vec::new();
     let mut pk = Vec::new();
-    for e in parms {
+    for e in parms.iter_mut() {
         pk.push(b.kind(e));
-        let ce = c_value(b, e);
-        pv.push(ce);
+        pv.push(c_value(b, e));
     }
     if fp.return_type == NONE {
         panic!("function with no RETURN type cannot be used in expression");
     }
     b.check_types(&fp, &pk);
+    let pv = pv
+        .into_iter()
+        .zip(parms.iter())
+        .enumerate()
+        .map(|(i, (ce, e))| decimal::c_assign(ce, e.data_type, fp.local_typ.borrow()[i]))
+        .collect();
     Box::new(cexp::Call { fp, pv })
 }
 
+/// Generate code to push an argument for parameter i of function f.
+/// An int or decimal argument for a decimal parameter is converted to the parameter type.
+pub fn push_arg(b: &mut Block, f: &Function, i: usize, e: &mut Expr) -> DataKind {
+    let k = b.kind(e);
+    if i < f.param_count && matches!(k, DataKind::Int | DataKind::Decimal) {
+        let t = f.local_typ.borrow()[i];
+        if data_kind(t) == DataKind::Decimal {
+            let ce = decimal::c_assign(c_value(b, e), e.data_type, t);
+            b.add(PushValue(ce));
+            return k;
+        }
+    }
+    push(b, e)
+}
+
 /// Generate code to evaluate expression and push the value onto the stack.
 pub fn push(b: &mut Block, e: &mut Expr) -> DataKind {
     if b.parse_only {
         return DataKind::None;
     }
     let k = b.kind(e);
+    let null = nullable(e.data_type);
     match &mut e.exp {
         ExprIs::Const(x) => {
             b.add(PushConst((*x).clone()));
         }
-        ExprIs::Binary(_, _, _) => match k {
-            DataKind::Int => {
+        ExprIs::Binary(_, _, _) if !null => match k {
+            DataKind::Int | DataKind::DateTime => {
                 let ce = c_int(b, e);
                 b.add(PushInt(ce));
             }
@@ -654,7 +1317,7 @@ pub fn push(b: &mut Block, e: &mut Expr) -> DataKind {
 pub fn c_for(b: &mut Block, se: FromExpression, start_id: usize, break_id: usize, for_id: usize) {
     let mut cse = c_select(b, se);
     let orderbylen = cse.orderby.len();
-    if orderbylen == 0 {
+    if orderbylen == 0 && cse.limit.is_none() && cse.offset.is_none() {
         b.add(ForInit(for_id, Box::new(cse.from.unwrap())));
         b.set_jump(start_id);
         let info = Box::new(ForNextInfo {
@@ -0,0 +1,434 @@
+//! A datetime is stored as an int, the number of microseconds since 1970-01-01T00:00:00 UTC.
+//!
+//! Builtin functions:
+//!
+//! PARSEDATETIME(s) : parse ISO 8601 string, e.g. '2024-02-29', '2024-02-29T13:45:00.5Z', '2024-02-29 13:45+01:00'.
+//!
+//! DATETIMESTR(d) : ISO 8601 string ( the format used when a datetime is selected or concatenated ).
+//!
+//! FORMATDATETIME(d, fmt) : format using %Y %m %d %H %M %S %f ( microseconds ) %j ( day of year ) %%.
+//!
+//! TODATETIME(n) : datetime from microseconds since the epoch ( e.g. TODATETIME(GLOBAL(0)) ).
+//!
+//! DATEADD(unit, n, d), DATEDIFF(unit, d1, d2), DATETRUNC(unit, d), DATEPART(unit, d).
+//!
+//! Units are 'year', 'month', 'week', 'day', 'hour', 'minute', 'second', 'millisecond' and 'microsecond'.
+//! DATEPART also accepts 'weekday' ( 1 = Monday .. 7 = Sunday ) and 'dayofyear'.
+//!
+//! The result is NULL if an argument is NULL. DATEADD panics if the result is out of range.
+
+use crate::{
+    builtin::{c_null_map, check_types},
+    *,
+};
+
+/// Microseconds in a second.
+const SECOND: i64 = 1_000_000;
+/// Microseconds in a minute.
+const MINUTE: i64 = 60 * SECOND;
+/// Microseconds in an hour.
+const HOUR: i64 = 60 * MINUTE;
+/// Microseconds in a day.
+const DAY: i64 = 24 * HOUR;
+/// Years further than this from 1970 are out of range ( the limit of a datetime is about 292,000 years ).
+const MAX_YEARS: i64 = 300_000;
+
+/// Panic if a datetime calculation overflowed.
+fn checked(x: Option<i64>) -> i64 {
+    x.unwrap_or_else(|| panic!("datetime out of range"))
+}
+
+/// Number of days from 1970-01-01 to the specified date.
+pub fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Date ( year, month, day ) from number of days since 1970-01-01.
+pub fn civil_from_days(z: i64) -> (i64, i64, i64) {
+    let z = z + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    (
+        if m <= 2 {
+            yoe + era * 400 + 1
+        } else {
+            yoe + era * 400
+        },
+        m,
+        d,
+    )
+}
+
+fn is_leap(y: i64) -> bool {
+    y % 4 == 0 && (y % 100 != 0 || y % 400 == 0)
+}
+
+fn days_in_month(y: i64, m: i64) -> i64 {
+    match m {
+        2 if is_leap(y) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Parts of a datetime : year, month, day, hour, minute, second, microsecond.
+struct Parts {
+    y: i64,
+    m: i64,
+    d: i64,
+    hh: i64,
+    mm: i64,
+    ss: i64,
+    us: i64,
+}
+
+impl Parts {
+    fn new(t: i64) -> Self {
+        let (y, m, d) = civil_from_days(t.div_euclid(DAY));
+        let tod = t.rem_euclid(DAY);
+        Self {
+            y,
+            m,
+            d,
+            hh: tod / HOUR,
+            mm: tod % HOUR / MINUTE,
+            ss: tod % MINUTE / SECOND,
+            us: tod % SECOND,
+        }
+    }
+}
+
+/// ISO 8601 string for a datetime. Fractional seconds are only shown if non-zero.
+pub fn to_iso(t: i64) -> String {
+    let p = Parts::new(t);
+    let mut s = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        p.y, p.m, p.d, p.hh, p.mm, p.ss
+    );
+    if p.us != 0 {
+        s += &format!(".{:06}", p.us);
+    }
+    s
+}
+
+/// Format a datetime.
+pub fn format(t: i64, fmt: &str) -> String {
+    let p = Parts::new(t);
+    let mut s = String::new();
+    let mut chars = fmt.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            s.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => s += &format!("{:04}", p.y),
+            Some('m') => s += &format!("{:02}", p.m),
+            Some('d') => s += &format!("{:02}", p.d),
+            Some('H') => s += &format!("{:02}", p.hh),
+            Some('M') => s += &format!("{:02}", p.mm),
+            Some('S') => s += &format!("{:02}", p.ss),
+            Some('f') => s += &format!("{:06}", p.us),
+            Some('j') => s += &format!("{:03}", day_of_year(&p)),
+            Some('%') => s.push('%'),
+            _ => panic!("invalid datetime format '{}'", fmt),
+        }
+    }
+    s
+}
+
+fn day_of_year(p: &Parts) -> i64 {
+    days_from_civil(p.y, p.m, p.d) - days_from_civil(p.y, 1, 1) + 1
+}
+
+/// Parse ISO 8601 date or date and time, with optional UTC offset.
+pub fn parse(s: &str) -> i64 {
+    try_parse(s).unwrap_or_else(|| panic!("invalid datetime '{}'", s))
+}
+
+/// Parse ISO 8601 date or date and time, None if the string is not valid.
+pub fn try_parse(s: &str) -> Option<i64> {
+    parse_iso(s.trim())
+}
+
+/// Read n decimal digits.
+fn digits(b: &[u8], ix: &mut usize, n: usize) -> Option<i64> {
+    let d = b.get(*ix..*ix + n)?;
+    if !d.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    *ix += n;
+    Some(d.iter().fold(0, |v, c| v * 10 + (c - b'0') as i64))
+}
+
+/// Skip the next byte if it is one of the specified bytes.
+fn skip(b: &[u8], ix: &mut usize, any: &[u8]) -> bool {
+    match b.get(*ix) {
+        Some(c) if any.contains(c) => {
+            *ix += 1;
+            true
+        }
+        _ => false,
+    }
+}
+
+fn parse_iso(s: &str) -> Option<i64> {
+    let b = s.as_bytes();
+    let ix = &mut 0;
+    let y = digits(b, ix, 4)?;
+    if !skip(b, ix, b"-") {
+        return None;
+    }
+    let m = digits(b, ix, 2)?;
+    if !skip(b, ix, b"-") {
+        return None;
+    }
+    let d = digits(b, ix, 2)?;
+    if !(1..=12).contains(&m) || d < 1 || d > days_in_month(y, m) {
+        return None;
+    }
+    let mut tod = 0;
+    if skip(b, ix, b"Tt ") {
+        let hh = digits(b, ix, 2)?;
+        if !skip(b, ix, b":") {
+            return None;
+        }
+        let mm = digits(b, ix, 2)?;
+        let ss = if skip(b, ix, b":") {
+            digits(b, ix, 2)?
+        } else {
+            0
+        };
+        if hh > 23 || mm > 59 || ss > 59 {
+            return None;
+        }
+        tod = hh * HOUR + mm * MINUTE + ss * SECOND;
+        if skip(b, ix, b".") {
+            let start = *ix;
+            let mut scale = SECOND;
+            while let Some(c) = b.get(*ix).filter(|c| c.is_ascii_digit()) {
+                scale /= 10;
+                tod += (c - b'0') as i64 * scale;
+                *ix += 1;
+            }
+            if *ix == start {
+                return None;
+            }
+        }
+    }
+    let mut offset = 0;
+    if !skip(b, ix, b"Zz") {
+        let sign = match b.get(*ix) {
+            Some(b'+') => 1,
+            Some(b'-') => -1,
+            _ => 0,
+        };
+        if sign != 0 {
+            *ix += 1;
+            let oh = digits(b, ix, 2)?;
+            skip(b, ix, b":");
+            let om = digits(b, ix, 2)?;
+            if oh > 23 || om > 59 {
+                return None;
+            }
+            offset = sign * (oh * HOUR + om * MINUTE);
+        }
+    }
+    if *ix != b.len() {
+        return None;
+    }
+    Some(days_from_civil(y, m, d) * DAY + tod - offset)
+}
+
+/// Add months, the day is reduced if necessary to fit in the resulting month.
+fn add_months(t: i64, n: i64) -> i64 {
+    let p = Parts::new(t);
+    let months = checked((p.y * 12 + p.m - 1).checked_add(n));
+    let (y, m) = (months.div_euclid(12), months.rem_euclid(12) + 1);
+    if (y - 1970).abs() > MAX_YEARS {
+        panic!("datetime out of range");
+    }
+    let d = p.d.min(days_in_month(y, m));
+    checked(
+        days_from_civil(y, m, d)
+            .checked_mul(DAY)
+            .and_then(|x| x.checked_add(t.rem_euclid(DAY))),
+    )
+}
+
+/// Length of a fixed size unit in microseconds ( None for month and year ).
+fn unit_size(unit: &str) -> Option<i64> {
+    Some(match unit {
+        "week" => 7 * DAY,
+        "day" => DAY,
+        "hour" => HOUR,
+        "minute" => MINUTE,
+        "second" => SECOND,
+        "millisecond" => 1000,
+        "microsecond" => 1,
+        "year" | "month" => return None,
+        _ => panic!("invalid datetime unit '{}'", unit),
+    })
+}
+
+/// Add n units to a datetime.
+pub fn add(unit: &str, n: i64, t: i64) -> i64 {
+    match unit {
+        "year" => add_months(t, checked(n.checked_mul(12))),
+        "month" => add_months(t, n),
+        _ => checked(
+            n.checked_mul(unit_size(unit).unwrap())
+                .and_then(|x| t.checked_add(x)),
+        ),
+    }
+}
+
+/// Number of unit boundaries crossed between t1 and t2.
+pub fn diff(unit: &str, t1: i64, t2: i64) -> i64 {
+    match unit {
+        "year" => Parts::new(t2).y - Parts::new(t1).y,
+        "month" => {
+            let (p1, p2) = (Parts::new(t1), Parts::new(t2));
+            (p2.y * 12 + p2.m) - (p1.y * 12 + p1.m)
+        }
+        "week" => (t2 - DAY * 4).div_euclid(7 * DAY) - (t1 - DAY * 4).div_euclid(7 * DAY),
+        _ => {
+            let n = unit_size(unit).unwrap();
+            t2.div_euclid(n) - t1.div_euclid(n)
+        }
+    }
+}
+
+/// Truncate a datetime to the start of the unit ( weeks start on Monday ).
+pub fn trunc(unit: &str, t: i64) -> i64 {
+    match unit {
+        "year" => days_from_civil(Parts::new(t).y, 1, 1) * DAY,
+        "month" => {
+            let p = Parts::new(t);
+            days_from_civil(p.y, p.m, 1) * DAY
+        }
+        // 1970-01-01 was a Thursday, so weeks are counted from 1970-01-05.
+        "week" => (t - 4 * DAY).div_euclid(7 * DAY) * 7 * DAY + 4 * DAY,
+        _ => {
+            let n = unit_size(unit).unwrap();
+            t.div_euclid(n) * n
+        }
+    }
+}
+
+/// Extract part of a datetime.
+pub fn part(unit: &str, t: i64) -> i64 {
+    let p = Parts::new(t);
+    match unit {
+        "year" => p.y,
+        "month" => p.m,
+        "day" => p.d,
+        "hour" => p.hh,
+        "minute" => p.mm,
+        "second" => p.ss,
+        "millisecond" => p.us / 1000,
+        "microsecond" => p.us,
+        "weekday" => (t.div_euclid(DAY) + 3).rem_euclid(7) + 1,
+        "dayofyear" => day_of_year(&p),
+        _ => panic!("invalid datetime part '{}'", unit),
+    }
+}
+
+/////////////////////////////
+/// Compile call to PARSEDATETIME.
+pub(crate) fn c_parse_datetime(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_types(b, args, &[DataKind::String]);
+    c_null_map(b, args, |v| Value::Int(parse(&v[0].str())))
+}
+/////////////////////////////
+/// Compile call to TODATETIME.
+pub(crate) fn c_to_datetime(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_types(b, args, &[DataKind::Int]);
+    c_value(b, &mut args[0])
+}
+/////////////////////////////
+/// Compile call to DATETIMESTR.
+pub(crate) fn c_datetime_str(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_types(b, args, &[DataKind::DateTime]);
+    Box::new(NullDateTimeStr {
+        ce: c_value(b, &mut args[0]),
+    })
+}
+/// Convert datetime to ISO 8601 string.
+pub(crate) struct DateTimeStr {
+    pub t: CExpPtr<i64>,
+}
+impl CExp<Value> for DateTimeStr {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> Value {
+        let t = self.t.eval(e, d);
+        Value::String(Rc::new(to_iso(t)))
+    }
+}
+/// Convert nullable datetime to ISO 8601 string, NULL gives NULL.
+pub(crate) struct NullDateTimeStr {
+    pub ce: CExpPtr<Value>,
+}
+impl CExp<Value> for NullDateTimeStr {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> Value {
+        match self.ce.eval(e, d) {
+            Value::Null => Value::Null,
+            v => Value::String(Rc::new(to_iso(v.int()))),
+        }
+    }
+}
+/////////////////////////////
+/// Compile call to FORMATDATETIME.
+pub(crate) fn c_format_datetime(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_types(b, args, &[DataKind::DateTime, DataKind::String]);
+    c_null_map(b, args, |v| {
+        Value::String(Rc::new(format(v[0].int(), &v[1].str())))
+    })
+}
+/////////////////////////////
+/// Compile call to DATEADD.
+pub(crate) fn c_date_add(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_types(
+        b,
+        args,
+        &[DataKind::String, DataKind::Int, DataKind::DateTime],
+    );
+    c_null_map(b, args, |v| {
+        Value::Int(add(&v[0].str(), v[1].int(), v[2].int()))
+    })
+}
+/////////////////////////////
+/// Compile call to DATEDIFF.
+pub(crate) fn c_date_diff(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_types(
+        b,
+        args,
+        &[DataKind::String, DataKind::DateTime, DataKind::DateTime],
+    );
+    c_null_map(b, args, |v| {
+        Value::Int(diff(&v[0].str(), v[1].int(), v[2].int()))
+    })
+}
+/////////////////////////////
+/// Compile call to DATETRUNC.
+pub(crate) fn c_date_trunc(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_types(b, args, &[DataKind::String, DataKind::DateTime]);
+    c_null_map(b, args, |v| Value::Int(trunc(&v[0].str(), v[1].int())))
+}
+/////////////////////////////
+/// Compile call to DATEPART.
+pub(crate) fn c_date_part(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_types(b, args, &[DataKind::String, DataKind::DateTime]);
+    c_null_map(b, args, |v| Value::Int(part(&v[0].str(), v[1].int())))
+}
@@ -0,0 +1,262 @@
+//! A decimal(p,s) value is an int scaled by 10^s, with at most p digits ( p is at most 18, default is decimal(18,0) ).
+//!
+//! Arithmetic is exact. The scale of the result is max(s1,s2) for + - and %, s1+s2 for * and max(s1,s2,6) for /.
+//! An int operand is treated as a decimal with scale 0.
+//!
+//! Rounding is half away from zero. It happens when a quotient or product has more digits than the result scale,
+//! and when a value is assigned to a variable or parameter or saved in a column with a smaller scale.
+//! A value which does not fit the precision of a column, variable or parameter, or which overflows, is an error
+//! ( the result of += and -= on a variable is only rounded to its scale ).
+
+use crate::*;
+
+/// Maximum precision.
+pub const MAX_PRECISION: usize = 18;
+
+/// Minimum scale of a quotient.
+const DIV_SCALE: u8 = 6;
+
+/// 10 to the power n.
+fn pow10(n: u8) -> i128 {
+    10_i128.pow(n as u32)
+}
+
+/// x / d rounded half away from zero.
+fn div_round(x: i128, d: i128) -> i128 {
+    let (q, r) = (x / d, x % d);
+    if 2 * r.abs() >= d.abs() {
+        q + x.signum() * d.signum()
+    } else {
+        q
+    }
+}
+
+/// Convert result to i64, panics on overflow.
+fn narrow(x: i128) -> i64 {
+    i64::try_from(x).unwrap_or_else(|_| panic!("decimal overflow"))
+}
+
+/// Change the scale of a scaled value, rounding half away from zero.
+pub fn rescale(v: i64, from: u8, to: u8) -> i64 {
+    let v = v as i128;
+    if to >= from {
+        narrow(v * pow10(to - from))
+    } else {
+        narrow(div_round(v, pow10(from - to)))
+    }
+}
+
+/// Convert a scaled value for saving in a column of decimal type t.
+pub fn to_column(v: i64, s: u8, t: DataType) -> i64 {
+    convert(v, s, t).unwrap_or_else(|| {
+        panic!(
+            "decimal value too large for decimal({},{})",
+            data_precision(t),
+            data_scale(t)
+        )
+    })
+}
+
+/// Convert a scaled value to the scale of decimal type t, None if it does not fit the precision.
+pub fn convert(v: i64, s: u8, t: DataType) -> Option<i64> {
+    let (p, ts) = (data_precision(t) as u8, data_scale(t) as u8);
+    let v = v as i128;
+    let r = if ts >= s {
+        v.checked_mul(pow10(ts - s))?
+    } else {
+        div_round(v, pow10(s - ts))
+    };
+    (r.abs() < pow10(p)).then_some(r as i64)
+}
+
+/// Round a scaled value to n decimal places ( n may be negative ), the scale is unchanged.
+pub fn round(v: i64, s: u8, n: i64) -> i64 {
+    let drop = s as i64 - n;
+    if drop <= 0 {
+        v
+    } else if drop > MAX_PRECISION as i64 {
+        0
+    } else {
+        let d = pow10(drop as u8);
+        narrow(div_round(v as i128, d) * d)
+    }
+}
+
+/// Largest whole number not greater than a scaled value ( or smallest not less if up is true ), the scale is unchanged.
+pub fn floor(v: i64, s: u8, up: bool) -> i64 {
+    let d = pow10(s);
+    let (v, q) = (v as i128, (v as i128).div_euclid(d));
+    let q = if up && q * d != v { q + 1 } else { q };
+    narrow(q * d)
+}
+
+/// Truncate a scaled value to an int.
+pub fn trunc(v: i64, s: u8) -> i64 {
+    narrow(v as i128 / pow10(s))
+}
+
+/// Convert a scaled value to a float.
+pub fn to_float(v: i64, s: u8) -> f64 {
+    v as f64 / pow10(s) as f64
+}
+
+/// Compare scaled values.
+pub fn cmp(v1: i64, s1: u8, v2: i64, s2: u8) -> Ordering {
+    let s = s1.max(s2);
+    (v1 as i128 * pow10(s - s1)).cmp(&(v2 as i128 * pow10(s - s2)))
+}
+
+/// Format a scaled value, e.g. 12345 with scale 2 is "123.45".
+pub fn to_string(v: i64, s: u8) -> String {
+    if s == 0 {
+        return v.to_string();
+    }
+    let d = 10_u64.pow(s as u32);
+    let a = v.unsigned_abs();
+    let sign = if v < 0 { "-" } else { "" };
+    format!("{}{}.{:0w$}", sign, a / d, a % d, w = s as usize)
+}
+
+/// Parse a decimal number, e.g. "-123.45", result is scaled value and scale.
+pub fn parse(s: &str) -> Option<(i64, u8)> {
+    let (neg, s) = match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+    if int.is_empty() && frac.is_empty() || frac.len() > MAX_PRECISION {
+        return None;
+    }
+    let mut v: i64 = 0;
+    for c in int.bytes().chain(frac.bytes()) {
+        if !c.is_ascii_digit() {
+            return None;
+        }
+        v = v.checked_mul(10)?.checked_add((c - b'0') as i64)?;
+    }
+    Some((if neg { -v } else { v }, frac.len() as u8))
+}
+
+/// Scaled value and scale of an int or decimal Value.
+fn parts(v: &Value) -> (i64, u8) {
+    match v {
+        Value::Int(x) => (*x, 0),
+        Value::Decimal(x, s) => (*x, *s),
+        _ => panic!(),
+    }
+}
+
+/// Scale of an int or decimal DataType.
+fn type_scale(t: DataType) -> u8 {
+    if data_kind(t) == DataKind::Decimal {
+        data_scale(t) as u8
+    } else {
+        0
+    }
+}
+
+/// Scale of the result of an arithmetic operation.
+fn result_scale(op: Token, s1: u8, s2: u8) -> u8 {
+    match op {
+        Token::Times => (s1 + s2).min(MAX_PRECISION as u8),
+        Token::Divide => s1.max(s2).max(DIV_SCALE),
+        _ => s1.max(s2),
+    }
+}
+
+/// DataType of the result of an arithmetic operation where either operand is decimal.
+pub fn result_type(op: Token, t1: DataType, t2: DataType) -> DataType {
+    let s = result_scale(op, type_scale(t1), type_scale(t2));
+    decimal_type(MAX_PRECISION, s as usize)
+}
+
+/// Evaluate an arithmetic operation where either operand is decimal.
+pub fn arithmetic(op: Token, v1: &Value, v2: &Value) -> Value {
+    let ((x1, s1), (x2, s2)) = (parts(v1), parts(v2));
+    let rs = result_scale(op, s1, s2);
+    let (x1, x2) = (x1 as i128, x2 as i128);
+    let s = s1.max(s2);
+    let (a, b) = (x1 * pow10(s - s1), x2 * pow10(s - s2));
+    let r = match op {
+        Token::Plus => a + b,
+        Token::Minus => a - b,
+        Token::Percent => {
+            if b == 0 {
+                panic!("division by zero");
+            }
+            a % b
+        }
+        Token::Times => div_round(x1 * x2, pow10(s1 + s2 - rs)),
+        Token::Divide => {
+            if x2 == 0 {
+                panic!("division by zero");
+            }
+            // x1/10^s1 / x2/10^s2 * 10^rs
+            let n = x1
+                .checked_mul(pow10(rs + s2 - s1))
+                .unwrap_or_else(|| panic!("decimal overflow"));
+            div_round(n, x2)
+        }
+        _ => panic!("invalid decimal operator {:?}", op),
+    };
+    Value::Decimal(narrow(r), rs)
+}
+
+/// Compile conversion of an assigned expression of type et to a variable of type t.
+pub(crate) fn c_assign(ce: CExpPtr<Value>, et: DataType, t: DataType) -> CExpPtr<Value> {
+    if data_kind(t) == DataKind::Decimal
+        && (data_kind(et) != DataKind::Decimal
+            || data_scale(et) != data_scale(t)
+            || data_precision(et) > data_precision(t))
+    {
+        Box::new(Convert { ce, typ: t })
+    } else {
+        ce
+    }
+}
+
+/// Arithmetic where either operand is decimal.
+pub(crate) struct Arithmetic {
+    pub op: Token,
+    pub c1: CExpPtr<Value>,
+    pub c2: CExpPtr<Value>,
+}
+
+impl CExp<Value> for Arithmetic {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> Value {
+        let v1 = self.c1.eval(e, d);
+        let v2 = self.c2.eval(e, d);
+        arithmetic(self.op, &v1, &v2)
+    }
+}
+
+/// Convert an int or decimal value to decimal type typ, checking the precision.
+pub(crate) struct Convert {
+    pub ce: CExpPtr<Value>,
+    pub typ: DataType,
+}
+
+impl CExp<Value> for Convert {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> Value {
+        match self.ce.eval(e, d) {
+            Value::Null => Value::Null,
+            v => {
+                let (x, s) = parts(&v);
+                Value::Decimal(to_column(x, s, self.typ), data_scale(self.typ) as u8)
+            }
+        }
+    }
+}
+
+/// Decimal column.
+pub(crate) struct ColumnDecimal {
+    pub off: usize,
+    pub scale: u8,
+}
+
+impl CExp<Value> for ColumnDecimal {
+    fn eval(&self, _e: &mut EvalEnv, data: &[u8]) -> Value {
+        Value::Decimal(util::iget(data, self.off, 8), self.scale)
+    }
+}
@@ -14,17 +14,36 @@ pub struct EvalEnv<'r> {
     pub tr: &'r mut dyn Transaction,
     /// Function call depth, prevents stack overflow.
     pub call_depth: usize,
+    /// Error caught by the most recent CATCH block.
+    pub exception: Option<Rc<String>>,
+    /// When profiling, name of the current function ( or "batch" ), batch section ( 0 for a function ) and source line of each instruction.
+    pub profile: Option<(Rc<String>, usize, Rc<Vec<usize>>)>,
 }
 
 impl<'r> EvalEnv<'r> {
     /// Construct a new EvalEnv.
     pub fn new(db: DB, tr: &'r mut dyn Transaction) -> Self {
+        let profile = if db.profile.enabled.get() {
+            let section = db.profile.section();
+            Some((Rc::new("batch".to_string()), section, Rc::new(Vec::new())))
+        } else {
+            None
+        };
         EvalEnv {
             stack: Vec::with_capacity(64),
             bp: 0,
             db,
             tr,
             call_depth: 0,
+            exception: None,
+            profile,
+        }
+    }
+
+    /// Set the source line of each instruction, used when profiling.
+    pub fn set_lines(&mut self, lines: &[usize]) {
+        if let Some(p) = &mut self.profile {
+            p.2 = Rc::new(lines.to_vec());
         }
     }
 
@@ -38,10 +57,20 @@ impl<'r> EvalEnv<'r> {
 
     /// Execute list of instructions.
     pub fn go(&mut self, ilist: &[Instruction]) {
-        let n = ilist.len();
-        let mut ip = 0;
-        while ip < n {
+        self.go_range(ilist, 0, 0..ilist.len());
+    }
+
+    /// Execute instructions starting at ip, while ip is in the specified range.
+    /// Result is the next instruction, or None if RETURN was executed.
+    fn go_range(
+        &mut self,
+        ilist: &[Instruction],
+        mut ip: usize,
+        range: std::ops::Range<usize>,
+    ) -> Option<usize> {
+        while range.contains(&ip) {
             let i = &ilist[ip];
+            let start = self.profile.as_ref().map(|_| (ip, self.db.profile.start()));
             ip += 1;
             match i {
                 PushConst(x) => self.stack.push((*x).clone()),
@@ -58,11 +87,41 @@ impl<'r> EvalEnv<'r> {
                     }
                 }
                 Call(x) => self.call(x),
-                Return => break,
+                Return => return None,
                 Throw => {
                     let s = self.pop_string();
                     panic!("{}", s);
                 }
+                Try(catch) => {
+                    let (sp, bp, call_depth) = (self.stack.len(), self.bp, self.call_depth);
+                    let profile = self.profile.clone();
+                    // Savepoint so that changes made by a failing statement can be undone.
+                    let spi = self.db.savepoints.borrow().len();
+                    self.db.savepoint("");
+                    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                        self.go_range(ilist, ip, ip..*catch)
+                    }));
+                    let exists = self.db.savepoints.borrow().len() > spi;
+                    if exists && result.is_ok() {
+                        self.db.release_savepoint(spi);
+                    }
+                    match result {
+                        Ok(Some(next)) => ip = next,
+                        Ok(None) => return None,
+                        Err(x) => {
+                            if exists {
+                                self.db.rollback_savepoint(spi);
+                                self.db.savepoints.borrow_mut().truncate(spi);
+                            }
+                            self.stack.truncate(sp);
+                            self.bp = bp;
+                            self.call_depth = call_depth;
+                            self.profile = profile;
+                            self.exception = Some(Rc::new(panic_message(&x)));
+                            ip = *catch;
+                        }
+                    }
+                }
                 Execute => self.execute(),
                 DataOp(x) => self.exec_do(x),
                 Select(cse) => self.select(cse),
@@ -110,8 +169,17 @@ impl<'r> EvalEnv<'r> {
                     self.stack[self.bp + x].dec(&v);
                 }
             }
+            if let Some((ip, start)) = start {
+                if let Some((name, section, lines)) = &self.profile {
+                    let line = lines.get(ip).copied().unwrap_or(0);
+                    self.db
+                        .profile
+                        .record(name, *section, Some(ip), line, start);
+                }
+            }
         }
-    } // end fn go
+        Some(ip)
+    } // end fn go_range
 
     /// Call a function.
     pub fn call(&mut self, r: &Function) {
@@ -126,11 +194,19 @@ impl<'r> EvalEnv<'r> {
         if self.call_depth > 500 {
             panic!("call depth limit of 500 reached");
         }
+        let profile = if self.profile.is_some() {
+            let save = self
+                .profile
+                .replace((r.name.clone(), 0, r.lines.borrow().clone()));
+            Some((save, self.db.profile.start()))
+        } else {
+            None
+        };
         let save_bp = self.bp;
         self.bp = self.stack.len() - r.param_count;
-        self.alloc_locals(&r.local_typ, r.param_count);
+        self.alloc_locals(&r.local_typ.borrow(), r.param_count);
         self.go(&r.ilist.borrow());
-        let pop_count = r.local_typ.len();
+        let pop_count = r.local_typ.borrow().len();
         if pop_count > 0 {
             if r.return_type != NONE {
                 if r.param_count == 0
@@ -148,6 +224,10 @@ impl<'r> EvalEnv<'r> {
         }
         self.bp = save_bp;
         self.call_depth -= 1;
+        if let Some((save, start)) = profile {
+            self.db.profile.record(&r.name, 0, None, 0, start);
+            self.profile = save;
+        }
     }
 
     /// Discard n items from stack.
@@ -246,6 +326,12 @@ impl<'r> EvalEnv<'r> {
 
     /// Execute a data operation (DO).
     fn exec_do(&mut self, dop: &DO) {
+        if !matches!(
+            dop,
+            DO::Insert(..) | DO::Update(..) | DO::Delete(..) | DO::Savepoint(_) | DO::RollbackTo(_)
+        ) {
+            self.db.schema_gen.set(self.db.schema_gen.get() + 1);
+        }
         match dop {
             DO::Insert(tp, cols, values) => self.insert(tp.clone(), cols, values),
             DO::Update(assigns, from, wher) => self.update(assigns, from, wher),
@@ -253,15 +339,27 @@ impl<'r> EvalEnv<'r> {
 
             DO::CreateSchema(name) => sys::create_schema(&self.db, name),
             DO::CreateTable(ti) => sys::create_table(&self.db, ti),
-            DO::CreateIndex(x) => sys::create_index(&self.db, x),
+            DO::CreateIndex(x) => self.create_index(x),
+            DO::CreateForeignKey(fk) => self.create_foreign_key(fk),
+            DO::CreateConstraint(c) => self.create_constraint(c),
+            DO::CreateTrigger(tr) => self.create_trigger(tr),
             DO::CreateFunction(name, source, alter) => {
                 sys::create_function(&self.db, name, source.clone(), *alter)
             }
+            DO::CreateView(name, source) => self.create_view(name, source),
             DO::DropSchema(name) => self.drop_schema(name),
             DO::DropTable(name) => self.drop_table(name),
             DO::DropFunction(name) => self.drop_function(name),
+            DO::DropView(name) => self.drop_view(name),
+            DO::DropTrigger(tname, name) => self.drop_trigger(tname, name),
             DO::DropIndex(tname, iname) => self.drop_index(tname, iname),
             DO::AlterTable(tname, actions) => self.alter_table(tname, actions),
+            DO::Savepoint(name) => self.db.savepoint(name),
+            DO::RollbackTo(name) => {
+                if let Err(e) = self.db.rollback_to(name) {
+                    panic!("{}", e);
+                }
+            }
         }
     }
 
@@ -281,10 +379,10 @@ impl<'r> EvalEnv<'r> {
 
     /// Execute INSERT operation.
     fn insert(&mut self, t: Rc<Table>, cols: &[usize], src: &CTableExpression) {
-        if let CTableExpression::Values(x) = src {
-            self.insert_values(t, cols, x);
-        } else {
-            panic!();
+        match src {
+            CTableExpression::Values(x) => self.insert_values(t, cols, x),
+            CTableExpression::Select(cse) => self.insert_select(t, cols, cse),
+            _ => panic!(),
         }
     }
 
@@ -292,6 +390,11 @@ impl<'r> EvalEnv<'r> {
     fn delete(&mut self, from: &CTableExpression, w: &Option<CExpPtr<bool>>) {
         let idlist = self.get_id_list(from, w);
         let t = from.table();
+        self.delete_ids(&t, idlist);
+    }
+
+    /// Delete the rows with the specified ids.
+    fn delete_ids(&mut self, t: &Rc<Table>, idlist: Vec<u64>) {
         let mut oldrow = t.row();
         for id in idlist {
             // Load oldrow so that any codes are deleted.
@@ -300,9 +403,54 @@ impl<'r> EvalEnv<'r> {
                 let data = &p.data[off..];
                 oldrow.load(&self.db, data);
             } else {
-                unreachable!()
+                continue; // Already deleted by a cascade.
             }
             t.remove(&self.db, &oldrow);
+            self.delete_refs(t, id as i64);
+            self.fire(t, TriggerEvent::Delete, Some(&oldrow), None);
+        }
+    }
+
+    /// Apply foreign key ON DELETE actions to rows which reference a deleted row.
+    fn delete_refs(&mut self, t: &Table, id: i64) {
+        let refs = t.refs.borrow().clone();
+        for fk in refs {
+            let rt = self.db.get_table(&fk.table).unwrap();
+            let ids = rt.find_refs(&self.db, fk.col, id);
+            if ids.is_empty() {
+                continue;
+            }
+            match fk.action {
+                RefAction::Restrict => panic!(
+                    "delete of {} Id {} violates foreign key {}.{}",
+                    t.info.name.str(),
+                    id,
+                    fk.table.str(),
+                    rt.info.colnames[fk.col]
+                ),
+                RefAction::Cascade => self.delete_ids(&rt, ids),
+                RefAction::SetDefault => {
+                    let mut oldrow = rt.row();
+                    for id in ids {
+                        let (pp, off) = rt.id_get(&self.db, id).unwrap();
+                        oldrow.load(&self.db, &pp.borrow().data[off..]);
+                        let mut newrow = oldrow.clone();
+                        newrow.values[fk.col] = self.default_value(&rt, fk.col);
+                        rt.remove(&self.db, &oldrow);
+                        rt.insert(&self.db, &mut newrow);
+                        self.fire(&rt, TriggerEvent::Update, Some(&oldrow), Some(&newrow));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Evaluate the DEFAULT value of a column ( the default for its type if it has no DEFAULT constraint ).
+    fn default_value(&mut self, t: &Table, col: usize) -> Value {
+        let defaults = t.defaults.borrow();
+        match defaults.iter().rfind(|(x, _)| *x == col) {
+            Some((_, exp)) => exp.eval(self, &[]),
+            None => t.row().values[col].clone(),
         }
     }
 
@@ -331,12 +479,24 @@ impl<'r> EvalEnv<'r> {
                 // Would be nice to optimise this to minimise re-indexing.
                 t.remove(&self.db, &oldrow);
                 t.insert(&self.db, &mut newrow);
+                self.check_row(&t, newrow.id);
+                self.fire(&t, TriggerEvent::Update, Some(&oldrow), Some(&newrow));
             }
         }
     }
 
-    /// Get DataSource from CTableExpression.
+    /// Get DataSource from CTableExpression, counting the rows fetched when profiling.
     fn data_source(&mut self, te: &CTableExpression) -> DataSource {
+        let ds = self.get_source(te);
+        if self.profile.is_none() {
+            return ds;
+        }
+        let db = self.db.clone();
+        Box::new(ds.inspect(move |_| db.profile.rows.set(db.profile.rows.get() + 1)))
+    }
+
+    /// Get DataSource from CTableExpression.
+    fn get_source(&mut self, te: &CTableExpression) -> DataSource {
         match te {
             CTableExpression::Base(t) => Box::new(t.scan(&self.db)),
             CTableExpression::IdGet(t, idexp) => {
@@ -350,45 +510,99 @@ impl<'r> EvalEnv<'r> {
                 }
                 Box::new(t.scan_keys(&self.db, keys, *index))
             }
+            CTableExpression::IxRange(t, r) => {
+                let keys = r.keys.iter().map(|k| k.eval(self, &[])).collect();
+                let lower = r.lower.as_ref().map(|(e, inc)| (e.eval(self, &[]), *inc));
+                let upper = r.upper.as_ref().map(|(e, inc)| (e.eval(self, &[]), *inc));
+                Box::new(t.scan_range(&self.db, r.index, keys, lower, upper, r.desc))
+            }
+            CTableExpression::Derived(d) => {
+                let n = self.derived_rows(d);
+                table::row_numbers(n, 8)
+            }
+            CTableExpression::KeyList(list) => {
+                let sources: Vec<DataSource> = list.iter().map(|te| self.get_source(te)).collect();
+                let mut seen = HashSet::default();
+                Box::new(
+                    sources.into_iter().flatten().filter(move |(pp, off)| {
+                        seen.insert(util::getu64(&pp.borrow().data, *off))
+                    }),
+                )
+            }
+            CTableExpression::Join(j) => {
+                let derived: Vec<Option<u64>> = j
+                    .derived
+                    .iter()
+                    .map(|d| d.as_ref().map(|d| self.derived_rows(d)))
+                    .collect();
+                let first: DataSource = match derived[0] {
+                    Some(n) => table::row_numbers(n, 8),
+                    None => Box::new(j.tables[0].1.scan(&self.db)),
+                };
+                if j.steps.is_empty() {
+                    return first;
+                }
+                let consts = j.consts.iter().map(|ce| ce.eval(self, &[])).collect();
+                let mut js = table::JoinScan::new(&self.db, j.clone(), consts, derived, first);
+                if j.steps.iter().any(|s| s.pred.is_some()) {
+                    // ON conditions of a LEFT JOIN need the EvalEnv, so the joined records are fetched here.
+                    let mut rows = Vec::new();
+                    while let Some(r) = js.fetch(Some(self)) {
+                        rows.push(r);
+                    }
+                    return Box::new(rows.into_iter());
+                }
+                Box::new(js)
+            }
             _ => panic!(),
         }
     }
 
+    /// Get the rows of a derived table, which are held in a local variable. Result is the number of rows.
+    fn derived_rows(&mut self, d: &CDerived) -> u64 {
+        let rows = self.get_rows(&d.select);
+        let n = rows.len() as u64;
+        self.stack[self.bp + d.local] = Value::ForSort(util::new(ForSortState { ix: 0, rows }));
+        n
+    }
+
     /// Execute a SELECT operation.
     fn select(&mut self, cse: &CFromExpression) {
+        if cse.json {
+            let rows = self.get_rows(cse);
+            let s = json::rows(&cse.colnames, &cse.types, &rows);
+            self.tr.selected(&[Value::String(Rc::new(s))]);
+            return;
+        }
         if let Some(te) = &cse.from {
             let obl = cse.orderby.len();
-            let mut temp = Vec::new(); // For sorting.
+            if obl > 0 {
+                // Sort then output the rows.
+                for r in &self.get_temp(cse) {
+                    self.selected(cse, &r[obl..]);
+                }
+                return;
+            }
+            let (mut skip, mut n) = self.limits(cse);
             for (pp, off) in self.data_source(te) {
+                if n == 0 {
+                    break;
+                }
                 let p = pp.borrow();
                 let data = &p.data[off..];
                 if self.ok(&cse.wher, data) {
-                    let mut values = Vec::new();
-                    if obl > 0 {
-                        // Push the sort keys.
-                        for ce in &cse.orderby {
-                            let val = ce.eval(self, data);
-                            values.push(val);
-                        }
+                    if skip > 0 {
+                        skip -= 1;
+                        continue;
                     }
+                    n -= 1;
+                    let mut values = Vec::new();
                     for ce in &cse.exps {
                         let val = ce.eval(self, data);
                         values.push(val);
                     }
-                    if obl > 0 {
-                        // Save row for later sorting.
-                        temp.push(values);
-                    } else {
-                        // Output directly.
-                        self.tr.selected(&values);
-                    }
-                }
-            }
-            if obl > 0 {
-                // Sort then output the rows.
-                temp.sort_by(|a, b| table::row_compare(a, b, &cse.desc));
-                for r in &temp {
-                    self.tr.selected(&r[obl..]);
+                    // Output directly.
+                    self.selected(cse, &values);
                 }
             }
         } else {
@@ -397,10 +611,33 @@ impl<'r> EvalEnv<'r> {
                 let val = ce.eval(self, &[]);
                 values.push(val);
             }
-            self.tr.selected(&values);
+            self.selected(cse, &values);
         }
     }
 
+    /// Output a selected row, datetime values are converted to ISO 8601 strings.
+    fn selected(&mut self, cse: &CFromExpression, values: &[Value]) {
+        if !cse
+            .types
+            .iter()
+            .any(|t| data_kind(*t) == DataKind::DateTime)
+        {
+            self.tr.selected(values);
+            return;
+        }
+        let values: Vec<Value> = values
+            .iter()
+            .zip(&cse.types)
+            .map(|(v, t)| match v {
+                Value::Int(x) if data_kind(*t) == DataKind::DateTime => {
+                    Value::String(Rc::new(datetime::to_iso(*x)))
+                }
+                _ => v.clone(),
+            })
+            .collect();
+        self.tr.selected(&values);
+    }
+
     /// Execute a SET operation.
     fn set(&mut self, cse: &CFromExpression) {
         if let Some(te) = &cse.from {
@@ -438,33 +675,118 @@ impl<'r> EvalEnv<'r> {
     fn insert_values(&mut self, table: Rc<Table>, ci: &[usize], vals: &[Vec<CExpPtr<Value>>]) {
         let mut row = Row::new(table.info.clone());
         for r in vals {
-            row.id = 0;
-            for (i, ce) in r.iter().enumerate() {
-                let val = ce.eval(self, &[]);
-                let cn = ci[i];
-                if cn == usize::MAX {
-                    if let Value::Int(v) = val {
-                        row.id = v;
-                    }
-                } else {
-                    row.values[cn] = val;
-                }
+            let values: Vec<Value> = r.iter().map(|ce| ce.eval(self, &[])).collect();
+            self.insert_row(&table, ci, &mut row, values);
+        }
+    }
+
+    /// Insert rows selected by a query into a table.
+    fn insert_select(&mut self, table: Rc<Table>, ci: &[usize], cse: &CFromExpression) {
+        // All rows are fetched before any are inserted, as the query may read the table.
+        let rows = self.get_rows(cse);
+        let mut row = Row::new(table.info.clone());
+        for values in rows {
+            self.insert_row(&table, ci, &mut row, values);
+        }
+    }
+
+    /// Get the rows selected by a query.
+    fn get_rows(&mut self, cse: &CFromExpression) -> Vec<Vec<Value>> {
+        if cse.from.is_some() {
+            let obl = cse.orderby.len();
+            let mut rows = self.get_temp(cse);
+            for r in &mut rows {
+                r.drain(0..obl);
+            }
+            rows
+        } else {
+            vec![cse.exps.iter().map(|ce| ce.eval(self, &[])).collect()]
+        }
+    }
+
+    /// Insert a row of values into a table.
+    fn insert_row(&mut self, table: &Table, ci: &[usize], row: &mut Row, values: Vec<Value>) {
+        row.id = 0;
+        for (cn, exp) in &*table.defaults.borrow() {
+            if !ci.contains(cn) {
+                row.values[*cn] = exp.eval(self, &[]);
             }
-            if row.id == 0 {
-                row.id = table.alloc_id(&self.db);
+        }
+        for (i, val) in values.into_iter().enumerate() {
+            let cn = ci[i];
+            if cn == usize::MAX {
+                if let Value::Int(v) = val {
+                    row.id = v;
+                }
             } else {
-                table.id_allocated(&self.db, row.id);
+                row.values[cn] = val;
             }
-            self.db.lastid.set(row.id);
-            table.insert(&self.db, &mut row);
         }
+        if row.id == 0 {
+            row.id = table.alloc_id(&self.db);
+        } else {
+            table.id_allocated(&self.db, row.id);
+        }
+        self.db.lastid.set(row.id);
+        table.insert(&self.db, row);
+        self.check_row(table, row.id);
+        self.fire(table, TriggerEvent::Insert, None, Some(row));
     }
 
-    /// Get sorted temporary table.
+    /// Evaluate the CHECK constraints for the row with specified id.
+    fn check_row(&mut self, t: &Table, id: i64) {
+        if let Some(source) = self.failed_check(t, id) {
+            check_failed(t, &source, id);
+        }
+    }
+
+    /// Get the source of the first CHECK constraint which is false for the row with specified id.
+    fn failed_check(&mut self, t: &Table, id: i64) -> Option<Rc<String>> {
+        let checks = t.checks.borrow();
+        if checks.is_empty() {
+            return None;
+        }
+        let (pp, off) = t.id_get(&self.db, id as u64).unwrap();
+        let p = pp.borrow();
+        let data = &p.data[off..];
+        for (source, exp) in &*checks {
+            if !exp.eval(self, data) {
+                return Some(source.clone());
+            }
+        }
+        None
+    }
+
+    /// Evaluate OFFSET and LIMIT. Result is number of rows to skip and maximum number of rows ( usize::MAX if no LIMIT ).
+    fn limits(&mut self, cse: &CFromExpression) -> (usize, usize) {
+        let mut get = |e: &Option<CExpPtr<i64>>, def: usize| {
+            if let Some(e) = e {
+                let n = e.eval(self, &[]);
+                if n < 0 {
+                    panic!("LIMIT/OFFSET cannot be negative");
+                }
+                n as usize
+            } else {
+                def
+            }
+        };
+        (get(&cse.offset, 0), get(&cse.limit, usize::MAX))
+    }
+
+    /// Get sorted temporary table. Only the rows selected by OFFSET and LIMIT are kept.
     fn get_temp(&mut self, cse: &CFromExpression) -> Vec<Vec<Value>> {
         if let Some(te) = &cse.from {
-            let mut temp = Vec::new(); // For sorting.
+            let (skip, n) = self.limits(cse);
+            let keep = skip.saturating_add(n);
+            let sorted = !cse.orderby.is_empty();
+            // For sorting.
+            let mut temp = Vec::new();
+            // With ORDER BY and LIMIT, a bounded heap holds the first keep rows found so far.
+            let mut heap = std::collections::BinaryHeap::new();
             for (pp, off) in self.data_source(te) {
+                if keep == 0 {
+                    break;
+                }
                 let p = pp.borrow();
                 let data = &p.data[off..];
                 if self.ok(&cse.wher, data) {
@@ -477,11 +799,33 @@ impl<'r> EvalEnv<'r> {
                         let val = ce.eval(self, data);
                         values.push(val);
                     }
-                    temp.push(values); // Save row for later sorting.
+                    if !sorted || keep == usize::MAX {
+                        temp.push(values); // Save row for later sorting.
+                        if temp.len() == keep {
+                            break;
+                        }
+                    } else {
+                        heap.push(table::SortRow {
+                            values,
+                            desc: &cse.desc,
+                        });
+                        if heap.len() > keep {
+                            heap.pop();
+                        }
+                    }
                 }
             }
-            // Sort the rows.
-            temp.sort_by(|a, b| table::row_compare(a, b, &cse.desc));
+            if !heap.is_empty() {
+                temp = heap
+                    .into_sorted_vec()
+                    .into_iter()
+                    .map(|r| r.values)
+                    .collect();
+            } else if sorted {
+                // Sort the rows.
+                temp.sort_by(|a, b| table::row_compare(a, b, &cse.desc));
+            }
+            temp.drain(0..skip.min(temp.len()));
             temp
         } else {
             panic!()
@@ -501,6 +845,42 @@ impl<'r> EvalEnv<'r> {
 
     fn drop_table(&mut self, name: &ObjRef) {
         if let Some(t) = sys::get_table(&self.db, name) {
+            if let Some(fk) = t.refs.borrow().iter().find(|fk| fk.table != *name) {
+                panic!(
+                    "table {} is referenced by a foreign key of {}",
+                    name.str(),
+                    fk.table.str()
+                );
+            }
+            if !t.defaults.borrow().is_empty() || !t.checks.borrow().is_empty() {
+                let sql = format!("DELETE FROM sys.Constraint WHERE Table = {}", t.id);
+                self.db.run(&sql, self.tr);
+            }
+            if !t.triggers.borrow().is_empty() {
+                let sql = format!("DELETE FROM sys.Trigger WHERE Table = {}", t.id);
+                self.db.run(&sql, self.tr);
+                t.triggers.borrow_mut().clear(); // Rows are deleted without firing triggers.
+            }
+            let unique: Vec<i64> = t
+                .ixlist
+                .borrow()
+                .iter()
+                .filter(|ix| ix.unique)
+                .map(|ix| ix.id)
+                .collect();
+            for id in unique {
+                let sql = format!("DELETE FROM sys.UniqueIndex WHERE Index = {}", id);
+                self.db.run(&sql, self.tr);
+            }
+            if !t.fkeys.borrow().is_empty() {
+                let sql = format!("DELETE FROM sys.ForeignKey WHERE Table = {}", t.id);
+                self.db.run(&sql, self.tr);
+                for fk in &*t.fkeys.borrow() {
+                    if let Some(rt) = self.db.get_table(&fk.rtable) {
+                        rt.refs.borrow_mut().retain(|r| r.table != *name);
+                    }
+                }
+            }
             let sql = format!("EXEC sys.DropTable({})", t.id);
             self.db.run(&sql, self.tr);
             self.db.tables.borrow_mut().remove(name);
@@ -521,8 +901,144 @@ impl<'r> EvalEnv<'r> {
         }
     }
 
+    /// Create a system table if it does not exist ( see sys::sys_table ).
+    fn ensure_sys_table(&mut self, name: &str, sql: &str) {
+        if sys::sys_table(&self.db, name).is_none() {
+            self.db.run(sql, self.tr);
+        }
+    }
+
+    fn create_view(&mut self, name: &ObjRef, source: &Rc<String>) {
+        self.ensure_sys_table("View", sys::VIEW_TABLE);
+        sys::create_view(&self.db, name, source.clone());
+    }
+
+    fn create_index(&mut self, x: &IndexInfo) {
+        if x.unique {
+            self.ensure_sys_table("UniqueIndex", sys::UNIQUE_INDEX_TABLE);
+        }
+        sys::create_index(&self.db, x);
+    }
+
+    fn create_foreign_key(&mut self, fk: &ForeignKey) {
+        self.ensure_sys_table("ForeignKey", sys::FOREIGN_KEY_TABLE);
+        sys::create_foreign_key(&self.db, fk);
+    }
+
+    fn create_constraint(&mut self, c: &Constraint) {
+        self.ensure_sys_table("Constraint", sys::CONSTRAINT_TABLE);
+        let t = sys::create_constraint(&self.db, c);
+
+        // Apply the constraint to existing rows.
+        let ids: Vec<u64> = t
+            .scan(&self.db)
+            .map(|(pp, off)| util::getu64(&pp.borrow().data, off))
+            .collect();
+        match c.kind {
+            ConstraintKind::Default => {
+                let cn = t.info.colmap[&c.col];
+                let defaults = t.defaults.borrow();
+                let (_, exp) = defaults.iter().rfind(|(x, _)| *x == cn).unwrap();
+                let mut oldrow = t.row();
+                for id in ids {
+                    let (pp, off) = t.id_get(&self.db, id).unwrap();
+                    oldrow.load(&self.db, &pp.borrow().data[off..]);
+                    let mut newrow = oldrow.clone();
+                    newrow.values[cn] = exp.eval(self, &[]);
+                    t.remove(&self.db, &oldrow);
+                    t.insert(&self.db, &mut newrow);
+                }
+            }
+            ConstraintKind::Check => {
+                for id in ids {
+                    if let Some(source) = self.failed_check(&t, id as i64) {
+                        // Remove the new constraint, as the table may still be used after rollback.
+                        t.checks.borrow_mut().pop();
+                        check_failed(&t, &source, id as i64);
+                    }
+                }
+            }
+        }
+    }
+
+    fn create_trigger(&mut self, tr: &Trigger) {
+        self.ensure_sys_table("Trigger", sys::TRIGGER_TABLE);
+        sys::create_trigger(&self.db, tr);
+    }
+
+    fn drop_trigger(&mut self, tname: &ObjRef, name: &str) {
+        let Some(t) = self.db.get_table(tname) else {
+            panic!("table {} not found", tname.str());
+        };
+        if let Some(id) = sys::get_trigger_id(&self.db, &t, name) {
+            let sql = format!("DELETE FROM sys.Trigger WHERE Id = {}", id);
+            self.db.run(&sql, self.tr);
+            t.triggers.borrow_mut().retain(|x| x.name != name);
+        } else {
+            panic!("Drop Trigger not found {}", name);
+        }
+    }
+
+    /// Call the trigger functions for the specified event.
+    fn fire(&mut self, t: &Table, event: TriggerEvent, old: Option<&Row>, new: Option<&Row>) {
+        let triggers: Vec<Rc<Trigger>> = t
+            .triggers
+            .borrow()
+            .iter()
+            .filter(|x| x.event == event)
+            .cloned()
+            .collect();
+        for tr in triggers {
+            let f = compile::c_function(&self.db, &tr.func);
+            // The function may have been altered since the trigger was created.
+            let mut kinds = vec![DataKind::Int];
+            kinds.extend(t.info.typ.iter().map(|typ| data_kind(*typ)));
+            let kinds = kinds.repeat([old, new].into_iter().flatten().count());
+            if f.param_count != kinds.len()
+                || kinds
+                    .iter()
+                    .zip(f.local_typ.borrow().iter())
+                    .any(|(k, typ)| {
+                        let pk = data_kind(*typ);
+                        *k != pk && !(*k == DataKind::Int && pk == DataKind::Decimal)
+                    })
+            {
+                panic!(
+                    "trigger {} function {} parameters do not match table {}",
+                    tr.name,
+                    tr.func.str(),
+                    t.info.name.str()
+                );
+            }
+            for row in [old, new].into_iter().flatten() {
+                self.stack.push(Value::Int(row.id));
+                for v in &row.values {
+                    self.stack.push(v.clone());
+                }
+            }
+            self.call(&f);
+            if f.return_type != NONE {
+                self.stack.pop();
+            }
+        }
+    }
+
+    fn drop_view(&mut self, name: &ObjRef) {
+        if let Some((vid, _)) = sys::get_view0(&self.db, name) {
+            let sql = format!("DELETE FROM sys.View WHERE Id = {}", vid);
+            self.db.run(&sql, self.tr);
+            self.db.function_reset.set(true);
+        } else {
+            panic!("Drop View not found {}", name.str());
+        }
+    }
+
     fn drop_index(&mut self, tname: &ObjRef, iname: &str) {
         let (t, ix, id) = sys::get_index(&self.db, tname, iname);
+        if t.ixlist.borrow()[ix].unique {
+            let sql = format!("DELETE FROM sys.UniqueIndex WHERE Index = {}", id);
+            self.db.run(&sql, self.tr);
+        }
         let sql = format!("EXEC sys.DropIndex({})", id);
         self.db.run(&sql, self.tr);
         self.db.tables.borrow_mut().remove(tname);
@@ -536,6 +1052,16 @@ impl<'r> EvalEnv<'r> {
             if t.ixlist.borrow().len() > 0 {
                 panic!("alter table indexes have to be dropped first");
             }
+            if !actions.is_empty() && !t.triggers.borrow().is_empty() {
+                panic!("alter table triggers have to be dropped first");
+            }
+            if !t.fkeys.borrow().is_empty() || !t.refs.borrow().is_empty() {
+                panic!("alter table with foreign keys is not supported");
+            }
+            let constrained = !t.defaults.borrow().is_empty() || !t.checks.borrow().is_empty();
+            if constrained && actions.iter().any(|a| matches!(a, AlterCol::Drop(_))) {
+                panic!("alter table DROP with DEFAULT or CHECK constraints is not supported");
+            }
 
             for act in actions {
                 match act {
@@ -594,10 +1120,14 @@ impl<'r> EvalEnv<'r> {
                 }
                 nt.insert(db, &mut newrow);
             }
+            t.triggers.borrow_mut().clear(); // Rows are cleared without firing triggers.
             let sql = format!("EXEC sys.ClearTable({})", t.id);
             db.run(&sql, self.tr);
             t.free_pages(db);
             sys::set_root(db, nt.id, root);
+            sys::get_foreign_keys(db, &nt, name);
+            sys::get_constraints(db, &nt);
+            sys::get_triggers(db, &nt, name);
 
             db.tables.borrow_mut().remove(name);
             db.tables.borrow_mut().insert(name.clone(), nt);
@@ -607,3 +1137,26 @@ impl<'r> EvalEnv<'r> {
         }
     }
 } // impl EvalEnv
+
+/// Get the error message from a caught panic.
+pub(crate) fn panic_message(x: &Box<dyn std::any::Any + Send>) -> String {
+    if let Some(e) = x.downcast_ref::<SqlError>() {
+        e.msg.clone()
+    } else if let Some(s) = x.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = x.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unrecognised/unexpected error".to_string()
+    }
+}
+
+/// Panic with error for failed CHECK constraint.
+fn check_failed(t: &Table, source: &str, id: i64) -> ! {
+    panic!(
+        "CHECK constraint {} failed for {} Id {}",
+        source,
+        t.info.name.str(),
+        id
+    )
+}
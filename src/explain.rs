@@ -0,0 +1,215 @@
+use crate::*;
+use Instruction::*;
+
+/// Describe a list of instructions ( for EXPLAIN ).
+/// Jump targets are converted to positions in the list using the block jump table, offset by base.
+pub fn explain(b: &Block, ilist: &[Instruction], base: usize) -> Vec<String> {
+    let mut x = Explainer {
+        db: &b.db,
+        lines: Vec::new(),
+    };
+    // Jumps out of the list ( e.g. BREAK ) are shown as "?".
+    let target = |id: usize| match b.jump_target(id).checked_sub(base) {
+        Some(ip) if ip <= ilist.len() => ip.to_string(),
+        _ => "?".to_string(),
+    };
+    for (ip, i) in ilist.iter().enumerate() {
+        let s = match i {
+            PushConst(v) => format!("PushConst {}", v.str()),
+            PushValue(_) => "PushValue".to_string(),
+            PushLocal(n) => format!("PushLocal {}", n),
+            PopToLocal(n) => format!("PopToLocal {}", n),
+            Jump(id) => format!("Jump {}", target(*id)),
+            JumpIfFalse(id, _) => format!("JumpIfFalse {}", target(*id)),
+            Call(_) => "Call".to_string(),
+            Return => "Return".to_string(),
+            Throw => "Throw".to_string(),
+            Try(id) => format!("Try catch at {}", target(*id)),
+            Execute => "Execute".to_string(),
+            ForInit(n, _) => format!("ForInit {}", n),
+            ForNext(id, info) => format!("ForNext {} exit at {}", info.for_id, target(*id)),
+            ForSortInit(n, cse) => {
+                if cse.orderby.is_empty() {
+                    format!("ForSortInit {}", n)
+                } else {
+                    format!("ForSortInit {} sort by {} keys", n, cse.orderby.len())
+                }
+            }
+            ForSortNext(id, info) => format!("ForSortNext {} exit at {}", info.0, target(*id)),
+            DataOp(dop) => format!("DataOp {}", do_name(dop)),
+            Select(cse) => format!("Select {} columns", cse.exps.len()),
+            Set(cse) => format!("Set {} locals", cse.assigns.len()),
+            PushInt(_) => "PushInt".to_string(),
+            PushFloat(_) => "PushFloat".to_string(),
+            PushBool(_) => "PushBool".to_string(),
+            AssignLocal(n, _) => format!("AssignLocal {}", n),
+            AppendLocal(n, _) => format!("AppendLocal {}", n),
+            IncLocal(n, _) => format!("IncLocal {}", n),
+            DecLocal(n, _) => format!("DecLocal {}", n),
+        };
+        x.lines.push(format!("{}: {}", ip, s));
+        match i {
+            ForInit(_, te) => x.table(te, 1),
+            ForSortInit(_, cse) | Select(cse) | Set(cse) => x.from(cse, 1),
+            DataOp(dop) => match &**dop {
+                DO::Insert(_, _, te) | DO::Update(_, te, _) | DO::Delete(te, _) => x.table(te, 1),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    x.lines
+}
+
+/// Name of a Data Operation.
+fn do_name(dop: &DO) -> String {
+    match dop {
+        DO::Insert(t, _, _) => format!("Insert {}", t.info.name.str()),
+        DO::Update(..) => "Update".to_string(),
+        DO::Delete(..) => "Delete".to_string(),
+        DO::CreateTable(ci) => format!("CreateTable {}", ci.name.str()),
+        DO::CreateIndex(ii) => format!("CreateIndex {}", ii.iname),
+        DO::CreateForeignKey(_) => "CreateForeignKey".to_string(),
+        DO::CreateConstraint(_) => "CreateConstraint".to_string(),
+        DO::CreateTrigger(tr) => format!("CreateTrigger {}", tr.name),
+        DO::CreateSchema(name) => format!("CreateSchema {}", name),
+        DO::CreateFunction(name, _, _) => format!("CreateFunction {}", name.str()),
+        DO::CreateView(name, _) => format!("CreateView {}", name.str()),
+        DO::AlterTable(name, _) => format!("AlterTable {}", name.str()),
+        DO::DropSchema(name) => format!("DropSchema {}", name),
+        DO::DropTable(name) => format!("DropTable {}", name.str()),
+        DO::DropIndex(name, ix) => format!("DropIndex {} {}", ix, name.str()),
+        DO::DropFunction(name) => format!("DropFunction {}", name.str()),
+        DO::DropView(name) => format!("DropView {}", name.str()),
+        DO::DropTrigger(name, tr) => format!("DropTrigger {} {}", tr, name.str()),
+        DO::Savepoint(name) => format!("Savepoint {}", name),
+        DO::RollbackTo(name) => format!("RollbackTo {}", name),
+    }
+}
+
+/// Accumulates description of access paths.
+struct Explainer<'a> {
+    db: &'a DB,
+    lines: Vec<String>,
+}
+
+impl Explainer<'_> {
+    fn add(&mut self, depth: usize, s: String) {
+        self.lines.push(format!("{}{}", "  ".repeat(depth), s));
+    }
+
+    /// Name of index, and the leading key columns used.
+    fn index(&self, t: &Table, ix: usize, n: usize) -> String {
+        let list = &*t.ixlist.borrow();
+        let index = &list[ix];
+        let cols: Vec<&str> = index
+            .cols
+            .iter()
+            .take(n)
+            .map(|c| t.info.colnames[*c].as_str())
+            .collect();
+        let name = sys::get_index_name(self.db, index.id);
+        if cols.is_empty() {
+            name
+        } else {
+            format!("{} key ({})", name, cols.join(","))
+        }
+    }
+
+    fn from(&mut self, cse: &CFromExpression, depth: usize) {
+        if !cse.orderby.is_empty() {
+            self.add(depth, format!("Sort by {} keys", cse.orderby.len()));
+        }
+        if let Some(te) = &cse.from {
+            self.table(te, depth);
+        }
+    }
+
+    fn table(&mut self, te: &CTableExpression, depth: usize) {
+        match te {
+            CTableExpression::Select(cse) => {
+                self.add(depth, "Select".to_string());
+                self.from(cse, depth + 1);
+            }
+            CTableExpression::Base(t) => self.add(depth, format!("Scan {}", t.info.name.str())),
+            CTableExpression::IdGet(t, _) => {
+                self.add(depth, format!("Id lookup {}", t.info.name.str()))
+            }
+            CTableExpression::IxGet(t, keys, ix) => {
+                let s = format!(
+                    "Index lookup {} index {}",
+                    t.info.name.str(),
+                    self.index(t, *ix, keys.len())
+                );
+                self.add(depth, s);
+            }
+            CTableExpression::IxRange(t, r) => {
+                let rcol = t.ixlist.borrow()[r.index].cols.get(r.keys.len()).copied();
+                let s = format!(
+                    "Index range {} index {}{}{}",
+                    t.info.name.str(),
+                    self.index(t, r.index, r.keys.len()),
+                    match rcol {
+                        Some(c) if r.lower.is_some() || r.upper.is_some() => {
+                            format!(" range {}", t.info.colnames[c])
+                        }
+                        _ => String::new(),
+                    },
+                    if r.desc { " desc" } else { "" }
+                );
+                self.add(depth, s);
+            }
+            CTableExpression::Values(rows) => {
+                self.add(depth, format!("Values {} rows", rows.len()))
+            }
+            CTableExpression::Join(j) => {
+                self.add(depth, "Join".to_string());
+                let (alias, t, _) = &j.tables[0];
+                if let Some(d) = &j.derived[0] {
+                    self.add(depth + 1, format!("View {} {}", t.info.name.str(), alias));
+                    self.from(&d.select, depth + 2);
+                } else {
+                    self.add(depth + 1, format!("Scan {} {}", t.info.name.str(), alias));
+                }
+                for (i, step) in j.steps.iter().enumerate() {
+                    let (alias, t, _) = &j.tables[i + 1];
+                    if let Some(d) = &j.derived[i + 1] {
+                        let outer = if step.outer { "Left " } else { "" };
+                        let s = format!("{}View {} {}", outer, t.info.name.str(), alias);
+                        self.add(depth + 1, s);
+                        self.from(&d.select, depth + 2);
+                        continue;
+                    }
+                    let (kind, access) = match &step.access {
+                        JoinAccess::Id(_) => ("Id lookup", String::new()),
+                        JoinAccess::Index(ix, keys) => (
+                            "Index lookup",
+                            format!(" index {}", self.index(t, *ix, keys.len())),
+                        ),
+                        JoinAccess::Scan => ("Scan", String::new()),
+                    };
+                    let s = format!(
+                        "{}{} {} {}{}",
+                        if step.outer { "Left " } else { "" },
+                        kind,
+                        t.info.name.str(),
+                        alias,
+                        access
+                    );
+                    self.add(depth + 1, s);
+                }
+            }
+            CTableExpression::Derived(d) => {
+                let alias = d.alias.as_deref().unwrap_or("");
+                self.add(depth, format!("Derived {}", alias));
+                self.from(&d.select, depth + 1);
+            }
+            CTableExpression::KeyList(list) => {
+                self.add(depth, "Key list".to_string());
+                for te in list {
+                    self.table(te, depth + 1);
+                }
+            }
+        }
+    }
+}
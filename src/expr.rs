@@ -1,5 +1,5 @@
 use crate::*;
-use Instruction::{DataOp, ForNext, ForSortNext, Jump, JumpIfFalse};
+use Instruction::{DataOp, ForNext, ForSortNext, Jump, JumpIfFalse, Try};
 
 /// Holds function name, line, column and message.
 #[derive(Clone)]
@@ -10,13 +10,32 @@ pub(crate) struct SqlError {
     pub msg: String,
 }
 /// Table Expression ( not yet type-checked or compiled against database ).
+#[derive(Clone)]
 pub enum TableExpression {
-    // Select( FromExpression ),
+    /// SELECT expression, with optional alias ( for a derived table in a FROM clause ).
+    Select(Box<FromExpression>, Option<String>),
     ///
     Base(ObjRef),
     ///
     Values(Vec<Vec<Expr>>),
+    /// Tables joined by INNER JOIN / LEFT JOIN ( or a single table with an alias ).
+    Join(Vec<JoinTable>),
 }
+
+/// Table in a FROM clause join ( not yet compiled ).
+#[derive(Clone)]
+#[non_exhaustive]
+pub struct JoinTable {
+    /// Table name.
+    pub name: ObjRef,
+    /// Name used to qualify column names ( alias, or table name if there is no alias ).
+    pub alias: String,
+    /// LEFT JOIN : rows with no match are joined with NULL values.
+    pub outer: bool,
+    /// ON condition ( None for the first table ).
+    pub on: Option<Expr>,
+}
+
 /// Assign operation.
 #[derive(Clone, Copy)]
 #[non_exhaustive]
@@ -34,6 +53,7 @@ pub enum AssignOp {
 pub type Assigns = Vec<(usize, AssignOp)>;
 
 /// From Expression ( not yet compiled ).
+#[derive(Clone)]
 #[non_exhaustive]
 pub struct FromExpression {
     ///
@@ -48,6 +68,10 @@ pub struct FromExpression {
     pub wher: Option<Expr>,
     ///
     pub orderby: Vec<(Expr, bool)>,
+    /// LIMIT ( maximum number of rows ).
+    pub limit: Option<Expr>,
+    /// OFFSET ( number of rows to skip ).
+    pub offset: Option<Expr>,
 }
 
 /// Parsing token.
@@ -85,6 +109,12 @@ pub enum Token {
     ///
     Or,
     ///
+    Is,
+    ///
+    Between,
+    /// LIKE ( or NOT LIKE ).
+    Like,
+    ///
     VBarEqual,
     ///
     PlusEqual,
@@ -94,6 +124,8 @@ pub enum Token {
     Id,
     ///
     Number,
+    /// Number with a decimal point.
+    Decimal,
     ///
     Hex,
     ///
@@ -119,12 +151,15 @@ pub enum Token {
 impl Token {
     ///
     pub fn precedence(self) -> i8 {
-        const PA: [i8; 15] = [10, 10, 10, 10, 10, 10, 10, 20, 20, 30, 30, 30, 15, 8, 5];
+        const PA: [i8; 18] = [
+            10, 10, 10, 10, 10, 10, 10, 20, 20, 30, 30, 30, 15, 8, 5, 10, 10, 10,
+        ];
         PA[self as usize]
     }
 }
 
 /// Scalar Expression (uncompiled).
+#[derive(Clone)]
 #[non_exhaustive]
 pub struct Expr {
     ///
@@ -153,6 +188,7 @@ impl Expr {
 }
 
 /// Scalar Expression variants.
+#[derive(Clone)]
 #[non_exhaustive]
 pub enum ExprIs {
     ///
@@ -177,6 +213,12 @@ pub enum ExprIs {
     ScalarSelect(Box<FromExpression>),
     ///
     List(Vec<Expr>),
+    /// IS NULL test.
+    IsNull(Box<Expr>),
+    /// LIKE test : expression, pattern, ESCAPE character and whether it is NOT LIKE.
+    Like(Box<Expr>, Box<Expr>, Option<char>, bool),
+    /// CAST ( or TRY_CAST if flag is true ) : expression and target type.
+    Cast(Box<Expr>, DataType, bool),
 }
 
 /// Object reference ( Schema.Name ).
@@ -203,7 +245,7 @@ impl ObjRef {
     }
 }
 
-/// Binary=1, String=2, Int=3, Float=4, Bool=5.
+/// Binary=1, String=2, Int=3, Float=4, Bool=5, DateTime=6, Decimal=7.
 #[derive(Debug, PartialEq, Eq, PartialOrd, Clone, Copy)]
 #[non_exhaustive]
 pub enum DataKind {
@@ -219,9 +261,14 @@ pub enum DataKind {
     Float = 4,
     ///
     Bool = 5,
+    /// Microseconds since 1970-01-01 UTC, evaluated as an int.
+    DateTime = 6,
+    /// Exact number stored as an int scaled by a power of ten ( see [crate::decimal] ).
+    Decimal = 7,
 }
 
-/// Low 3 (KBITS) bits are DataKind, rest is size in bytes.
+/// Low 3 (KBITS) bits are DataKind, next 8 bits are size in bytes, bit 16 is set for nullable types.
+/// For decimal types bits 11-15 are the scale and bits 17-21 are the precision.
 pub type DataType = usize;
 
 pub(crate) const KBITS: usize = 3;
@@ -234,16 +281,22 @@ pub(crate) const INT: DataType = DataKind::Int as usize + (8 << KBITS);
 pub(crate) const FLOAT: DataType = DataKind::Float as usize + (4 << KBITS);
 pub(crate) const DOUBLE: DataType = DataKind::Float as usize + (8 << KBITS);
 pub(crate) const BOOL: DataType = DataKind::Bool as usize + (1 << KBITS);
+pub(crate) const DATETIME: DataType = DataKind::DateTime as usize + (8 << KBITS);
+pub(crate) const DECIMAL: DataType = decimal_type(18, 0);
+/// Flag set in a nullable DataType.
+pub(crate) const NULLABLE: DataType = 1 << 16;
 
 /// Compute the DataKind of a DataType.
 pub fn data_kind(x: DataType) -> DataKind {
-    const DKLOOK: [DataKind; 6] = [
+    const DKLOOK: [DataKind; 8] = [
         DataKind::None,
         DataKind::Binary,
         DataKind::String,
         DataKind::Int,
         DataKind::Float,
         DataKind::Bool,
+        DataKind::DateTime,
+        DataKind::Decimal,
     ];
     DKLOOK[x % (1 << KBITS)]
 }
@@ -251,7 +304,27 @@ pub fn data_kind(x: DataType) -> DataKind {
 /// Compute the number of bytes required to store a value of the specified DataType.
 #[must_use]
 pub fn data_size(x: DataType) -> usize {
-    x >> KBITS
+    (x >> KBITS) % 256
+}
+
+/// decimal(p,s) DataType.
+pub(crate) const fn decimal_type(p: usize, s: usize) -> DataType {
+    DataKind::Decimal as usize + (8 << KBITS) + (s << 11) + (p << 17)
+}
+
+/// Scale of a decimal DataType ( number of digits after the decimal point ).
+pub fn data_scale(x: DataType) -> usize {
+    (x >> 11) % 32
+}
+
+/// Precision of a decimal DataType ( maximum number of digits ).
+pub fn data_precision(x: DataType) -> usize {
+    (x >> 17) % 32
+}
+
+/// Is the DataType nullable ( values may be NULL ).
+pub fn nullable(x: DataType) -> bool {
+    x & NULLABLE != 0
 }
 
 /// Compilation block ( body of function or batch section ).
@@ -264,6 +337,10 @@ pub struct Block<'a> {
     pub local_typ: Vec<DataType>,
     /// List of instructions.
     pub ilist: Vec<Instruction>,
+    /// Source line of each instruction ( for profiling ).
+    pub lines: Vec<usize>,
+    /// Source line of the current statement.
+    pub line: usize,
     /// Id of break.
     pub break_id: usize,
     /// Database.
@@ -287,6 +364,8 @@ impl<'a> Block<'a> {
     pub fn new(db: DB) -> Self {
         Block {
             ilist: Vec::new(),
+            lines: Vec::new(),
+            line: 0,
             jumps: Vec::new(),
             labels: HashMap::default(),
             local_map: HashMap::default(),
@@ -310,7 +389,7 @@ impl<'a> Block<'a> {
         }
         for i in &mut self.ilist {
             match i {
-                JumpIfFalse(x, _) | Jump(x) | ForNext(x, _) | ForSortNext(x, _) => {
+                JumpIfFalse(x, _) | Jump(x) | Try(x) | ForNext(x, _) | ForSortNext(x, _) => {
                     *x = self.jumps[*x]
                 }
                 _ => {}
@@ -318,10 +397,16 @@ impl<'a> Block<'a> {
         }
     }
 
+    /// Get the instruction position for a jump id ( for EXPLAIN ).
+    pub fn jump_target(&self, id: usize) -> usize {
+        self.jumps[id]
+    }
+
     /// Add an instruction to the instruction list.
     pub fn add(&mut self, s: Instruction) {
         if !self.parse_only {
             self.ilist.push(s);
+            self.lines.push(self.line);
         }
     }
 
@@ -338,9 +423,13 @@ impl<'a> Block<'a> {
             panic!("param count mismatch");
         }
         for (i, pk) in pkinds.iter().enumerate() {
-            let ft = data_kind(r.local_typ[i]);
+            let ft = data_kind(r.local_typ.borrow()[i]);
             let et = *pk;
-            if ft != et {
+            // NULL ( DataKind::None ) can be passed for a nullable parameter, and int for a decimal parameter.
+            if ft != et
+                && !(et == DataKind::None && nullable(r.local_typ.borrow()[i]))
+                && !(et == DataKind::Int && ft == DataKind::Decimal)
+            {
                 panic!("param type mismatch expected {:?} got {:?}", ft, et);
             }
         }
@@ -359,6 +448,14 @@ impl<'a> Block<'a> {
         self.local_map.insert(name, local_id);
     }
 
+    /// Allocate an unnamed local variable ( for FOR loop state, derived table rows etc. ).
+    pub fn alloc_local(&mut self) -> usize {
+        let local_id = self.local_typ.len();
+        self.local_typ.push(NONE);
+        self.locals.push(b"");
+        local_id
+    }
+
     /// Get the number of a local variable from a name.
     pub fn get_local(&self, name: &[u8]) -> Option<&usize> {
         self.local_map.get(name)
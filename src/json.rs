@@ -0,0 +1,492 @@
+//! JSON support.
+//!
+//! Builtin functions:
+//!
+//! JSONVALUE(doc, path) : value at path, e.g. JSONVALUE('{"a":[1,{"b":"x"}]}', '$.a[1].b') is 'x'.
+//! A string is unescaped, a number or boolean is returned as written, an object or array is returned as JSON text.
+//! The result is an empty string if the value is null or the path is not found.
+//!
+//! JSONESCAPE(s) : s escaped for use inside a JSON string literal ( without the enclosing quotes ).
+//!
+//! JSONARRAY(v1, v2, ...) : JSON array of values.
+//!
+//! JSONOBJECT(name1, v1, name2, v2, ...) : JSON object with string names.
+//!
+//! Values of JSONARRAY and JSONOBJECT are converted to JSON according to their type : strings ( and binary values
+//! as hex, datetime values as ISO 8601 ) are quoted, NULL is null. An argument which is itself a call to
+//! JSONARRAY or JSONOBJECT is included as is, so they can be nested.
+//!
+//! SELECT ... FOR JSON outputs the result as a single value, a JSON array with an object for each row,
+//! keyed by the column names. An unnamed column ( an expression without an alias ) is named colN, where N is the
+//! column number.
+
+use crate::{builtin::check_types, parse::to_s, *};
+
+/// Append s to out with JSON escapes.
+pub fn escape(s: &str, out: &mut String) {
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Append s to out as a JSON string literal.
+fn quote(s: &str, out: &mut String) {
+    out.push('"');
+    escape(s, out);
+    out.push('"');
+}
+
+/// Append JSON for a value of type t to out.
+pub fn value(v: &Value, t: DataType, out: &mut String) {
+    match v {
+        Value::Null => out.push_str("null"),
+        Value::Int(x) if data_kind(t) == DataKind::DateTime => quote(&datetime::to_iso(*x), out),
+        Value::Float(x) if !x.is_finite() => out.push_str("null"),
+        Value::Int(_) | Value::Float(_) | Value::Decimal(_, _) | Value::Bool(_) => {
+            out.push_str(&v.str())
+        }
+        _ => quote(&v.str(), out),
+    }
+}
+
+/// Names for the columns of a SELECT ... FOR JSON, an unnamed column is named colN ( N is the column number ).
+/// Identifiers cannot contain digits, so the name cannot clash with another column.
+fn names(colnames: &[String]) -> Vec<String> {
+    colnames
+        .iter()
+        .enumerate()
+        .map(|(j, name)| {
+            if name.is_empty() {
+                format!("col{}", j + 1)
+            } else {
+                name.clone()
+            }
+        })
+        .collect()
+}
+
+/// JSON array of objects for rows of a SELECT ... FOR JSON.
+pub fn rows(colnames: &[String], types: &[DataType], rows: &[Vec<Value>]) -> String {
+    let colnames = names(colnames);
+    let mut out = String::from("[");
+    for (i, row) in rows.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push('{');
+        for (j, v) in row.iter().enumerate() {
+            if j > 0 {
+                out.push(',');
+            }
+            quote(&colnames[j], &mut out);
+            out.push(':');
+            value(v, types[j], &mut out);
+        }
+        out.push('}');
+    }
+    out.push(']');
+    out
+}
+
+/// Path step.
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+/// Parse a path such as $.a[1].b ( the leading $ is optional ).
+fn parse_path(path: &str) -> Vec<Step> {
+    let bad = || -> ! { panic!("invalid JSON path '{}'", path) };
+    let s = path.strip_prefix('$').unwrap_or(path);
+    let s = s.as_bytes();
+    let mut steps = Vec::new();
+    let mut i = 0;
+    while i < s.len() {
+        match s[i] {
+            b'[' => {
+                let end = i + s[i..]
+                    .iter()
+                    .position(|c| *c == b']')
+                    .unwrap_or_else(|| bad());
+                let n = std::str::from_utf8(&s[i + 1..end]).unwrap();
+                steps.push(Step::Index(n.trim().parse().unwrap_or_else(|_| bad())));
+                i = end + 1;
+            }
+            c => {
+                if c == b'.' {
+                    i += 1;
+                } else if i > 0 {
+                    bad();
+                }
+                let start = i;
+                while i < s.len() && s[i] != b'.' && s[i] != b'[' {
+                    i += 1;
+                }
+                if i == start {
+                    bad();
+                }
+                steps.push(Step::Key(to_s(&s[start..i])));
+            }
+        }
+    }
+    steps
+}
+
+/// Check a JSON number : optional -, 0 or digits not starting with 0, optional fraction, optional exponent.
+fn is_number(v: &[u8]) -> bool {
+    let mut i = usize::from(v.first() == Some(&b'-'));
+    let digits = |i: &mut usize| {
+        let start = *i;
+        while *i < v.len() && v[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        *i > start
+    };
+    if v.get(i) == Some(&b'0') {
+        i += 1;
+    } else if !digits(&mut i) {
+        return false;
+    }
+    if v.get(i) == Some(&b'.') {
+        i += 1;
+        if !digits(&mut i) {
+            return false;
+        }
+    }
+    if matches!(v.get(i), Some(b'e' | b'E')) {
+        i += 1;
+        if matches!(v.get(i), Some(b'+' | b'-')) {
+            i += 1;
+        }
+        if !digits(&mut i) {
+            return false;
+        }
+    }
+    i == v.len()
+}
+
+/// Scanner for JSON text.
+struct Scan<'a> {
+    s: &'a [u8],
+    i: usize,
+}
+
+impl Scan<'_> {
+    fn bad(&self) -> ! {
+        panic!("invalid JSON at position {}", self.i)
+    }
+
+    fn skip_space(&mut self) {
+        while self.i < self.s.len() && matches!(self.s[self.i], b' ' | b'\n' | b'\r' | b'\t') {
+            self.i += 1;
+        }
+    }
+
+    fn peek(&mut self) -> u8 {
+        self.skip_space();
+        if self.i < self.s.len() {
+            self.s[self.i]
+        } else {
+            0
+        }
+    }
+
+    fn expect(&mut self, c: u8) {
+        if self.peek() != c {
+            self.bad();
+        }
+        self.i += 1;
+    }
+
+    /// Read a string literal, result is the unescaped string.
+    fn string(&mut self) -> String {
+        self.expect(b'"');
+        let mut bytes = Vec::new();
+        loop {
+            let Some(&c) = self.s.get(self.i) else {
+                self.bad()
+            };
+            self.i += 1;
+            match c {
+                b'"' => break,
+                b'\\' => {
+                    let Some(&e) = self.s.get(self.i) else {
+                        self.bad()
+                    };
+                    self.i += 1;
+                    let c = match e {
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'u' => {
+                            let mut u = self.hex4();
+                            if (0xd800..0xdc00).contains(&u) && self.s[self.i..].starts_with(b"\\u")
+                            {
+                                self.i += 2;
+                                let lo = self.hex4();
+                                u = 0x10000
+                                    + ((u - 0xd800) << 10)
+                                    + (lo.wrapping_sub(0xdc00) & 0x3ff);
+                            }
+                            char::from_u32(u).unwrap_or('\u{fffd}')
+                        }
+                        e => e as char,
+                    };
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+                c => bytes.push(c),
+            }
+        }
+        String::from_utf8(bytes).unwrap_or_else(|_| self.bad())
+    }
+
+    fn hex4(&mut self) -> u32 {
+        let Some(h) = self.s.get(self.i..self.i + 4) else {
+            self.bad()
+        };
+        self.i += 4;
+        u32::from_str_radix(std::str::from_utf8(h).unwrap_or("?"), 16)
+            .unwrap_or_else(|_| self.bad())
+    }
+
+    /// Skip a value.
+    fn skip(&mut self) {
+        match self.peek() {
+            b'"' => {
+                self.string();
+            }
+            c @ (b'{' | b'[') => {
+                let close = if c == b'{' { b'}' } else { b']' };
+                self.i += 1;
+                if self.peek() == close {
+                    self.i += 1;
+                    return;
+                }
+                loop {
+                    if c == b'{' {
+                        self.string();
+                        self.expect(b':');
+                    }
+                    self.skip();
+                    match self.peek() {
+                        b',' => self.i += 1,
+                        x if x == close => {
+                            self.i += 1;
+                            return;
+                        }
+                        _ => self.bad(),
+                    }
+                }
+            }
+            _ => {
+                let start = self.i;
+                while self.i < self.s.len()
+                    && matches!(self.s[self.i], b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'+' | b'.')
+                {
+                    self.i += 1;
+                }
+                let v = &self.s[start..self.i];
+                if !matches!(v, b"true" | b"false" | b"null") && !is_number(v) {
+                    self.i = start;
+                    self.bad();
+                }
+            }
+        }
+    }
+
+    /// Move to the element of an object ( or array ), result is false if not found.
+    fn find(&mut self, step: &Step) -> bool {
+        match (self.peek(), step) {
+            (b'{', Step::Key(k)) => {
+                self.i += 1;
+                if self.peek() == b'}' {
+                    return false;
+                }
+                loop {
+                    let name = self.string();
+                    self.expect(b':');
+                    if name == *k {
+                        return true;
+                    }
+                    self.skip();
+                    if self.peek() != b',' {
+                        return false;
+                    }
+                    self.i += 1;
+                }
+            }
+            (b'[', Step::Index(n)) => {
+                self.i += 1;
+                if self.peek() == b']' {
+                    return false;
+                }
+                for _ in 0..*n {
+                    self.skip();
+                    if self.peek() != b',' {
+                        return false;
+                    }
+                    self.i += 1;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Get the value at path in a JSON document ( see JSONVALUE ).
+pub fn get(doc: &str, path: &str) -> String {
+    let mut sc = Scan {
+        s: doc.as_bytes(),
+        i: 0,
+    };
+    // Check the whole document is valid.
+    sc.skip();
+    if sc.peek() != 0 || sc.i < sc.s.len() {
+        sc.bad();
+    }
+    sc.i = 0;
+    for step in &parse_path(path) {
+        if !sc.find(step) {
+            return String::new();
+        }
+    }
+    match sc.peek() {
+        b'"' => sc.string(),
+        _ => {
+            let start = sc.i;
+            sc.skip();
+            let v = &doc[start..sc.i];
+            if v == "null" {
+                String::new()
+            } else {
+                v.to_string()
+            }
+        }
+    }
+}
+
+/////////////////////////////
+/// Compile call to JSONVALUE.
+pub(crate) fn c_json_value(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_types(b, args, &[DataKind::String, DataKind::String]);
+    let doc = c_value(b, &mut args[0]);
+    let path = c_value(b, &mut args[1]);
+    Box::new(JsonValue { doc, path })
+}
+struct JsonValue {
+    doc: CExpPtr<Value>,
+    path: CExpPtr<Value>,
+}
+impl CExp<Value> for JsonValue {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> Value {
+        let doc = self.doc.eval(e, d).str();
+        let path = self.path.eval(e, d).str();
+        Value::String(Rc::new(get(&doc, &path)))
+    }
+}
+/////////////////////////////
+/// Compile call to JSONESCAPE.
+pub(crate) fn c_json_escape(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_types(b, args, &[DataKind::String]);
+    let s = c_value(b, &mut args[0]);
+    Box::new(JsonEscape { s })
+}
+struct JsonEscape {
+    s: CExpPtr<Value>,
+}
+impl CExp<Value> for JsonEscape {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> Value {
+        let s = self.s.eval(e, d).str();
+        let mut out = String::with_capacity(s.len());
+        escape(&s, &mut out);
+        Value::String(Rc::new(out))
+    }
+}
+/////////////////////////////
+/// Compile JSONARRAY or JSONOBJECT argument, result includes its type, and whether it is nested JSON.
+fn c_json_arg(b: &Block, e: &mut Expr) -> (CExpPtr<Value>, DataType, bool) {
+    let nested =
+        matches!(&e.exp, ExprIs::BuiltinCall(n, _) if n == "JSONARRAY" || n == "JSONOBJECT");
+    let ce = c_value(b, e);
+    (ce, e.data_type, nested)
+}
+/// Compile call to JSONARRAY.
+pub(crate) fn c_json_array(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    let list = args.iter_mut().map(|e| c_json_arg(b, e)).collect();
+    Box::new(JsonArray { list })
+}
+struct JsonArray {
+    list: Vec<(CExpPtr<Value>, DataType, bool)>,
+}
+impl CExp<Value> for JsonArray {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> Value {
+        let mut out = String::from("[");
+        for (i, (ce, t, nested)) in self.list.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            let v = ce.eval(e, d);
+            if *nested {
+                out.push_str(&v.str());
+            } else {
+                value(&v, *t, &mut out);
+            }
+        }
+        out.push(']');
+        Value::String(Rc::new(out))
+    }
+}
+/////////////////////////////
+/// Compile call to JSONOBJECT.
+pub(crate) fn c_json_object(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    if !args.len().is_multiple_of(2) {
+        panic!("JSONOBJECT needs name and value pairs");
+    }
+    let mut list = Vec::new();
+    for pair in args.chunks_mut(2) {
+        if b.kind(&mut pair[0]) != DataKind::String {
+            panic!("JSONOBJECT name must be string");
+        }
+        let name = c_value(b, &mut pair[0]);
+        list.push((name, c_json_arg(b, &mut pair[1])));
+    }
+    Box::new(JsonObject { list })
+}
+type JsonMember = (CExpPtr<Value>, (CExpPtr<Value>, DataType, bool));
+struct JsonObject {
+    list: Vec<JsonMember>,
+}
+impl CExp<Value> for JsonObject {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> Value {
+        let mut out = String::from("{");
+        for (i, (name, (ce, t, nested))) in self.list.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            quote(&name.eval(e, d).str(), &mut out);
+            out.push(':');
+            let v = ce.eval(e, d);
+            if *nested {
+                out.push_str(&v.str());
+            } else {
+                value(&v, *t, &mut out);
+            }
+        }
+        out.push('}');
+        Value::String(Rc::new(out))
+    }
+}
@@ -1,7 +1,7 @@
 //! This crate (rustdb) implements a high-performance database written entirely in [Rust](https://www.rust-lang.org/).
 //!
-//! The SQL-like language is relatively minimal, and does not (currently) include features such as joins or views.
-//! Instead it has high performance SET .. FROM ... and FOR .. FROM statements to access database tables,
+//! The SQL-like language is relatively minimal.
+//! Tables can be combined with INNER JOIN and LEFT JOIN, queries can be saved as views, and it has high performance SET .. FROM ... and FOR .. FROM statements to access database tables,
 //! generally using an INDEX.
 //!
 //! Read-only transactions run immediately and concurrently on a virtual read-only copy of the database, and cannot be blocked.
@@ -33,6 +33,7 @@
 //! - `verify` : Allows database structure to be verified using builtin function VERIFYDB.
 //! - `pack` : Allows database pages to be packed using builtin function REPACKFILE.
 //! - `renumber` : Allows database pages to be renumbered using builtin function RENUMBER, eliminating free pages.
+//! - `regex` : Regular expression builtin functions REGEXMATCH, REGEXREPLACE and REGEXEXTRACT ( uses the regex crate ).
 //! - `unsafe_opt` : Enable unsafe optimisations in release mode.
 //!
 //! By default, all features except unsafe_opt are enabled.
@@ -95,8 +96,11 @@
 pub use crate::{
     atomfile::AtomicFile,
     builtin::standard_builtins,
+    profile::ProfileEntry,
     pstore::{AccessPagedData, SharedPagedData},
+    run::Statement,
     stg::{MemFile, SimpleFileStorage, Storage},
+    value::Value,
 };
 
 #[cfg(feature = "gentrans")]
@@ -110,7 +114,6 @@ pub use crate::{
     expr::ObjRef,
     expr::{Block, DataKind, Expr},
     run::{CExp, CExpPtr, CompileFunc},
-    value::Value,
 };
 #[cfg(not(feature = "builtin"))]
 use crate::{
@@ -118,7 +121,6 @@ use crate::{
     exec::EvalEnv,
     expr::{Block, DataKind, Expr},
     run::{CExp, CExpPtr, CompileFunc},
-    value::Value,
 };
 
 use crate::{
@@ -127,9 +129,14 @@ use crate::{
     expr::*,
     page::{Page, PagePtr},
     parse::Parser,
+    profile::Profile,
     run::*,
+    savepoint::Savepoint,
     sortedfile::{Asc, Id, Record, SortedFile},
-    table::{ColInfo, IndexInfo, Row, SaveOp, Table},
+    table::{
+        ColInfo, Constraint, ConstraintKind, ForeignKey, IndexInfo, RefAction, Row, SaveOp, Table,
+        Trigger, TriggerEvent,
+    },
     util::{nd, newmap, SmallSet},
     value::*,
 };
@@ -272,12 +279,74 @@ pub mod bytes;
 #[cfg(not(feature = "max"))]
 mod bytes;
 
+#[cfg(feature = "max")]
+/// [Savepoint] : saved state for partial rollback.
+pub mod savepoint;
+#[cfg(not(feature = "max"))]
+mod savepoint;
+
+#[cfg(feature = "max")]
+/// EXPLAIN : description of compiled instructions and access paths.
+pub mod explain;
+#[cfg(not(feature = "max"))]
+mod explain;
+
+#[cfg(feature = "max")]
+/// [Profile] : execution statistics.
+pub mod profile;
+#[cfg(not(feature = "max"))]
+mod profile;
+
+#[cfg(feature = "max")]
+/// Date and time builtin functions.
+pub mod datetime;
+#[cfg(not(feature = "max"))]
+mod datetime;
+
+#[cfg(feature = "max")]
+/// Decimal arithmetic.
+pub mod decimal;
+#[cfg(not(feature = "max"))]
+mod decimal;
+
+#[cfg(feature = "max")]
+/// JSON builtin functions and output.
+pub mod json;
+#[cfg(not(feature = "max"))]
+mod json;
+
+#[cfg(feature = "max")]
+/// CAST and TRY_CAST conversions.
+pub mod cast;
+#[cfg(not(feature = "max"))]
+mod cast;
+
+#[cfg(feature = "max")]
+/// Numeric builtin functions.
+pub mod math;
+#[cfg(not(feature = "max"))]
+mod math;
+
 // End of modules.
 
 /// ```Arc<Vec<u8>>```
 pub type Data = Arc<Vec<u8>>;
 
+/// Convert a panic caught while parsing or executing a batch to a SqlError.
+fn sql_error(p: &Parser, x: Box<dyn std::any::Any + Send>) -> SqlError {
+    if let Some(e) = x.downcast_ref::<SqlError>() {
+        e.clone()
+    } else if let Some(s) = x.downcast_ref::<&str>() {
+        p.make_error((*s).to_string())
+    } else if let Some(s) = x.downcast_ref::<String>() {
+        p.make_error(s.to_string())
+    } else {
+        p.make_error("unrecognised/unexpected error".to_string())
+    }
+}
+
 /// Mutable Data, copied on write.
+#[derive(Clone)]
 pub struct MData(Data);
 
 impl MData {
@@ -308,6 +377,8 @@ impl std::ops::DerefMut for MData {
 pub type DB = Rc<Database>;
 
 /// Map that defines SQL pre-defined functions.
+///
+/// The [DataKind] is the kind of the result, DataKind::None means the result has the type of the first argument.
 pub type BuiltinMap = HashMap<String, (DataKind, CompileFunc)>;
 
 /// Database with SQL-like interface.
@@ -345,11 +416,17 @@ pub struct Database {
     pub err: Cell<bool>,
     /// Is the database new?
     pub is_new: bool,
+    /// Schema generation, incremented when tables, views, functions etc. change.
+    pub schema_gen: Cell<u64>,
 
     /// Storage of variable length data.
     bs: Vec<ByteStorage>,
     /// Flag to reset the functions cache after save.
     function_reset: Cell<bool>,
+    /// Savepoints since last save.
+    savepoints: RefCell<Vec<Savepoint>>,
+    /// Profiling state.
+    profile: Profile,
     /// Maximum size of logical page.
     page_size_max: usize,
 }
@@ -380,13 +457,13 @@ impl Database {
             "Function",
             &[("Schema", INT), ("Name", NAMESTR), ("Def", BIGSTR)],
         );
-        sys_schema.add_index(tb.rt(), vec![0], 1);
-        sys_table.add_index(tb.rt(), vec![1, 2], 2);
-        sys_column.add_index(tb.rt(), vec![0], 3);
-        sys_index.add_index(tb.rt(), vec![1], 4);
-        sys_index_col.add_index(tb.rt(), vec![0], 5);
-        sys_function.add_index(tb.rt(), vec![0, 1], 6);
-        sys_function.add_index(tb.rt(), vec![1], 7);
+        sys_schema.add_index(tb.rt(), vec![0], 1, false);
+        sys_table.add_index(tb.rt(), vec![1, 2], 2, false);
+        sys_column.add_index(tb.rt(), vec![0], 3, false);
+        sys_index.add_index(tb.rt(), vec![1], 4, false);
+        sys_index_col.add_index(tb.rt(), vec![0], 5, false);
+        sys_function.add_index(tb.rt(), vec![0, 1], 6, false);
+        sys_function.add_index(tb.rt(), vec![1], 7, false);
 
         let mut bs = Vec::new();
         for ft in 0..bytes::NFT {
@@ -409,9 +486,12 @@ impl Database {
             tables: newmap(),
             builtins,
             function_reset: Cell::new(false),
+            savepoints: RefCell::new(Vec::new()),
+            profile: Profile::default(),
             lastid: Cell::new(0),
             err: Cell::new(false),
             is_new,
+            schema_gen: Cell::new(0),
             page_size_max,
         });
 
@@ -452,6 +532,11 @@ GO
 ";
             let mut dq = DummyTransaction {};
             db.run(sysinit, &mut dq);
+            db.run(sys::VIEW_TABLE, &mut dq);
+            db.run(sys::FOREIGN_KEY_TABLE, &mut dq);
+            db.run(sys::CONSTRAINT_TABLE, &mut dq);
+            db.run(sys::TRIGGER_TABLE, &mut dq);
+            db.run(sys::UNIQUE_INDEX_TABLE, &mut dq);
             db.run(initsql, &mut dq);
             db.save();
         }
@@ -461,6 +546,10 @@ GO
 
     /// Run a batch of SQL.
     pub fn run(self: &DB, source: &str, tr: &mut dyn Transaction) {
+        let profile = self.profile.enabled.get();
+        if profile {
+            self.profile.begin();
+        }
         if let Some(e) = self.go(source, tr) {
             let err = format!(
                 "{} in {} at line {} column {}.",
@@ -469,6 +558,20 @@ GO
             tr.set_error(err);
             self.err.set(true);
         }
+        if profile {
+            self.profile.end(tr);
+        }
+    }
+
+    /// Enable or disable profiling. While enabled, execution statistics are collected for each batch,
+    /// passed to [Transaction]::profile and available from [Database::get_profile].
+    pub fn set_profile(self: &DB, on: bool) {
+        self.profile.enabled.set(on);
+    }
+
+    /// Get the execution statistics for the most recent batch ( when profiling is enabled ).
+    pub fn get_profile(self: &DB) -> Vec<ProfileEntry> {
+        self.profile.get()
     }
 
     /// Run a batch of SQL.
@@ -477,24 +580,22 @@ GO
         let result = std::panic::catch_unwind(panic::AssertUnwindSafe(|| {
             p.batch(tr);
         }));
-        if let Err(x) = result {
-            Some(if let Some(e) = x.downcast_ref::<SqlError>() {
-                SqlError {
-                    msg: e.msg.clone(),
-                    line: e.line,
-                    column: e.column,
-                    rname: e.rname.clone(),
-                }
-            } else if let Some(s) = x.downcast_ref::<&str>() {
-                p.make_error((*s).to_string())
-            } else if let Some(s) = x.downcast_ref::<String>() {
-                p.make_error(s.to_string())
-            } else {
-                p.make_error("unrecognised/unexpected error".to_string())
-            })
-        } else {
-            None
-        }
+        result.err().map(|x| sql_error(&p, x))
+    }
+
+    /// Parse and compile SQL for repeated execution by [Statement::execute].
+    /// Leading DECLARE statements declare the parameters, for example "DECLARE id int SELECT Name FROM dbo.Cust WHERE Id = id".
+    /// The statement has to be prepared again after the schema changes, executing it is an error.
+    pub fn prepare(self: &DB, source: &str) -> Result<Statement, String> {
+        let mut p = Parser::new(source, self);
+        let result = std::panic::catch_unwind(panic::AssertUnwindSafe(|| p.prepare()));
+        result.map_err(|x| {
+            let e = sql_error(&p, x);
+            format!(
+                "{} in {} at line {} column {}.",
+                e.msg, e.rname, e.line, e.column
+            )
+        })
     }
 
     /// Test whether there are unsaved changes.
@@ -527,6 +628,13 @@ GO
         } else {
             SaveOp::Save
         };
+        for sp in self.savepoints.borrow_mut().drain(..) {
+            if op == SaveOp::Save {
+                for pnum in sp.freed {
+                    self.apd.free_page(pnum);
+                }
+            }
+        }
         for bs in &self.bs {
             bs.save(self, op);
         }
@@ -631,14 +739,75 @@ GO
 
     /// Allocate a page of underlying file storage.
     fn alloc_page(self: &DB) -> u64 {
-        self.apd.alloc_page()
+        let pnum = self.apd.alloc_page();
+        if let Some(sp) = self.savepoints.borrow_mut().last_mut() {
+            sp.allocated.push(pnum);
+        }
+        pnum
     }
 
     /// Free a page of underlying file storage.
     fn free_page(self: &DB, lpnum: u64) {
+        if let Some(sp) = self.savepoints.borrow_mut().last_mut() {
+            // The page may be needed by ROLLBACK TO, so it is freed by save.
+            sp.freed.push(lpnum);
+            return;
+        }
         self.apd.free_page(lpnum);
     }
 
+    /// Record the current state, so that later changes can be undone by [Database::rollback_to].
+    pub fn savepoint(self: &DB, name: &str) {
+        let sp = Savepoint::new(self, name);
+        self.savepoints.borrow_mut().push(sp);
+    }
+
+    /// Undo the changes made since the named savepoint. The savepoint is kept.
+    /// The result is an error if there is no savepoint with the name.
+    pub fn rollback_to(self: &DB, name: &str) -> Result<(), String> {
+        let Some(i) = self
+            .savepoints
+            .borrow()
+            .iter()
+            .rposition(|sp| sp.name == name)
+        else {
+            return Err(format!("savepoint {} not found", name));
+        };
+        self.rollback_savepoint(i);
+        Ok(())
+    }
+
+    /// Undo the changes made since the savepoint at index i. The savepoint is kept.
+    pub(crate) fn rollback_savepoint(self: &DB, i: usize) {
+        let later: Vec<Savepoint> = self.savepoints.borrow_mut().drain(i + 1..).collect();
+        for sp in later {
+            // Pages allocated after the savepoint are not used.
+            for pnum in sp.allocated {
+                self.apd.free_page(pnum);
+            }
+        }
+        let sp = &mut self.savepoints.borrow_mut()[i];
+        for pnum in sp.allocated.drain(..) {
+            self.apd.free_page(pnum);
+        }
+        sp.freed.clear();
+        sp.restore(self);
+    }
+
+    /// Remove the savepoint at index i, keeping the changes made since.
+    pub(crate) fn release_savepoint(self: &DB, i: usize) {
+        let sps = &mut *self.savepoints.borrow_mut();
+        let sp = sps.remove(i);
+        if i > 0 {
+            sps[i - 1].allocated.extend(sp.allocated);
+            sps[i - 1].freed.extend(sp.freed);
+        } else {
+            for pnum in sp.freed {
+                self.apd.free_page(pnum);
+            }
+        }
+    }
+
     #[cfg(feature = "pack")]
     /// Get size of logical page.
     fn lp_size(&self, pnum: u64) -> u64 {
@@ -794,6 +963,9 @@ pub trait Transaction: Any {
     /// Set the error string.
     fn set_error(&mut self, err: String);
 
+    /// Receive execution statistics after a batch is run with profiling enabled.
+    fn profile(&mut self, _entries: &[ProfileEntry]) {}
+
     /// Get the error string.
     fn get_error(&mut self) -> String {
         String::new()
@@ -0,0 +1,230 @@
+//! Numeric builtin functions.
+//!
+//! ABS(x), SIGN(x), FLOOR(x), CEILING(x), ROUND(x [, n]) and MOD(x, y) : the result has the type of x, which may be
+//! int, float or decimal. ROUND rounds half away from zero to n decimal places ( default 0, may be negative ).
+//! MOD is the remainder with the sign of x, y must have the same type as x ( or be int if x is decimal ).
+//! The result is NULL if an argument is NULL.
+//!
+//! POWER(x, y), SQRT(x), LOG(x [, base]) : float result, the arguments may be int, float or decimal.
+//! LOG is the natural logarithm if no base is given. The result is NULL if an argument is NULL.
+
+use crate::{builtin::c_null_map, *};
+
+/// Check arguments are numeric ( or NULL ), count is the allowed range for the number of arguments.
+fn check_numeric(b: &Block, args: &mut [Expr], count: std::ops::RangeInclusive<usize>) {
+    if !count.contains(&args.len()) {
+        panic!("wrong number of args");
+    }
+    for (i, e) in args.iter_mut().enumerate() {
+        let k = b.kind(e);
+        if !(matches!(k, DataKind::Int | DataKind::Float | DataKind::Decimal)
+            || k == DataKind::None)
+        {
+            panic!(
+                "Builtin function arg {} type mismatch expected number got {:?}",
+                i + 1,
+                k
+            );
+        }
+    }
+}
+
+/// Panic if an int operation overflowed.
+fn checked(x: Option<i64>) -> i64 {
+    x.unwrap_or_else(|| panic!("integer overflow"))
+}
+
+/////////////////////////////
+/// Compile call to a numeric function with one argument.
+fn c_map(b: &Block, args: &mut [Expr], f: fn(&Value) -> Value) -> CExpPtr<Value> {
+    check_numeric(b, args, 1..=1);
+    let x = c_value(b, &mut args[0]);
+    Box::new(NumMap { x, f })
+}
+/// Compile call to ABS.
+pub(crate) fn c_abs(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    c_map(b, args, |v| match v {
+        Value::Int(x) => Value::Int(checked(x.checked_abs())),
+        Value::Float(x) => Value::Float(x.abs()),
+        Value::Decimal(x, s) => Value::Decimal(checked(x.checked_abs()), *s),
+        _ => panic!(),
+    })
+}
+/// Compile call to SIGN.
+pub(crate) fn c_sign(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    c_map(b, args, |v| match v {
+        Value::Int(x) => Value::Int(x.signum()),
+        Value::Float(x) if *x == 0.0 => Value::Float(0.0),
+        Value::Float(x) => Value::Float(x.signum()),
+        Value::Decimal(x, s) => Value::Decimal(decimal::rescale(x.signum(), 0, *s), *s),
+        _ => panic!(),
+    })
+}
+/// Compile call to FLOOR.
+pub(crate) fn c_floor(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    c_map(b, args, |v| match v {
+        Value::Float(x) => Value::Float(x.floor()),
+        Value::Decimal(x, s) => Value::Decimal(decimal::floor(*x, *s, false), *s),
+        _ => v.clone(),
+    })
+}
+/// Compile call to CEILING.
+pub(crate) fn c_ceiling(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    c_map(b, args, |v| match v {
+        Value::Float(x) => Value::Float(x.ceil()),
+        Value::Decimal(x, s) => Value::Decimal(decimal::floor(*x, *s, true), *s),
+        _ => v.clone(),
+    })
+}
+struct NumMap {
+    x: CExpPtr<Value>,
+    f: fn(&Value) -> Value,
+}
+impl CExp<Value> for NumMap {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> Value {
+        match self.x.eval(e, d) {
+            Value::Null => Value::Null,
+            v => (self.f)(&v),
+        }
+    }
+}
+/////////////////////////////
+/// Compile call to ROUND.
+pub(crate) fn c_round(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    if args.len() == 2 {
+        check_numeric(b, &mut args[0..1], 1..=1);
+        let k = b.kind(&mut args[1]);
+        if k != DataKind::Int && k != DataKind::None {
+            panic!(
+                "Builtin function arg 2 type mismatch expected Int got {:?}",
+                k
+            );
+        }
+    } else {
+        check_numeric(b, args, 1..=1);
+    }
+    let x = c_value(b, &mut args[0]);
+    let n = args.get_mut(1).map(|n| c_value(b, n));
+    Box::new(Round { x, n })
+}
+struct Round {
+    x: CExpPtr<Value>,
+    n: Option<CExpPtr<Value>>,
+}
+impl CExp<Value> for Round {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> Value {
+        let x = self.x.eval(e, d);
+        let n = match &self.n {
+            Some(n) => n.eval(e, d),
+            None => Value::Int(0),
+        };
+        match (x, n) {
+            (Value::Null, _) | (_, Value::Null) => Value::Null,
+            (Value::Int(x), Value::Int(n)) => Value::Int(round_int(x, n)),
+            (Value::Decimal(x, s), Value::Int(n)) => Value::Decimal(decimal::round(x, s, n), s),
+            (Value::Float(x), Value::Int(n)) => {
+                let m = 10_f64.powi(n.clamp(-400, 400) as i32);
+                Value::Float((x * m).round() / m)
+            }
+            _ => panic!(),
+        }
+    }
+}
+/// Round an int to n decimal places ( n may be negative ), half away from zero.
+fn round_int(x: i64, n: i64) -> i64 {
+    if n >= 0 {
+        return x;
+    }
+    if n < -19 {
+        // |x| is less than half of 10^20.
+        return 0;
+    }
+    let d = 10_i128.pow(-n as u32);
+    let (x, r) = (x as i128, x as i128 % d);
+    let q = if 2 * r.abs() >= d {
+        x - r + x.signum() * d
+    } else {
+        x - r
+    };
+    checked(i64::try_from(q).ok())
+}
+/////////////////////////////
+/// Compile call to MOD.
+pub(crate) fn c_mod(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_numeric(b, args, 2..=2);
+    let (k1, k2) = (b.kind(&mut args[0]), b.kind(&mut args[1]));
+    if k1 != k2
+        && (k1, k2) != (DataKind::Decimal, DataKind::Int)
+        && k1 != DataKind::None
+        && k2 != DataKind::None
+    {
+        panic!("MOD argument type mismatch");
+    }
+    let x = c_value(b, &mut args[0]);
+    let y = c_value(b, &mut args[1]);
+    Box::new(Mod { x, y })
+}
+struct Mod {
+    x: CExpPtr<Value>,
+    y: CExpPtr<Value>,
+}
+impl CExp<Value> for Mod {
+    fn eval(&self, e: &mut EvalEnv, d: &[u8]) -> Value {
+        let x = self.x.eval(e, d);
+        let y = self.y.eval(e, d);
+        match (&x, &y) {
+            (Value::Null, _) | (_, Value::Null) => Value::Null,
+            (Value::Int(_), Value::Int(0)) => panic!("division by zero"),
+            // i64::MIN % -1 overflows, the result is 0.
+            (Value::Int(x), Value::Int(y)) => Value::Int(x.checked_rem(*y).unwrap_or(0)),
+            (Value::Float(x), Value::Float(y)) => Value::Float(x % y),
+            _ => decimal::arithmetic(Token::Percent, &x, &y),
+        }
+    }
+}
+/////////////////////////////
+/// Convert a numeric value to a float.
+fn to_float(v: &Value) -> f64 {
+    match v {
+        Value::Int(x) => *x as f64,
+        Value::Decimal(x, s) => decimal::to_float(*x, *s),
+        v => v.float(),
+    }
+}
+/// Panic if the result of a float function is not a number.
+fn domain(name: &str, r: f64) -> Value {
+    if r.is_nan() {
+        panic!("{} argument out of range", name);
+    }
+    Value::Float(r)
+}
+/////////////////////////////
+/// Compile call to POWER.
+pub(crate) fn c_power(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_numeric(b, args, 2..=2);
+    c_null_map(b, args, |v| {
+        domain("POWER", to_float(&v[0]).powf(to_float(&v[1])))
+    })
+}
+/////////////////////////////
+/// Compile call to SQRT.
+pub(crate) fn c_sqrt(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_numeric(b, args, 1..=1);
+    c_null_map(b, args, |v| domain("SQRT", to_float(&v[0]).sqrt()))
+}
+/////////////////////////////
+/// Compile call to LOG.
+pub(crate) fn c_log(b: &Block, args: &mut [Expr]) -> CExpPtr<Value> {
+    check_numeric(b, args, 1..=2);
+    c_null_map(b, args, |v| {
+        let x = to_float(&v[0]);
+        let r = match v.get(1).map(to_float) {
+            // log10 and log2 are exact for powers of the base.
+            Some(10.0) => x.log10(),
+            Some(2.0) => x.log2(),
+            Some(base) => x.log(base),
+            None => x.ln(),
+        };
+        domain("LOG", r)
+    })
+}
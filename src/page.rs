@@ -37,6 +37,7 @@ const MAX_NODE: usize = bitmask!(0, NODE_ID_BITS);
 
 /// A page in a SortedFile.
 /// Note that left subtree has nodes that compare greater.
+#[derive(Clone)]
 pub struct Page {
     /// Data storage.
     pub data: MData,
@@ -25,6 +25,8 @@ pub struct Parser<'a> {
     token: Token,
     /// Source index of start of current token.
     token_start: usize,
+    /// Source line of start of current token.
+    token_line: usize,
     /// Source index of start of current token (including spacce).
     token_space_start: usize,
     /// source slice for current token ( but string literals are in ts )
@@ -49,6 +51,7 @@ impl<'a> Parser<'a> {
             source_ix: 0,
             cc: 0,
             token_start: 0,
+            token_line: 1,
             token_space_start: 0,
             token: Token::EndOfFile,
             cs: source,
@@ -67,6 +70,8 @@ impl<'a> Parser<'a> {
 
     /// Parse a single statement.
     fn statement(&mut self) {
+        let save = self.b.line;
+        self.b.line = self.token_line;
         if self.token == Token::Id {
             let id = self.cs;
             self.read_token();
@@ -82,6 +87,7 @@ impl<'a> Parser<'a> {
                     b"DECLARE" => self.s_declare(),
                     b"DELETE" => self.s_delete(),
                     b"EXEC" => self.s_exec(),
+                    b"EXPLAIN" => self.s_explain(),
                     b"CHECK" => self.s_check(),
                     b"EXECUTE" => self.s_execute(),
                     b"FOR" => self.s_for(),
@@ -89,6 +95,8 @@ impl<'a> Parser<'a> {
                     b"IF" => self.s_if(),
                     b"INSERT" => self.s_insert(),
                     b"RETURN" => self.s_return(),
+                    b"ROLLBACK" => self.s_rollback(),
+                    b"SAVEPOINT" => self.s_savepoint(),
                     b"SELECT" => self.s_select(),
                     b"SET" => self.s_set(),
                     b"THROW" => self.s_throw(),
@@ -100,6 +108,7 @@ impl<'a> Parser<'a> {
         } else {
             panic!("statement keyword expected, got '{:?}'", self.token)
         }
+        self.b.line = save;
     } // end fn statement
 
     /// Parse and execute a batch of statements.
@@ -112,6 +121,7 @@ impl<'a> Parser<'a> {
             let mut ee = EvalEnv::new(self.b.db.clone(), rs);
             // let start = std::time::Instant::now();
             ee.alloc_locals(&self.b.local_typ, 0);
+            ee.set_lines(&self.b.lines);
             ee.go(&self.b.ilist);
             if self.token == Token::EndOfFile {
                 break;
@@ -120,6 +130,26 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Parse a prepared statement. Leading DECLARE statements declare the parameters.
+    pub fn prepare(&mut self) -> Statement {
+        while self.test_id(b"DECLARE") {
+            self.s_declare();
+        }
+        self.b.param_count = self.b.local_typ.len();
+        while self.token != Token::EndOfFile {
+            self.statement();
+        }
+        self.b.resolve_jumps();
+        Statement {
+            db: self.b.db.clone(),
+            param_count: self.b.param_count,
+            local_typ: std::mem::take(&mut self.b.local_typ),
+            ilist: std::mem::take(&mut self.b.ilist),
+            lines: std::mem::take(&mut self.b.lines),
+            schema_gen: self.b.db.schema_gen.get(),
+        }
+    }
+
     /// Parse the definition of a function.
     pub fn parse_function(&mut self) {
         self.read(Token::LBra);
@@ -154,6 +184,16 @@ impl<'a> Parser<'a> {
         self.b.resolve_jumps();
     }
 
+    /// Parse a view definition.
+    pub fn parse_view(&mut self) -> FromExpression {
+        self.read_id(b"SELECT");
+        let se = self.select_expression(false);
+        if self.token != Token::EndOfFile {
+            panic!("unexpected input after view definition");
+        }
+        se
+    }
+
     /// Read a byte, adjusting source line/column.
     fn read_char(&mut self) -> u8 {
         let cc;
@@ -188,6 +228,7 @@ impl<'a> Parser<'a> {
                 cc = self.read_char();
             }
             self.token_start = self.source_ix - 1;
+            self.token_line = self.source_line;
             let sc: u8 = cc;
             cc = self.read_char();
             match sc {
@@ -196,6 +237,15 @@ impl<'a> Parser<'a> {
                     while cc.is_ascii_alphabetic() {
                         cc = self.read_char();
                     }
+                    // TRY_CAST is the only identifier with an underscore.
+                    if &self.source[self.token_start..self.source_ix - 1] == b"TRY"
+                        && self.source[self.source_ix - 1..].starts_with(b"_CAST")
+                    {
+                        cc = self.read_char();
+                        while cc.is_ascii_alphabetic() {
+                            cc = self.read_char();
+                        }
+                    }
                     self.cs = &self.source[self.token_start..self.source_ix - 1];
                 }
                 b'0'..=b'9' => {
@@ -214,6 +264,16 @@ impl<'a> Parser<'a> {
                         let part1 = self.source_ix - 1;
                         let s = str::from_utf8(&self.source[self.token_start..part1]).unwrap();
                         self.decimal_int = s.parse().unwrap();
+                        if cc == b'.'
+                            && self.source_ix < self.source.len()
+                            && self.source[self.source_ix].is_ascii_digit()
+                        {
+                            token = Token::Decimal;
+                            cc = self.read_char();
+                            while cc.is_ascii_digit() {
+                                cc = self.read_char();
+                            }
+                        }
                     }
                     self.cs = &self.source[self.token_start..self.source_ix - 1];
                 }
@@ -355,9 +415,29 @@ impl<'a> Parser<'a> {
             b"float" => FLOAT,
             b"double" => DOUBLE,
             b"bool" => BOOL,
+            b"datetime" => DATETIME,
+            b"decimal" => DECIMAL,
             _ => panic!("datatype expected"),
         };
-        if self.test(Token::LBra) {
+        if t == DECIMAL {
+            if self.test(Token::LBra) {
+                let p = self.decimal_int as usize;
+                self.read(Token::Number);
+                let mut s = 0;
+                if self.test(Token::Comma) {
+                    s = self.decimal_int as usize;
+                    self.read(Token::Number);
+                }
+                self.read(Token::RBra);
+                if !(1..=decimal::MAX_PRECISION).contains(&p) {
+                    panic!("decimal precision must be 1 to {}", decimal::MAX_PRECISION);
+                }
+                if s > p {
+                    panic!("decimal scale must not exceed precision");
+                }
+                t = decimal_type(p, s);
+            }
+        } else if self.test(Token::LBra) {
             let mut n = self.decimal_int as usize;
             self.read(Token::Number);
             self.read(Token::RBra);
@@ -383,6 +463,9 @@ impl<'a> Parser<'a> {
             }
             t = (t % 8) + (8 * n);
         }
+        if self.test_id(b"NULL") {
+            t |= NULLABLE;
+        }
         t
     }
 
@@ -396,6 +479,10 @@ impl<'a> Parser<'a> {
                     b"AND" => Token::And,
                     b"OR" => Token::Or,
                     b"IN" => Token::In,
+                    b"IS" => Token::Is,
+                    b"BETWEEN" => Token::Between,
+                    b"LIKE" => Token::Like,
+                    b"NOT" if self.next_id_is(b"LIKE") => Token::Like,
                     _ => return (t, -1),
                 }
             } else {
@@ -459,6 +546,19 @@ impl<'a> Parser<'a> {
         result
     }
 
+    /// Tests whether the token after the current token is the specified id ( without consuming any tokens ).
+    fn next_id_is(&self, s: &[u8]) -> bool {
+        let src = self.source;
+        let mut i = self.source_ix - 1;
+        while i < src.len() && matches!(src[i], b' ' | b'\n' | b'\r') {
+            i += 1;
+        }
+        let end = i + s.len();
+        end <= src.len()
+            && &src[i..end] == s
+            && !(end < src.len() && src[end].is_ascii_alphabetic())
+    }
+
     /// Tests whether the token is the specified id. If so, it is consumed.
     fn test_id(&mut self, s: &[u8]) -> bool {
         if self.token != Token::Id || self.cs != s {
@@ -506,6 +606,10 @@ impl<'a> Parser<'a> {
         let name = self.id_ref();
         if self.test(Token::Dot) {
             let fname = self.id_ref();
+            if self.token != Token::LBra {
+                // Qualified column name e.g. c.Name
+                return Expr::new(ExprIs::ColName(format!("{}.{}", tos(name), tos(fname))));
+            }
             let mut parms = Vec::new();
             self.read(Token::LBra);
             if self.token != Token::RBra {
@@ -522,6 +626,13 @@ impl<'a> Parser<'a> {
                 name: to_s(fname),
             };
             Expr::new(ExprIs::FuncCall(name, parms))
+        } else if (name == b"CAST" || name == b"TRY_CAST") && self.test(Token::LBra) {
+            // e.g. CAST( x AS decimal(10,2) )
+            let e = self.exp();
+            self.read_id(b"AS");
+            let t = self.read_data_type();
+            self.read(Token::RBra);
+            Expr::new(ExprIs::Cast(Box::new(e), t, name == b"TRY_CAST"))
         } else if self.test(Token::LBra) {
             let mut parms = Vec::new();
             if self.token != Token::RBra {
@@ -538,6 +649,8 @@ impl<'a> Parser<'a> {
             Expr::new(ExprIs::Const(Value::Bool(true)))
         } else if name == b"false" {
             Expr::new(ExprIs::Const(Value::Bool(false)))
+        } else if name == b"NULL" {
+            Expr::new(ExprIs::Const(Value::Null))
         } else if let Some(lnum) = self.b.get_local(name) {
             Expr::new(ExprIs::Local(*lnum))
         } else {
@@ -585,6 +698,12 @@ impl<'a> Parser<'a> {
             let value = self.decimal_int;
             result = Expr::new(ExprIs::Const(Value::Int(value)));
             self.read_token();
+        } else if self.token == Token::Decimal {
+            let Some((x, s)) = decimal::parse(&to_s(self.cs)) else {
+                panic!("invalid decimal literal");
+            };
+            result = Expr::new(ExprIs::Const(Value::Decimal(x, s)));
+            self.read_token();
         } else if self.token == Token::Hex {
             if self.cs.len() % 2 == 1 {
                 panic!("hex literal must have even number of characters");
@@ -621,7 +740,56 @@ impl<'a> Parser<'a> {
         let mut t = self.operator();
         while t.1 >= precedence {
             let op = t;
+            let not = self.cs == b"NOT";
             self.read_token();
+            if op.0 == Token::Like {
+                // x [NOT] LIKE pattern [ESCAPE 'c']
+                if not {
+                    self.read_id(b"LIKE");
+                }
+                let p = self.exp_primary();
+                let p = self.exp_lp(p, Token::Like.precedence() + 1);
+                let mut escape = None;
+                if self.test_id(b"ESCAPE") {
+                    let mut chars = self.ts.chars();
+                    escape = chars.next();
+                    if self.token != Token::String || escape.is_none() || chars.next().is_some() {
+                        panic!("ESCAPE must be a single character string");
+                    }
+                    self.read_token();
+                }
+                lhs = Expr::new(ExprIs::Like(Box::new(lhs), Box::new(p), escape, not));
+                t = self.operator();
+                continue;
+            }
+            if op.0 == Token::Is {
+                // IS NULL or IS NOT NULL
+                let not = self.test_id(b"NOT");
+                self.read_id(b"NULL");
+                lhs = Expr::new(ExprIs::IsNull(Box::new(lhs)));
+                if not {
+                    lhs = Expr::new(ExprIs::Not(Box::new(lhs)));
+                }
+                t = self.operator();
+                continue;
+            }
+            if op.0 == Token::Between {
+                // x BETWEEN lo AND hi is x >= lo AND x <= hi
+                let lo = self.exp_primary();
+                let lo = self.exp_lp(lo, Token::Between.precedence() + 1);
+                self.read_id(b"AND");
+                let hi = self.exp_primary();
+                let hi = self.exp_lp(hi, Token::Between.precedence() + 1);
+                let ge = ExprIs::Binary(Token::GreaterEqual, Box::new(lhs.clone()), Box::new(lo));
+                let le = ExprIs::Binary(Token::LessEqual, Box::new(lhs), Box::new(hi));
+                lhs = Expr::new(ExprIs::Binary(
+                    Token::And,
+                    Box::new(Expr::new(ge)),
+                    Box::new(Expr::new(le)),
+                ));
+                t = self.operator();
+                continue;
+            }
             let mut rhs = self.exp_primary();
             t = self.operator();
             while t.1 > op.1
@@ -664,6 +832,13 @@ impl<'a> Parser<'a> {
     // ****************** Table expression parsing
 
     fn insert_expression(&mut self, expect: usize) -> TableExpression {
+        if self.test_id(b"SELECT") {
+            let se = self.select_expression(false);
+            if se.exps.len() != expect {
+                panic!("wrong number of values");
+            }
+            return TableExpression::Select(Box::new(se), None);
+        }
         self.read_id(b"VALUES");
         let mut values = Vec::new();
         while self.test(Token::LBra) {
@@ -689,19 +864,64 @@ impl<'a> Parser<'a> {
         TableExpression::Values(values)
     }
 
-    fn te_named_table(&mut self) -> TableExpression {
-        let schema = self.id();
-        self.read(Token::Dot);
-        let name = self.id();
-        let name = ObjRef { schema, name };
-        TableExpression::Base(name)
-    }
-
     fn primary_table_exp(&mut self) -> TableExpression {
+        if self.test(Token::LBra) {
+            // Derived table.
+            self.read_id(b"SELECT");
+            let se = self.select_expression(false);
+            self.read(Token::RBra);
+            let alias = self.te_alias();
+            return TableExpression::Select(Box::new(se), alias);
+        }
         if self.token != Token::Id {
             panic!("table expected");
         }
-        self.te_named_table()
+        let name = self.obj_ref();
+        let alias = self.te_alias();
+        let mut list = Vec::new();
+        loop {
+            let outer = self.test_id(b"LEFT");
+            if outer {
+                self.test_id(b"OUTER");
+            }
+            let inner = !outer && self.test_id(b"INNER");
+            if !self.test_id(b"JOIN") {
+                if outer || inner {
+                    panic!("JOIN expected");
+                }
+                break;
+            }
+            let jname = self.obj_ref();
+            let jalias = self.te_alias().unwrap_or_else(|| jname.name.clone());
+            self.read_id(b"ON");
+            let on = Some(self.exp());
+            list.push(JoinTable {
+                name: jname,
+                alias: jalias,
+                outer,
+                on,
+            });
+        }
+        if list.is_empty() && alias.is_none() {
+            return TableExpression::Base(name);
+        }
+        let first = JoinTable {
+            alias: alias.unwrap_or_else(|| name.name.clone()),
+            name,
+            outer: false,
+            on: None,
+        };
+        list.insert(0, first);
+        TableExpression::Join(list)
+    }
+
+    /// Parse optional AS alias for a table in a FROM clause.
+    fn te_alias(&mut self) -> Option<String> {
+        if self.test_id(b"AS") {
+            Some(self.id())
+        } else {
+            None
+        }
     }
 
     fn exp_name(&self, exp: &Expr) -> String {
@@ -768,6 +988,16 @@ impl<'a> Parser<'a> {
                 }
             }
         }
+        let limit = if self.test_id(b"LIMIT") {
+            Some(self.exp())
+        } else {
+            None
+        };
+        let offset = if self.test_id(b"OFFSET") {
+            Some(self.exp())
+        } else {
+            None
+        };
         FromExpression {
             colnames,
             assigns,
@@ -775,6 +1005,8 @@ impl<'a> Parser<'a> {
             from,
             wher,
             orderby,
+            limit,
+            offset,
         }
     }
 
@@ -782,8 +1014,14 @@ impl<'a> Parser<'a> {
 
     fn s_select(&mut self) {
         let se = self.select_expression(false);
+        let json = self.token == Token::Id && self.cs == b"FOR" && self.next_id_is(b"JSON");
+        if json {
+            self.read_token();
+            self.read_token();
+        }
         if !self.b.parse_only {
-            let cte = c_select(&mut self.b, se);
+            let mut cte = c_select(&mut self.b, se);
+            cte.json = json;
             self.b.add(Select(Box::new(cte)));
         }
     }
@@ -813,7 +1051,7 @@ impl<'a> Parser<'a> {
                 panic!("comma or closing bracket expected");
             }
         }
-        let mut src = self.insert_expression(cnames.len());
+        let src = self.insert_expression(cnames.len());
         if !self.b.parse_only {
             let t = c_table(&self.b, &tr);
             let mut cnums: Vec<usize> = Vec::new();
@@ -826,7 +1064,10 @@ impl<'a> Parser<'a> {
                     }
                 }
             }
-            let csrc = c_te(&self.b, &mut src);
+            let csrc = match src {
+                TableExpression::Select(se, _) => c_insert_select(&mut self.b, &t, &cnums, *se),
+                mut src => c_te(&mut self.b, &mut src),
+            };
             self.b.dop(DO::Insert(t, cnums, csrc));
         }
     }
@@ -894,18 +1135,27 @@ impl<'a> Parser<'a> {
             name: pname,
         };
         self.read(Token::LBra);
+        let func = if self.b.parse_only {
+            None
+        } else {
+            Some(c_function(&self.b.db, &name))
+        };
         let mut pkinds = Vec::new();
         if !self.test(Token::RBra) {
-            let mut e = self.exp();
-            pkinds.push(push(&mut self.b, &mut e));
-            while self.test(Token::Comma) {
+            loop {
                 let mut e = self.exp();
-                pkinds.push(push(&mut self.b, &mut e));
+                let k = match &func {
+                    Some(f) => push_arg(&mut self.b, f, pkinds.len(), &mut e),
+                    None => push(&mut self.b, &mut e),
+                };
+                pkinds.push(k);
+                if !self.test(Token::Comma) {
+                    break;
+                }
             }
             self.read(Token::RBra);
         }
-        if !self.b.parse_only {
-            let func = c_function(&self.b.db, &name);
+        if let Some(func) = func {
             self.b.check_types(&func, &pkinds);
             self.b.add(Call(func));
         }
@@ -913,8 +1163,7 @@ impl<'a> Parser<'a> {
 
     fn s_for(&mut self) {
         let se: FromExpression = self.select_expression(true);
-        let for_id = self.b.local_typ.len();
-        self.b.local_typ.push(NONE);
+        let for_id = self.b.alloc_local();
         let start_id = self.b.get_jump_id();
         let break_id = self.b.get_jump_id();
         if !self.b.parse_only {
@@ -934,12 +1183,43 @@ impl<'a> Parser<'a> {
         let name = self.obj_ref();
         let source_start = self.source_ix - 2;
         self.read(Token::LBra);
-        let mut ti = ColInfo::empty(name);
+        let mut ti = ColInfo::empty(name.clone());
+        let mut fkeys = Vec::new();
+        let mut cons = Vec::new();
         loop {
-            let cname = self.id();
-            let typ = self.read_data_type();
-            if ti.add(cname, typ) {
-                panic!("duplicate column name");
+            if self.test_id(b"CHECK") {
+                cons.push(self.constraint(&name, "", ConstraintKind::Check));
+            } else {
+                let cname = self.id();
+                let typ = self.read_data_type();
+                self.column_constraints(&name, &cname, &mut cons);
+                if self.test_id(b"REFERENCES") {
+                    if data_kind(typ) != DataKind::Int {
+                        panic!("REFERENCES column {} must be int", cname);
+                    }
+                    let rtable = self.obj_ref();
+                    let action = self.ref_action();
+                    if action == RefAction::SetDefault
+                        && !nullable(typ)
+                        && !cons
+                            .iter()
+                            .any(|c| c.kind == ConstraintKind::Default && c.col == cname)
+                    {
+                        panic!(
+                            "ON DELETE SET DEFAULT column {} must be nullable or have a DEFAULT",
+                            cname
+                        );
+                    }
+                    fkeys.push(ForeignKey {
+                        table: name.clone(),
+                        col: ti.colnames.len(),
+                        rtable,
+                        action,
+                    });
+                }
+                if ti.add(cname, typ) {
+                    panic!("duplicate column name");
+                }
             }
             if self.test(Token::RBra) {
                 break;
@@ -952,10 +1232,69 @@ impl<'a> Parser<'a> {
         if !self.b.parse_only {
             let _source = self.source_from(source_start, self.token_start);
             self.b.dop(DO::CreateTable(ti));
+            for fk in fkeys {
+                self.b.dop(DO::CreateForeignKey(fk));
+            }
+            for c in cons {
+                self.b.dop(DO::CreateConstraint(c));
+            }
         }
     }
 
-    fn create_index(&mut self) {
+    /// Parse optional DEFAULT and CHECK clauses of a column definition.
+    fn column_constraints(&mut self, table: &ObjRef, cname: &str, cons: &mut Vec<Constraint>) {
+        loop {
+            if self.test_id(b"DEFAULT") {
+                cons.push(self.constraint(table, cname, ConstraintKind::Default));
+            } else if self.test_id(b"CHECK") {
+                cons.push(self.constraint(table, cname, ConstraintKind::Check));
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Parse DEFAULT or CHECK expression, saving the source.
+    fn constraint(&mut self, table: &ObjRef, cname: &str, kind: ConstraintKind) -> Constraint {
+        let source_start = self.token_start;
+        self.exp();
+        let source = self.source_from(source_start, self.token_space_start);
+        Constraint {
+            table: table.clone(),
+            col: cname.to_string(),
+            kind,
+            source: Rc::new(source),
+        }
+    }
+
+    /// Parse a DEFAULT or CHECK expression saved by CREATE TABLE or ALTER TABLE.
+    pub fn parse_constraint(&mut self) -> Expr {
+        let exp = self.exp();
+        if self.token != Token::EndOfFile {
+            panic!("unexpected input after constraint expression");
+        }
+        exp
+    }
+
+    /// Parse optional ON DELETE action.
+    fn ref_action(&mut self) -> RefAction {
+        if !self.test_id(b"ON") {
+            return RefAction::Restrict;
+        }
+        self.read_id(b"DELETE");
+        if self.test_id(b"RESTRICT") {
+            RefAction::Restrict
+        } else if self.test_id(b"CASCADE") {
+            RefAction::Cascade
+        } else if self.test_id(b"SET") {
+            self.read_id(b"DEFAULT");
+            RefAction::SetDefault
+        } else {
+            panic!("RESTRICT, CASCADE or SET DEFAULT expected")
+        }
+    }
+
+    fn create_index(&mut self, unique: bool) {
         let iname = self.id();
         self.read_id(b"ON");
         let tname = self.obj_ref();
@@ -981,8 +1320,12 @@ impl<'a> Parser<'a> {
                     panic!("index column name not found {}", cname);
                 }
             }
-            self.b
-                .dop(DO::CreateIndex(IndexInfo { tname, iname, cols }));
+            self.b.dop(DO::CreateIndex(IndexInfo {
+                tname,
+                iname,
+                cols,
+                unique,
+            }));
         }
     }
 
@@ -1002,15 +1345,63 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn create_view(&mut self) {
+        let name = self.obj_ref();
+        self.read_id(b"AS");
+        let source_start = self.token_start;
+        self.read_id(b"SELECT");
+        self.select_expression(false);
+        if !self.b.parse_only {
+            let source = self.source_from(source_start, self.token_space_start);
+            self.b.dop(DO::CreateView(name, Rc::new(source)));
+        }
+    }
+
+    fn create_trigger(&mut self) {
+        let name = self.id();
+        self.read_id(b"AFTER");
+        let event = match self.id_ref() {
+            b"INSERT" => TriggerEvent::Insert,
+            b"UPDATE" => TriggerEvent::Update,
+            b"DELETE" => TriggerEvent::Delete,
+            _ => panic!("INSERT, UPDATE or DELETE expected"),
+        };
+        self.read_id(b"ON");
+        let table = self.obj_ref();
+        self.read_id(b"AS");
+        let func = self.obj_ref();
+        if !self.b.parse_only {
+            // Check the function parameters match the old and/or new row.
+            let t = c_table(&self.b, &table);
+            let f = c_function(&self.b.db, &func);
+            let mut kinds = vec![DataKind::Int];
+            kinds.extend(t.info.typ.iter().map(|typ| data_kind(*typ)));
+            let n = if event == TriggerEvent::Update { 2 } else { 1 };
+            self.b.check_types(&f, &kinds.repeat(n));
+            self.b.dop(DO::CreateTrigger(Trigger {
+                name,
+                table,
+                event,
+                func,
+            }));
+        }
+    }
+
     fn s_create(&mut self) {
         match self.id_ref() {
             b"FN" => self.create_function(false),
+            b"VIEW" => self.create_view(),
+            b"TRIGGER" => self.create_trigger(),
             b"TABLE" => self.create_table(),
             b"SCHEMA" => {
                 let name = self.id();
                 self.b.dop(DO::CreateSchema(name));
             }
-            b"INDEX" => self.create_index(),
+            b"INDEX" => self.create_index(false),
+            b"UNIQUE" => {
+                self.read_id(b"INDEX");
+                self.create_index(true)
+            }
             _ => panic!("CREATE : TABLE<FN.. expected"),
         }
     }
@@ -1039,6 +1430,16 @@ impl<'a> Parser<'a> {
                 let fr = self.obj_ref();
                 self.b.dop(DO::DropFunction(fr));
             }
+            b"VIEW" => {
+                let vr = self.obj_ref();
+                self.b.dop(DO::DropView(vr));
+            }
+            b"TRIGGER" => {
+                let name = self.id();
+                self.read_id(b"ON");
+                let tr = self.obj_ref();
+                self.b.dop(DO::DropTrigger(tr, name));
+            }
             b"SCHEMA" => {
                 let s = self.id();
                 self.b.dop(DO::DropSchema(s));
@@ -1052,11 +1453,17 @@ impl<'a> Parser<'a> {
     fn s_alter_table(&mut self) {
         let tr = self.obj_ref();
         let mut list = Vec::new();
+        let mut cons = Vec::new();
         loop {
             if self.test_id(b"ADD") {
-                let col = self.id();
-                let datatype = self.read_data_type();
-                list.push(AlterCol::Add(col, datatype));
+                if self.test_id(b"CHECK") {
+                    cons.push(self.constraint(&tr, "", ConstraintKind::Check));
+                } else {
+                    let col = self.id();
+                    let datatype = self.read_data_type();
+                    self.column_constraints(&tr, &col, &mut cons);
+                    list.push(AlterCol::Add(col, datatype));
+                }
             } else if self.test_id(b"DROP") {
                 let col = self.id();
                 list.push(AlterCol::Drop(col));
@@ -1071,7 +1478,12 @@ impl<'a> Parser<'a> {
                 break;
             }
         }
-        self.b.dop(DO::AlterTable(tr, list));
+        if !list.is_empty() {
+            self.b.dop(DO::AlterTable(tr, list));
+        }
+        for c in cons {
+            self.b.dop(DO::CreateConstraint(c));
+        }
     }
 
     // Other statements.
@@ -1139,12 +1551,26 @@ impl<'a> Parser<'a> {
         if self.b.return_type != NONE {
             let mut e = self.exp();
             if !self.b.parse_only {
-                let k = push(&mut self.b, &mut e);
-                let rk = data_kind(self.b.return_type);
-                if k != rk {
+                let rt = self.b.return_type;
+                let rk = data_kind(rt);
+                let k = self.b.kind(&mut e);
+                if k != rk
+                    && !(k == DataKind::None && nullable(rt))
+                    && !(k == DataKind::Int && rk == DataKind::Decimal)
+                {
                     panic!("return type mismatch expected {:?} got {:?}", rk, k)
                 }
-                self.b.add(PopToLocal(self.b.param_count));
+                if nullable(e.data_type) && !nullable(rt) {
+                    panic!("return type is not nullable");
+                }
+                if rk == DataKind::Decimal {
+                    // The result is converted to the return type.
+                    let ce = decimal::c_assign(c_value(&self.b, &mut e), e.data_type, rt);
+                    self.b.add(AssignLocal(self.b.param_count, ce));
+                } else {
+                    push(&mut self.b, &mut e);
+                    self.b.add(PopToLocal(self.b.param_count));
+                }
             }
         }
         self.b.add(Return);
@@ -1158,10 +1584,72 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn s_explain(&mut self) {
+        let start = self.b.ilist.len();
+        self.statement();
+        if self.b.parse_only {
+            return;
+        }
+        let list = self.b.ilist.split_off(start);
+        self.b.lines.truncate(start);
+        for line in explain::explain(&self.b, &list, start) {
+            let value = Value::String(Rc::new(line + "\n"));
+            let cse = CFromExpression {
+                colnames: vec!["plan".to_string()],
+                types: vec![STRING],
+                assigns: Vec::new(),
+                exps: vec![Box::new(cexp::Const { value })],
+                from: None,
+                wher: None,
+                orderby: Vec::new(),
+                desc: Vec::new(),
+                limit: None,
+                offset: None,
+                json: false,
+            };
+            self.b.add(Select(Box::new(cse)));
+        }
+    }
+
+    fn s_savepoint(&mut self) {
+        let name = self.id();
+        self.b.dop(DO::Savepoint(name));
+    }
+
+    fn s_rollback(&mut self) {
+        self.read_id(b"TO");
+        self.test_id(b"SAVEPOINT");
+        let name = self.id();
+        self.b.dop(DO::RollbackTo(name));
+    }
+
     fn s_begin(&mut self) {
+        if self.test_id(b"TRY") {
+            self.s_try();
+            return;
+        }
+        while !self.test_id(b"END") {
+            self.statement();
+        }
+    }
+
+    fn s_try(&mut self) {
+        let catch_id = self.b.get_jump_id();
+        let end_id = self.b.get_jump_id();
+        self.b.add(Try(catch_id));
+        while !self.test_id(b"END") {
+            self.statement();
+        }
+        self.read_id(b"TRY");
+        self.b.add(Jump(end_id));
+        self.b.set_jump(catch_id);
+        self.read_id(b"BEGIN");
+        self.read_id(b"CATCH");
         while !self.test_id(b"END") {
             self.statement();
         }
+        self.read_id(b"CATCH");
+        self.b.set_jump(end_id);
     }
 } // end impl Parser
 
@@ -0,0 +1,118 @@
+use crate::*;
+use std::time::{Duration, Instant};
+
+/// Execution statistics for an instruction, or for a function ( ip is None ).
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct ProfileEntry {
+    /// Function name, or "batch".
+    pub name: Rc<String>,
+    /// Batch section number, counting from 1 ( sections are separated by GO, nested batches are also counted ), 0 for a function.
+    pub section: usize,
+    /// Instruction number.
+    pub ip: Option<usize>,
+    /// Source line of the statement which compiled to the instruction.
+    pub line: usize,
+    /// Number of executions ( or calls ).
+    pub count: u64,
+    /// Total wall time, including called functions.
+    pub time: Duration,
+    /// Rows fetched from data sources.
+    pub rows: u64,
+    /// Pages fetched from sorted files.
+    pub pages: u64,
+}
+
+/// Profiling state ( see [Database]::set_profile ).
+#[derive(Default)]
+pub struct Profile {
+    /// Profiling is enabled.
+    pub enabled: Cell<bool>,
+    /// Rows fetched so far.
+    pub rows: Cell<u64>,
+    /// Pages fetched so far.
+    pub pages: Cell<u64>,
+    /// Depth of nested batches, statistics are reported when the outermost batch finishes.
+    depth: Cell<usize>,
+    /// Number of batch sections started.
+    sections: Cell<usize>,
+    /// Statistics by function name, batch section and instruction number.
+    entries: RefCell<HashMap<ProfileKey, ProfileEntry>>,
+}
+
+/// Function name, batch section and instruction number.
+type ProfileKey = (Rc<String>, usize, Option<usize>);
+
+/// Measurement started before an instruction or function call.
+pub struct ProfileStart {
+    time: Instant,
+    rows: u64,
+    pages: u64,
+}
+
+impl Profile {
+    /// Start a measurement.
+    pub fn start(&self) -> ProfileStart {
+        ProfileStart {
+            time: Instant::now(),
+            rows: self.rows.get(),
+            pages: self.pages.get(),
+        }
+    }
+
+    /// Start a batch section, result is the section number.
+    pub fn section(&self) -> usize {
+        self.sections.set(self.sections.get() + 1);
+        self.sections.get()
+    }
+
+    /// Add a measurement to the statistics.
+    pub fn record(
+        &self,
+        name: &Rc<String>,
+        section: usize,
+        ip: Option<usize>,
+        line: usize,
+        s: ProfileStart,
+    ) {
+        let time = s.time.elapsed();
+        let entries = &mut *self.entries.borrow_mut();
+        let e = entries
+            .entry((name.clone(), section, ip))
+            .or_insert_with(|| ProfileEntry {
+                name: name.clone(),
+                section,
+                ip,
+                line,
+                ..Default::default()
+            });
+        e.count += 1;
+        e.time += time;
+        e.rows += self.rows.get() - s.rows;
+        e.pages += self.pages.get() - s.pages;
+    }
+
+    /// Get the statistics, sorted by name, section then instruction number ( function totals first ).
+    pub fn get(&self) -> Vec<ProfileEntry> {
+        let mut result: Vec<ProfileEntry> = self.entries.borrow().values().cloned().collect();
+        result.sort_by(|a, b| (&a.name, a.section, a.ip).cmp(&(&b.name, b.section, b.ip)));
+        result
+    }
+
+    /// Called when a batch starts. Statistics are cleared for an outermost batch.
+    pub fn begin(&self) {
+        if self.depth.get() == 0 {
+            self.entries.borrow_mut().clear();
+            self.sections.set(0);
+        }
+        self.depth.set(self.depth.get() + 1);
+    }
+
+    /// Called when a batch finishes. Statistics are passed to the transaction for an outermost batch.
+    pub fn end(&self, tr: &mut dyn Transaction) {
+        self.depth.set(self.depth.get() - 1);
+        if self.depth.get() == 0 {
+            tr.profile(&self.get());
+        }
+    }
+}
@@ -21,6 +21,8 @@ pub enum Instruction {
     Return,
     ///
     Throw,
+    /// Start of TRY block, jump to CATCH block if an error occurs ( changes made by the TRY block are undone ).
+    Try(usize),
     ///
     Execute,
     ///
@@ -63,15 +65,92 @@ pub struct Function {
     /// Function return type.
     pub return_type: DataType,
     /// Types of local parameters/variables.
-    pub local_typ: Vec<DataType>,
+    pub local_typ: RefCell<Vec<DataType>>, // May grow when compiled.
     /// Source SQL.
     pub source: Rc<String>,
     /// List of instructions.
     pub ilist: RefCell<Vec<Instruction>>, // Valid when compiled is true.
+    /// Source line of each instruction.
+    pub lines: RefCell<Rc<Vec<usize>>>,
+    /// Function name ( for profiling ).
+    pub name: Rc<String>,
     /// Has function been compiled.
     pub compiled: Cell<bool>,
 }
 
+/// Prepared statement ( see [Database]::prepare ).
+#[non_exhaustive]
+pub struct Statement {
+    /// Database.
+    pub db: DB,
+    /// Number of parameters.
+    pub param_count: usize,
+    /// Types of parameters/local variables.
+    pub local_typ: Vec<DataType>,
+    /// List of instructions.
+    pub ilist: Vec<Instruction>,
+    /// Source line of each instruction.
+    pub lines: Vec<usize>,
+    /// Schema generation when the statement was prepared.
+    pub schema_gen: u64,
+}
+
+impl Statement {
+    /// Execute the statement, values are assigned to the parameters.
+    pub fn execute(&self, values: &[Value], tr: &mut dyn Transaction) {
+        let profile = self.db.profile.enabled.get();
+        if profile {
+            self.db.profile.begin();
+        }
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            if self.schema_gen != self.db.schema_gen.get() {
+                panic!("schema changed since statement was prepared");
+            }
+            if values.len() != self.param_count {
+                panic!(
+                    "expected {} parameter values, got {}",
+                    self.param_count,
+                    values.len()
+                );
+            }
+            let mut ee = EvalEnv::new(self.db.clone(), tr);
+            for (i, v) in values.iter().enumerate() {
+                let t = self.local_typ[i];
+                let ok = match v {
+                    Value::Null => nullable(t),
+                    Value::Int(_) => matches!(
+                        data_kind(t),
+                        DataKind::Int | DataKind::DateTime | DataKind::Decimal
+                    ),
+                    Value::Decimal(_, _) => data_kind(t) == DataKind::Decimal,
+                    Value::Float(_) => data_kind(t) == DataKind::Float,
+                    Value::String(_) => data_kind(t) == DataKind::String,
+                    Value::Bool(_) => data_kind(t) == DataKind::Bool,
+                    Value::RcBinary(_) | Value::ArcBinary(_) => data_kind(t) == DataKind::Binary,
+                    _ => false,
+                };
+                if !ok {
+                    panic!("parameter {} value does not match type", i + 1);
+                }
+                ee.stack.push(v.clone());
+            }
+            ee.alloc_locals(&self.local_typ, self.param_count);
+            ee.set_lines(&self.lines);
+            ee.go(&self.ilist);
+        }));
+        if let Err(x) = result {
+            tr.set_error(format!(
+                "{} in prepared statement.",
+                exec::panic_message(&x)
+            ));
+            self.db.err.set(true);
+        }
+        if profile {
+            self.db.profile.end(tr);
+        }
+    }
+}
+
 /// Compiled expression which yields type T when evaluated.
 pub trait CExp<T> {
     /// Evaluate the compiled expression.
@@ -91,6 +170,8 @@ pub enum CompileFunc {
     Int(fn(&Block, &mut [Expr]) -> CExpPtr<i64>),
     ///
     Float(fn(&Block, &mut [Expr]) -> CExpPtr<f64>),
+    /// Value which is NULL if an argument is NULL, the result is nullable if any argument is nullable.
+    NullValue(fn(&Block, &mut [Expr]) -> CExpPtr<Value>),
 }
 
 /// Iterator that yields references to page data.
@@ -138,15 +219,24 @@ pub struct ForNextInfo {
 /// Compiled Table Expression.
 #[non_exhaustive]
 pub enum CTableExpression {
-    // Select( FromExpression ),
+    /// SELECT expression.
+    Select(Box<CFromExpression>),
     ///
     Base(Rc<Table>),
     ///
     IdGet(Rc<Table>, CExpPtr<i64>),
     ///
     IxGet(Rc<Table>, Vec<CExpPtr<Value>>, usize),
+    /// Range of index keys.
+    IxRange(Rc<Table>, Box<CIndexRange>),
     ///
     Values(Vec<Vec<CExpPtr<Value>>>),
+    /// Joined tables.
+    Join(Rc<CJoin>),
+    /// Derived table ( SELECT in FROM clause ).
+    Derived(Box<CDerived>),
+    /// Records fetched by a list of lookups ( for IN list or OR condition ), with duplicates removed.
+    KeyList(Vec<CTableExpression>),
 }
 
 impl CTableExpression {
@@ -156,16 +246,97 @@ impl CTableExpression {
             CTableExpression::Base(t) => t.clone(),
             CTableExpression::IdGet(t, _) => t.clone(),
             CTableExpression::IxGet(t, _, _) => t.clone(),
+            CTableExpression::IxRange(t, _) => t.clone(),
+            CTableExpression::KeyList(list) => list[0].table(),
             _ => panic!(),
         }
     }
 }
 
+/// Compiled index range : keys for leading index columns, and bounds for the next index column.
+#[non_exhaustive]
+pub struct CIndexRange {
+    /// Index number.
+    pub index: usize,
+    /// Keys for leading index columns.
+    pub keys: Vec<CExpPtr<Value>>,
+    /// Lower bound, and whether the bound is inclusive.
+    pub lower: Option<(CExpPtr<Value>, bool)>,
+    /// Upper bound, and whether the bound is inclusive.
+    pub upper: Option<(CExpPtr<Value>, bool)>,
+    /// Records are fetched in descending order.
+    pub desc: bool,
+}
+
+/// Compiled derived table. The selected rows are held in a local variable while the table is read.
+#[non_exhaustive]
+pub struct CDerived {
+    /// Name used to qualify column names.
+    pub alias: Option<String>,
+    /// Local variable which holds the rows.
+    pub local: usize,
+    ///
+    pub select: CFromExpression,
+}
+
+/// Compiled JOIN. Each joined row is the concatenation of a record from each table.
+#[non_exhaustive]
+pub struct CJoin {
+    /// Tables in scope : alias, table and offset of the table record in the joined record.
+    pub tables: Vec<(String, Rc<Table>, usize)>,
+    /// How each table after the first is accessed.
+    pub steps: Vec<JoinStep>,
+    /// Key expressions that don't depend on the FROM clause, evaluated when the join starts.
+    pub consts: Vec<CExpPtr<Value>>,
+    /// For each table, the view it is compiled from ( if any ). The record of a view is a row number.
+    pub derived: Vec<Option<Rc<CDerived>>>,
+    /// For each table, offset of a byte in the joined record which is 1 if a LEFT JOIN found no matching record
+    /// ( usize::MAX if not a LEFT JOIN ). All columns of such a table are NULL.
+    pub missing: Vec<usize>,
+    /// Size of joined record.
+    pub total: usize,
+}
+
+/// How a joined table is accessed for each row of the preceding tables.
+#[non_exhaustive]
+pub struct JoinStep {
+    ///
+    pub access: JoinAccess,
+    /// Other column equalities which fetched records must satisfy : column number and key.
+    pub filter: Vec<(usize, JoinKey)>,
+    /// LEFT JOIN.
+    pub outer: bool,
+    /// Other ON conditions of a LEFT JOIN, checked before deciding whether a record matched.
+    pub pred: Option<CExpPtr<bool>>,
+}
+
+/// Access method for joined table.
+#[non_exhaustive]
+pub enum JoinAccess {
+    /// Lookup by Id.
+    Id(JoinKey),
+    /// Lookup using index ( index number and key for each leading index column ).
+    Index(usize, Vec<JoinKey>),
+    /// Scan all records.
+    Scan,
+}
+
+/// Key value for join lookup.
+#[non_exhaustive]
+pub enum JoinKey {
+    /// Column of a preceding table : offset in joined record and DataType.
+    Col(usize, DataType),
+    /// Expression that doesn't depend on the FROM clause ( index into [CJoin]::consts ).
+    Const(usize),
+}
+
 /// Compiled From Expression.
 #[non_exhaustive]
 pub struct CFromExpression {
     ///
     pub colnames: Vec<String>,
+    /// Data type of each expression.
+    pub types: Vec<DataType>,
     ///
     pub assigns: Assigns,
     ///
@@ -178,6 +349,12 @@ pub struct CFromExpression {
     pub orderby: Vec<CExpPtr<Value>>,
     ///
     pub desc: Vec<bool>,
+    /// LIMIT ( maximum number of rows ).
+    pub limit: Option<CExpPtr<i64>>,
+    /// OFFSET ( number of rows to skip ).
+    pub offset: Option<CExpPtr<i64>>,
+    /// FOR JSON : output is a single JSON array of row objects.
+    pub json: bool,
 }
 
 /// Database Operation
@@ -187,10 +364,18 @@ pub enum DO {
     CreateTable(ColInfo),
     ///
     CreateIndex(IndexInfo),
+    /// Create foreign key.
+    CreateForeignKey(ForeignKey),
+    /// Create DEFAULT or CHECK constraint.
+    CreateConstraint(Constraint),
+    /// Create trigger.
+    CreateTrigger(Trigger),
     ///
     CreateSchema(String),
     ///
     CreateFunction(ObjRef, Rc<String>, bool),
+    /// Create view ( name and SELECT source ).
+    CreateView(ObjRef, Rc<String>),
     ///
     AlterTable(ObjRef, Vec<AlterCol>),
     ///
@@ -202,6 +387,10 @@ pub enum DO {
     ///
     DropFunction(ObjRef),
     ///
+    DropView(ObjRef),
+    /// Drop trigger ( table and trigger name ).
+    DropTrigger(ObjRef, String),
+    ///
     Insert(Rc<Table>, Vec<usize>, CTableExpression),
     ///
     Update(
@@ -211,6 +400,10 @@ pub enum DO {
     ),
     ///
     Delete(CTableExpression, Option<CExpPtr<bool>>),
+    /// Set savepoint.
+    Savepoint(String),
+    /// Rollback to savepoint.
+    RollbackTo(String),
 }
 
 /// Actions for altering columns of a table.
@@ -0,0 +1,108 @@
+use crate::*;
+
+/// Saved state of a table. Table metadata lists only grow, so lengths are enough to restore them ( except triggers ).
+struct TableState {
+    table: Rc<Table>,
+    ixlist: usize,
+    fkeys: usize,
+    refs: usize,
+    defaults: usize,
+    checks: usize,
+    triggers: Vec<Rc<Trigger>>,
+}
+
+/// Copies of the unsaved pages of a file.
+type FilePages = (Rc<SortedFile>, Vec<(u64, Page)>);
+
+/// Saved state for partial rollback ( SAVEPOINT and ROLLBACK TO ).
+#[non_exhaustive]
+pub struct Savepoint {
+    /// Savepoint name.
+    pub name: String,
+    /// Pages allocated since the savepoint.
+    pub(crate) allocated: Vec<u64>,
+    /// Pages freed since the savepoint ( not freed until save ).
+    pub(crate) freed: Vec<u64>,
+    /// Loaded tables.
+    tables: Vec<(ObjRef, TableState)>,
+    /// Copies of unsaved pages.
+    files: Vec<FilePages>,
+    /// Schema generation.
+    schema_gen: u64,
+}
+
+impl Savepoint {
+    /// Record the current state of the database.
+    pub fn new(db: &DB, name: &str) -> Self {
+        let mut tables = Vec::new();
+        let mut files = Vec::new();
+        for (tname, t) in db.tables.borrow().iter() {
+            files.push((t.file.clone(), t.file.get_dirty()));
+            for ix in t.ixlist.borrow().iter() {
+                files.push((ix.file.clone(), ix.file.get_dirty()));
+            }
+            let state = TableState {
+                table: t.clone(),
+                ixlist: t.ixlist.borrow().len(),
+                fkeys: t.fkeys.borrow().len(),
+                refs: t.refs.borrow().len(),
+                defaults: t.defaults.borrow().len(),
+                checks: t.checks.borrow().len(),
+                triggers: t.triggers.borrow().clone(),
+            };
+            tables.push((tname.clone(), state));
+        }
+        for bs in &db.bs {
+            files.push((bs.file.clone(), bs.file.get_dirty()));
+        }
+        Self {
+            name: name.to_string(),
+            allocated: Vec::new(),
+            freed: Vec::new(),
+            tables,
+            files,
+            schema_gen: db.schema_gen.get(),
+        }
+    }
+
+    /// Restore the recorded state.
+    pub fn restore(&self, db: &DB) {
+        // Changes to tables loaded since the savepoint are discarded.
+        for t in db.tables.borrow().values() {
+            if !self.tables.iter().any(|(_, s)| Rc::ptr_eq(&s.table, t)) {
+                t.file.rollback();
+                for ix in t.ixlist.borrow().iter() {
+                    ix.file.rollback();
+                }
+            }
+        }
+        for (file, pages) in &self.files {
+            file.set_dirty_pages(pages);
+        }
+        let tm = &mut *db.tables.borrow_mut();
+        tm.clear();
+        for (tname, s) in &self.tables {
+            let t = &s.table;
+            {
+                let ixlist = &mut *t.ixlist.borrow_mut();
+                for ix in &ixlist[s.ixlist..] {
+                    ix.file.rollback();
+                }
+                ixlist.truncate(s.ixlist);
+            }
+            t.fkeys.borrow_mut().truncate(s.fkeys);
+            t.refs.borrow_mut().truncate(s.refs);
+            t.defaults.borrow_mut().truncate(s.defaults);
+            t.checks.borrow_mut().truncate(s.checks);
+            *t.triggers.borrow_mut() = s.triggers.clone();
+            tm.insert(tname.clone(), t.clone());
+        }
+        // Cached schemas and functions may refer to objects created since the savepoint.
+        db.schemas.borrow_mut().clear();
+        db.functions.borrow_mut().clear();
+        if db.schema_gen.get() != self.schema_gen {
+            // Prepared statements may refer to objects created since the savepoint.
+            db.schema_gen.set(db.schema_gen.get() + 1);
+        }
+    }
+}
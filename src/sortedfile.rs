@@ -58,6 +58,21 @@ impl SortedFile {
         self.dirty_pages.borrow_mut().clear();
     }
 
+    /// Copy the unsaved pages ( for a savepoint ).
+    pub fn get_dirty(&self) -> Vec<(u64, Page)> {
+        let dp = &*self.dirty_pages.borrow();
+        dp.iter().map(|(k, pp)| (*k, pp.borrow().clone())).collect()
+    }
+
+    /// Replace the unsaved pages ( for rollback to a savepoint ).
+    pub fn set_dirty_pages(&self, pages: &[(u64, Page)]) {
+        let dp = &mut *self.dirty_pages.borrow_mut();
+        dp.clear();
+        for (k, p) in pages {
+            dp.insert(*k, util::new(p.clone()));
+        }
+    }
+
     /// Free the underlying storage. File is not useable after this.
     pub fn free_pages(&self, db: &DB, r: &dyn Record) {
         self.free_page(db, self.root_page.get(), r);
@@ -277,6 +292,9 @@ impl SortedFile {
         if !self.ok.get() {
             panic!()
         }
+        if db.profile.enabled.get() {
+            db.profile.pages.set(db.profile.pages.get() + 1);
+        }
         if let Some(p) = self.dirty_pages.borrow().get(&pnum) {
             return p.clone();
         }
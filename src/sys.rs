@@ -17,6 +17,9 @@ pub fn create_table(db: &DB, info: &ColInfo) {
     if let Some(_t) = get_table(db, &info.name) {
         panic!("table {} already exists", info.name.str());
     }
+    if get_view0(db, &info.name).is_some() {
+        panic!("table or view {} already exists", info.name.str());
+    }
     let tid = {
         let schema = &info.name.schema;
         if let Some(schema_id) = get_schema(db, schema) {
@@ -65,6 +68,14 @@ pub fn create_index(db: &DB, info: &IndexInfo) {
             t.insert(db, &mut row);
             row.id
         };
+        if info.unique {
+            let t = sys_table(db, "UniqueIndex").unwrap();
+            let mut row = t.row();
+            // Columns are Index
+            row.id = t.alloc_id(db);
+            row.values[0] = Value::Int(index_id);
+            t.insert(db, &mut row);
+        }
         {
             let t = &db.sys_index_col;
             let mut row = t.row();
@@ -77,7 +88,7 @@ pub fn create_index(db: &DB, info: &IndexInfo) {
             }
         }
         if root > SYS_ROOT_LAST {
-            table.add_index(root, info.cols.clone(), index_id);
+            table.add_index(root, info.cols.clone(), index_id, info.unique);
             table.init_index(db);
         }
     } else {
@@ -128,6 +139,212 @@ pub fn create_function(db: &DB, name: &ObjRef, source: Rc<String>, alter: bool)
     }
 }
 
+/// SQL to create the UniqueIndex system table.
+pub const UNIQUE_INDEX_TABLE: &str = "
+CREATE TABLE sys.UniqueIndex( Index int )
+GO
+CREATE INDEX ByIndex ON sys.UniqueIndex(Index)
+GO
+";
+
+/// Is the index with specified id unique.
+fn is_unique_index(db: &DB, index_id: i64) -> bool {
+    let Some(t) = sys_table(db, "UniqueIndex") else {
+        return false;
+    };
+    // Columns are Index
+    t.scan_key(db, Value::Int(index_id), 0).next().is_some()
+}
+
+/// Get a system table which is created when first needed, None if it does not exist yet.
+/// Databases created by older versions do not have these tables.
+pub fn sys_table(db: &DB, name: &str) -> Option<Rc<Table>> {
+    db.get_table(&ObjRef::new("sys", name))
+}
+
+/// SQL to create the View system table.
+pub const VIEW_TABLE: &str = "
+CREATE TABLE sys.View( Schema int, Name string(31), Def string(249) )
+GO
+CREATE INDEX BySchemaName ON sys.View(Schema,Name)
+GO
+";
+
+/// SQL to create the ForeignKey system table.
+pub const FOREIGN_KEY_TABLE: &str = "
+CREATE TABLE sys.ForeignKey( Table int, Col int, RefTable int, Action int )
+GO
+CREATE INDEX ByTable ON sys.ForeignKey(Table)
+CREATE INDEX ByRefTable ON sys.ForeignKey(RefTable)
+GO
+";
+
+/// Create a foreign key by writing to the ForeignKey system table.
+pub fn create_foreign_key(db: &DB, fk: &ForeignKey) {
+    let Some(t) = db.get_table(&fk.table) else {
+        panic!("table {} not found", fk.table.str());
+    };
+    let Some(rt) = db.get_table(&fk.rtable) else {
+        panic!("referenced table {} not found", fk.rtable.str());
+    };
+    let ft = sys_table(db, "ForeignKey").unwrap();
+    let mut row = ft.row();
+    // Columns are Table, Col, RefTable, Action
+    row.id = ft.alloc_id(db);
+    row.values[0] = Value::Int(t.id);
+    row.values[1] = Value::Int(fk.col as i64);
+    row.values[2] = Value::Int(rt.id);
+    row.values[3] = Value::Int(fk.action as i64);
+    ft.insert(db, &mut row);
+    let fk = Rc::new(ForeignKey {
+        table: fk.table.clone(),
+        col: fk.col,
+        rtable: fk.rtable.clone(),
+        action: fk.action,
+    });
+    t.fkeys.borrow_mut().push(fk.clone());
+    rt.refs.borrow_mut().push(fk);
+}
+
+/// SQL to create the Constraint system table.
+pub const CONSTRAINT_TABLE: &str = "
+CREATE TABLE sys.Constraint( Table int, Col string(31), Kind int, Def string(249) )
+GO
+CREATE INDEX ByTable ON sys.Constraint(Table)
+GO
+";
+
+/// Create a DEFAULT or CHECK constraint by writing to the Constraint system table.
+/// Result is the table, with the compiled constraint added.
+pub fn create_constraint(db: &DB, c: &Constraint) -> Rc<Table> {
+    let Some(t) = db.get_table(&c.table) else {
+        panic!("table {} not found", c.table.str());
+    };
+    let ct = sys_table(db, "Constraint").unwrap();
+    let mut row = ct.row();
+    // Columns are Table, Col, Kind, Def
+    row.id = ct.alloc_id(db);
+    row.values[0] = Value::Int(t.id);
+    row.values[1] = Value::String(Rc::new(c.col.clone()));
+    row.values[2] = Value::Int(c.kind as i64);
+    row.values[3] = Value::String(c.source.clone());
+    ct.insert(db, &mut row);
+    compile::c_constraint(db, &t, &c.col, c.kind, &c.source);
+    t
+}
+
+/// SQL to create the Trigger system table.
+pub const TRIGGER_TABLE: &str = "
+CREATE TABLE sys.Trigger( Table int, Name string(31), Event int, FnSchema string(31), FnName string(31) )
+GO
+CREATE INDEX ByTable ON sys.Trigger(Table)
+GO
+";
+
+/// Create a trigger by writing to the Trigger system table.
+pub fn create_trigger(db: &DB, tr: &Trigger) {
+    let Some(t) = db.get_table(&tr.table) else {
+        panic!("table {} not found", tr.table.str());
+    };
+    if get_trigger_id(db, &t, &tr.name).is_some() {
+        panic!("trigger {} already exists on {}", tr.name, tr.table.str());
+    }
+    let tt = sys_table(db, "Trigger").unwrap();
+    let mut row = tt.row();
+    // Columns are Table, Name, Event, FnSchema, FnName
+    row.id = tt.alloc_id(db);
+    row.values[0] = Value::Int(t.id);
+    row.values[1] = Value::String(Rc::new(tr.name.clone()));
+    row.values[2] = Value::Int(tr.event as i64);
+    row.values[3] = Value::String(Rc::new(tr.func.schema.clone()));
+    row.values[4] = Value::String(Rc::new(tr.func.name.clone()));
+    tt.insert(db, &mut row);
+    t.triggers.borrow_mut().push(Rc::new(Trigger {
+        name: tr.name.clone(),
+        table: tr.table.clone(),
+        event: tr.event,
+        func: tr.func.clone(),
+    }));
+}
+
+/// Get the id of a trigger.
+pub fn get_trigger_id(db: &DB, t: &Table, name: &str) -> Option<i64> {
+    let tt = sys_table(db, "Trigger")?;
+    // Columns are Table, Name, Event, FnSchema, FnName
+    for (pp, off) in tt.scan_key(db, Value::Int(t.id), 0) {
+        let p = &pp.borrow();
+        let a = tt.access(p, off);
+        if a.str(db, 1) == name {
+            return Some(a.id() as i64);
+        }
+    }
+    None
+}
+
+/// Get the triggers of a table.
+pub fn get_triggers(db: &DB, table: &Table, name: &ObjRef) {
+    let Some(tt) = sys_table(db, "Trigger") else {
+        return;
+    };
+    // Columns are Table, Name, Event, FnSchema, FnName
+    for (pp, off) in tt.scan_key(db, Value::Int(table.id), 0) {
+        let p = &pp.borrow();
+        let a = tt.access(p, off);
+        let event = match a.int(2) {
+            0 => TriggerEvent::Insert,
+            1 => TriggerEvent::Update,
+            _ => TriggerEvent::Delete,
+        };
+        table.triggers.borrow_mut().push(Rc::new(Trigger {
+            name: a.str(db, 1),
+            table: name.clone(),
+            event,
+            func: ObjRef::new(&a.str(db, 3), &a.str(db, 4)),
+        }));
+    }
+}
+
+/// Create a view in the database by saving the source into the View system table.
+pub fn create_view(db: &DB, name: &ObjRef, source: Rc<String>) {
+    if let Some(schema_id) = get_schema(db, &name.schema) {
+        if db.get_table(name).is_some() || get_view0(db, name).is_some() {
+            panic!("table or view {} already exists", name.str());
+        }
+        let t = sys_table(db, "View").unwrap();
+        let mut row = t.row();
+        // Columns are Schema, Name, Definition
+        row.id = t.alloc_id(db);
+        row.values[0] = Value::Int(schema_id);
+        row.values[1] = Value::String(Rc::new(name.name.clone()));
+        row.values[2] = Value::String(source);
+        t.insert(db, &mut row);
+    } else {
+        panic!("schema [{}] not found", &name.schema);
+    }
+}
+
+/// Get the id and source of a view.
+pub fn get_view0(db: &DB, name: &ObjRef) -> Option<(i64, String)> {
+    let t = sys_table(db, "View")?;
+    let schema_id = get_schema(db, &name.schema)?;
+    let keys = vec![
+        Value::Int(schema_id),
+        Value::String(Rc::new(name.name.to_string())),
+    ];
+    let (pp, off) = t.ix_get(db, keys, 0)?;
+    let p = &pp.borrow();
+    let a = t.access(p, off);
+    Some((a.id() as i64, a.str(db, 2)))
+}
+
+/// Get then parse a view from the database.
+pub fn get_view(db: &DB, name: &ObjRef) -> Option<FromExpression> {
+    let (_, source) = get_view0(db, name)?;
+    let mut p = Parser::new(&source, db);
+    p.b.parse_only = true;
+    Some(p.parse_view())
+}
+
 /// Get the id of a schema from a name.
 pub fn get_schema(db: &DB, sname: &str) -> Option<i64> {
     if let Some(&id) = db.schemas.borrow().get(sname) {
@@ -184,6 +401,96 @@ pub fn get_index(db: &DB, tname: &ObjRef, iname: &str) -> (Rc<Table>, usize, u64
     }
 }
 
+/// Get the name of the index with specified id.
+pub fn get_index_name(db: &DB, id: i64) -> String {
+    let t = &db.sys_index;
+    if let Some((pp, off)) = t.id_get(db, id as u64) {
+        let p = &pp.borrow();
+        // Columns are Root, Table, Name.
+        t.access(p, off).str(db, 2)
+    } else {
+        id.to_string()
+    }
+}
+
+/// Get the name of the table with specified id.
+fn get_table_name(db: &DB, id: i64) -> ObjRef {
+    let t = &db.sys_table;
+    let (pp, off) = t.id_get(db, id as u64).unwrap();
+    let p = &pp.borrow();
+    // Columns are root, schema, name, id_gen
+    let a = t.access(p, off);
+    let schema_id = a.int(1);
+    let name = a.str(db, 2);
+    let t = &db.sys_schema;
+    let (pp, off) = t.id_get(db, schema_id as u64).unwrap();
+    let p = &pp.borrow();
+    let schema = t.access(p, off).str(db, 0);
+    ObjRef::new(&schema, &name)
+}
+
+/// Get the foreign keys of a table, and the foreign keys which reference it.
+pub fn get_foreign_keys(db: &DB, table: &Table, name: &ObjRef) {
+    let Some(ft) = sys_table(db, "ForeignKey") else {
+        return;
+    };
+    // Columns are Table, Col, RefTable, Action
+    for (index, list) in [(0, &table.fkeys), (1, &table.refs)] {
+        for (pp, off) in ft.scan_key(db, Value::Int(table.id), index) {
+            let (tid, col, rtid, action) = {
+                let p = &pp.borrow();
+                let a = ft.access(p, off);
+                (a.int(0), a.int(1) as usize, a.int(2), a.int(3))
+            };
+            let tname = if tid == table.id {
+                name.clone()
+            } else {
+                get_table_name(db, tid)
+            };
+            let rtable = if rtid == table.id {
+                name.clone()
+            } else {
+                get_table_name(db, rtid)
+            };
+            let action = match action {
+                1 => RefAction::Cascade,
+                2 => RefAction::SetDefault,
+                _ => RefAction::Restrict,
+            };
+            list.borrow_mut().push(Rc::new(ForeignKey {
+                table: tname,
+                col,
+                rtable,
+                action,
+            }));
+        }
+    }
+}
+
+/// Get and compile the DEFAULT and CHECK constraints of a table.
+pub fn get_constraints(db: &DB, table: &Rc<Table>) {
+    let Some(ct) = sys_table(db, "Constraint") else {
+        return;
+    };
+    // Columns are Table, Col, Kind, Def
+    let list: Vec<(String, i64, String)> = ct
+        .scan_key(db, Value::Int(table.id), 0)
+        .map(|(pp, off)| {
+            let p = &pp.borrow();
+            let a = ct.access(p, off);
+            (a.str(db, 1), a.int(2), a.str(db, 3))
+        })
+        .collect();
+    for (col, kind, source) in list {
+        let kind = if kind == 0 {
+            ConstraintKind::Default
+        } else {
+            ConstraintKind::Check
+        };
+        compile::c_constraint(db, table, &col, kind, &source);
+    }
+}
+
 /// Gets table from the database.
 pub fn get_table(db: &DB, name: &ObjRef) -> Option<Rc<Table>> {
     if let Some((table_id, root, id_gen)) = get_table0(db, name) {
@@ -209,6 +516,7 @@ pub fn get_table(db: &DB, name: &ObjRef) -> Option<Rc<Table>> {
             debug_assert!(a.int(1) == table_id);
             let index_id = a.id() as i64;
             let root = a.int(0) as u64;
+            let unique = name.schema != "sys" && is_unique_index(db, index_id);
             let mut cols = Vec::new();
             let t = &db.sys_index_col;
             // Columns are Index, ColIndex
@@ -220,7 +528,12 @@ pub fn get_table(db: &DB, name: &ObjRef) -> Option<Rc<Table>> {
                 let cnum = a.int(1) as usize;
                 cols.push(cnum);
             }
-            table.add_index(root, cols, index_id);
+            table.add_index(root, cols, index_id, unique);
+        }
+        if name.schema != "sys" {
+            get_foreign_keys(db, &table, name);
+            get_constraints(db, &table);
+            get_triggers(db, &table, name);
         }
         db.publish_table(table.clone());
         Some(table)
@@ -241,7 +554,7 @@ pub fn get_function(db: &DB, name: &ObjRef) -> Option<Rc<Function>> {
             let p = &pp.borrow();
             let a = t.access(p, off);
             let source = Rc::new(a.str(db, 2));
-            let function = parse_function(db, source);
+            let function = parse_function(db, name, source);
             db.functions
                 .borrow_mut()
                 .insert(name.clone(), function.clone());
@@ -269,14 +582,16 @@ pub fn get_function_id(db: &DB, name: &ObjRef) -> Option<i64> {
 }
 
 /// Parse a function definition.
-fn parse_function(db: &DB, source: Rc<String>) -> Rc<Function> {
+fn parse_function(db: &DB, name: &ObjRef, source: Rc<String>) -> Rc<Function> {
     let mut p = Parser::new(&source, db);
     p.b.parse_only = true;
     p.parse_function();
     Rc::new(Function {
         compiled: Cell::new(false),
         ilist: RefCell::new(Vec::new()),
-        local_typ: p.b.local_typ,
+        lines: RefCell::new(Rc::new(Vec::new())),
+        name: Rc::new(format!("{}.{}", name.schema, name.name)),
+        local_typ: RefCell::new(p.b.local_typ),
         return_type: p.b.return_type,
         param_count: p.b.param_count,
         source,
@@ -8,6 +8,8 @@ pub struct Index {
     pub cols: Rc<Vec<usize>>,
     ///
     pub id: i64,
+    /// Duplicate keys are not allowed.
+    pub unique: bool,
 }
 
 /// List of indexes. Each index has a file and a list of column numbers.
@@ -41,6 +43,21 @@ pub struct Table {
 
     /// Row id allocator has changed.
     pub id_gen_dirty: Cell<bool>,
+
+    /// Foreign keys of this table.
+    pub fkeys: RefCell<Vec<Rc<ForeignKey>>>,
+
+    /// Foreign keys of other tables which reference this table.
+    pub refs: RefCell<Vec<Rc<ForeignKey>>>,
+
+    /// Compiled column DEFAULT expressions.
+    pub defaults: RefCell<Vec<(usize, CExpPtr<Value>)>>,
+
+    /// Compiled CHECK expressions, with source.
+    pub checks: RefCell<Vec<(Rc<String>, CExpPtr<bool>)>>,
+
+    /// Triggers.
+    pub triggers: RefCell<Vec<Rc<Trigger>>>,
 }
 
 impl Table {
@@ -57,6 +74,11 @@ impl Table {
             ixlist,
             id_gen: Cell::new(Some(id_gen)),
             id_gen_dirty: Cell::new(false),
+            fkeys: RefCell::new(Vec::new()),
+            refs: RefCell::new(Vec::new()),
+            defaults: RefCell::new(Vec::new()),
+            checks: RefCell::new(Vec::new()),
+            triggers: RefCell::new(Vec::new()),
         })
     }
 
@@ -80,6 +102,20 @@ impl Table {
 
     /// Insert specified row into the table.
     pub fn insert(&self, db: &DB, row: &mut Row) {
+        for (i, val) in row.values.iter().enumerate() {
+            if val.is_null() && !nullable(self.info.typ[i]) {
+                panic!(
+                    "NULL value for non-nullable column {}",
+                    self.info.colnames[i]
+                );
+            }
+        }
+        for ix in &*self.ixlist.borrow() {
+            self.check_unique(db, ix, row);
+        }
+        for fk in &*self.fkeys.borrow() {
+            self.check_ref(db, fk, row);
+        }
         row.encode(db); // Calculate codes for Binary and String values.
         self.file.insert(db, row);
         // Update any indexes.
@@ -89,6 +125,72 @@ impl Table {
         }
     }
 
+    /// Check the row does not duplicate the key of an existing row in a unique index.
+    fn check_unique(&self, db: &DB, ix: &Index, row: &Row) {
+        if !ix.unique {
+            return;
+        }
+        let key: Vec<Value> = ix.cols.iter().map(|c| row.values[*c].clone()).collect();
+        if key.iter().any(|v| v.is_null()) {
+            return;
+        }
+        let ikey = IndexKey::new(self, ix.cols.clone(), key, Ordering::Equal);
+        if ix.file.get(db, &ikey).is_some() {
+            let name = sys::get_index_name(db, ix.id);
+            let key: Vec<String> = ikey.key.iter().map(|v| v.str().to_string()).collect();
+            panic!(
+                "duplicate key ({}) in unique index {} of table {}",
+                key.join(","),
+                name,
+                self.info.name.str()
+            );
+        }
+    }
+
+    /// Check a foreign key column refers to an existing row.
+    fn check_ref(&self, db: &DB, fk: &ForeignKey, row: &Row) {
+        let val = &row.values[fk.col];
+        if val.is_null() {
+            return;
+        }
+        let id = val.int();
+        let found = if let Some(rt) = db.get_table(&fk.rtable) {
+            rt.id_get(db, id as u64).is_some()
+        } else {
+            false
+        };
+        if !found {
+            panic!(
+                "foreign key violation: {} {} not found in {}",
+                self.info.colnames[fk.col],
+                id,
+                fk.rtable.str()
+            );
+        }
+    }
+
+    /// Get the ids of rows where the specified column is equal to id.
+    pub fn find_refs(self: &Rc<Table>, db: &DB, col: usize, id: i64) -> Vec<u64> {
+        let mut kc = SmallSet::default();
+        kc.insert(col);
+        let source: DataSource = match self.best_index(&kc) {
+            Some((index, _)) if self.ixlist.borrow()[index].cols[0] == col => {
+                Box::new(self.scan_key(db, Value::Int(id), index))
+            }
+            _ => Box::new(self.scan(db)),
+        };
+        let mut row = self.row();
+        let mut result = Vec::new();
+        for (pp, off) in source {
+            let p = pp.borrow();
+            row.load(db, &p.data[off..]);
+            if row.values[col] == Value::Int(id) {
+                result.push(row.id as u64);
+            }
+        }
+        result
+    }
+
     /// Remove specified loaded row from the table.
     pub fn remove(&self, db: &DB, row: &Row) {
         self.file.remove(db, row);
@@ -106,42 +208,50 @@ impl Table {
         we: &mut Expr,
     ) -> (Option<CExpPtr<bool>>, Option<CTableExpression>) {
         let mut kc = SmallSet::default(); // Set of known columns.
-        get_known_cols(we, &mut kc);
+        let mut rc = SmallSet::default(); // Set of columns with a range condition.
+        get_known_cols(we, &mut kc, &mut rc);
 
         let list = &*self.ixlist.borrow();
 
-        let mut best_match = 0;
-        let mut best_index = 0;
-        for (index, ix) in list.iter().enumerate() {
-            let m = covered(&ix.cols, &kc);
-            if m > best_match {
-                best_match = m;
-                best_index = index;
-            }
-        }
-        if best_match > 0 {
+        if let Some((best_index, best_match, range)) = self.best_range(&kc, &rc) {
             // Get the key values for the chosen index.
             let clist = &list[best_index].cols;
             let mut cols = SmallSet::default();
             for col in clist.iter().take(best_match) {
                 cols.insert(*col);
             }
+            let rcol = if range { clist[best_match] } else { usize::MAX };
             let mut kmap = BTreeMap::new();
-            let cwe = get_keys(b, we, &mut cols, &mut kmap);
+            let mut bounds = [None, None];
+            let cwe = get_keys(b, we, &mut cols, &mut kmap, rcol, &mut bounds);
             let keys = clist
                 .iter()
                 .take(best_match)
                 .map(|col| kmap.remove(col).unwrap())
                 .collect();
+            if !range {
+                return (
+                    cwe,
+                    Some(CTableExpression::IxGet(self.clone(), keys, best_index)),
+                );
+            }
+            let [lower, upper] = bounds;
+            let r = CIndexRange {
+                index: best_index,
+                keys,
+                lower,
+                upper,
+                desc: false,
+            };
             return (
                 cwe,
-                Some(CTableExpression::IxGet(self.clone(), keys, best_index)),
+                Some(CTableExpression::IxRange(self.clone(), Box::new(r))),
             );
         }
 
-        // ToDo: check for mirror expression, AND conditions, also Id = x OR Id = y ...  Id in (....) etc.
+        // ToDo: check for mirror expression.
         if let ExprIs::Binary(op, e1, e2) = &mut we.exp {
-            if *op == Token::Equal && e2.is_constant {
+            if *op == Token::Equal && key_const(e2) {
                 if let ExprIs::ColName(_) = &e1.exp {
                     if e1.col == usize::MAX
                     // Id column.
@@ -154,9 +264,156 @@ impl Table {
                 }
             }
         }
+        if let Some(te) = self.key_list(b, we) {
+            // The whole WHERE expression is checked for each fetched record.
+            return (Some(c_bool(b, we)), Some(te));
+        }
         (Some(c_bool(b, we)), None)
     }
 
+    /// Look for IN list or OR condition which can be satisfied by a list of lookups.
+    fn key_list(self: &Rc<Table>, b: &Block, we: &mut Expr) -> Option<CTableExpression> {
+        match &mut we.exp {
+            ExprIs::Binary(Token::And, e1, e2) => {
+                if let Some(te) = self.key_list(b, e1) {
+                    return Some(te);
+                }
+                self.key_list(b, e2)
+            }
+            ExprIs::Binary(Token::Or, e1, e2) => {
+                let t1 = self.index_from(b, e1).1?;
+                let t2 = self.index_from(b, e2).1?;
+                Some(CTableExpression::KeyList(vec![t1, t2]))
+            }
+            ExprIs::Binary(Token::In, e1, e2) => {
+                if !matches!(e1.exp, ExprIs::ColName(_)) {
+                    return None;
+                }
+                let col = e1.col;
+                let list = compile::in_list(e2);
+                if !list.iter().all(key_const) {
+                    return None;
+                }
+                let parts = if col == usize::MAX {
+                    list.iter_mut()
+                        .map(|k| CTableExpression::IdGet(self.clone(), c_int(b, k)))
+                        .collect()
+                } else {
+                    let mut kc = SmallSet::default();
+                    kc.insert(col);
+                    let (index, _) = self.best_index(&kc)?;
+                    list.iter_mut()
+                        .map(|k| CTableExpression::IxGet(self.clone(), vec![c_value(b, k)], index))
+                        .collect()
+                };
+                Some(CTableExpression::KeyList(parts))
+            }
+            _ => None,
+        }
+    }
+
+    /// Find the index with the most leading columns in the set of known columns.
+    /// Result is index number and number of known columns.
+    pub fn best_index(&self, kc: &SmallSet) -> Option<(usize, usize)> {
+        let list = &*self.ixlist.borrow();
+        let mut best_match = 0;
+        let mut best_index = 0;
+        for (index, ix) in list.iter().enumerate() {
+            let m = covered(&ix.cols, kc);
+            if m > best_match {
+                best_match = m;
+                best_index = index;
+            }
+        }
+        if best_match > 0 {
+            Some((best_index, best_match))
+        } else {
+            None
+        }
+    }
+
+    /// Look for index which delivers records in the order of the specified columns ( after any leading columns known from the WHERE expression ).
+    /// Result is compiled WHERE expression and table expression, or None if no suitable index is found.
+    pub fn order_from(
+        self: &Rc<Table>,
+        b: &Block,
+        we: Option<&mut Expr>,
+        order: &[usize],
+        desc: bool,
+    ) -> Option<(Option<CExpPtr<bool>>, CTableExpression)> {
+        let mut kc = SmallSet::default(); // Set of known columns.
+        let mut rc = SmallSet::default(); // Set of columns with a range condition.
+        if let Some(we) = &we {
+            get_known_cols(we, &mut kc, &mut rc);
+        }
+        let list = &*self.ixlist.borrow();
+        let mut best: Option<(usize, usize)> = None;
+        for (index, ix) in list.iter().enumerate() {
+            let m = covered(&ix.cols, &kc);
+            if ix.cols[m..].starts_with(order) && best.is_none_or(|(_, bm)| m > bm) {
+                best = Some((index, m));
+            }
+        }
+        let (index, m) = best?;
+        let clist = &list[index].cols;
+        let range = rc.contains(clist[m]);
+        if m == 0 && !range && we.as_ref().is_some_and(|we| key_list_cond(we)) {
+            // Fetching records using an IN list or OR condition is preferred to scanning the whole index.
+            return None;
+        }
+
+        // Don't use the index if a better index is available for the WHERE expression.
+        if let Some((_, bm, brange)) = self.best_range(&kc, &rc) {
+            if 2 * bm + brange as usize > 2 * m + range as usize {
+                return None;
+            }
+        }
+        let mut cols = SmallSet::default();
+        for col in clist.iter().take(m) {
+            cols.insert(*col);
+        }
+        let rcol = if range { clist[m] } else { usize::MAX };
+        let mut kmap = BTreeMap::new();
+        let mut bounds = [None, None];
+        let cwe = if let Some(we) = we {
+            get_keys(b, we, &mut cols, &mut kmap, rcol, &mut bounds)
+        } else {
+            None
+        };
+        let keys = clist
+            .iter()
+            .take(m)
+            .map(|col| kmap.remove(col).unwrap())
+            .collect();
+        let [lower, upper] = bounds;
+        let r = CIndexRange {
+            index,
+            keys,
+            lower,
+            upper,
+            desc,
+        };
+        Some((cwe, CTableExpression::IxRange(self.clone(), Box::new(r))))
+    }
+
+    /// Find the index with the most leading known columns, preferring an index where the next column has a range condition.
+    /// Result is index number, number of known columns and whether the next column is used as a range.
+    pub fn best_range(&self, kc: &SmallSet, rc: &SmallSet) -> Option<(usize, usize, bool)> {
+        let list = &*self.ixlist.borrow();
+        let mut best = None;
+        let mut best_score = 0;
+        for (index, ix) in list.iter().enumerate() {
+            let m = covered(&ix.cols, kc);
+            let range = m < ix.cols.len() && rc.contains(ix.cols[m]);
+            let score = 2 * m + range as usize;
+            if score > best_score {
+                best_score = score;
+                best = Some((index, m, range));
+            }
+        }
+        best
+    }
+
     /// Get record with specified id.
     pub fn id_get(&self, db: &DB, id: u64) -> Option<(PagePtr, usize)> {
         self.file.get(db, &Id { id })
@@ -212,8 +469,54 @@ impl Table {
         }
     }
 
+    /// Get records with matching keys and the next index column in the specified range, in ascending or descending order.
+    /// Each bound is a value and whether the bound is inclusive.
+    pub fn scan_range(
+        self: &Rc<Table>,
+        db: &DB,
+        index: usize,
+        keys: Vec<Value>,
+        lower: Option<(Value, bool)>,
+        upper: Option<(Value, bool)>,
+        desc: bool,
+    ) -> IndexRange {
+        let ixlist = &*self.ixlist.borrow();
+        let ix = &ixlist[index];
+        let bounded = lower.is_some() || upper.is_some();
+        // Index records are visited in descending key order by Asc, and in ascending key order by Dsc.
+        let (begin, end) = if desc { (upper, lower) } else { (lower, upper) };
+        let mut start = keys.clone();
+        let mut def = if desc {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        };
+        if let Some((val, inclusive)) = begin {
+            start.push(val);
+            if !inclusive {
+                def = def.reverse();
+            }
+        }
+        let ikey = Box::new(IndexKey::new(self, ix.cols.clone(), start, def));
+        let ixs: DataSource = if desc {
+            Box::new(ix.file.asc(db, ikey))
+        } else {
+            Box::new(ix.file.dsc(db, ikey))
+        };
+        IndexRange {
+            ixs,
+            keys,
+            end,
+            desc,
+            bounded,
+            cols: ix.cols.clone(),
+            table: self.clone(),
+            db: db.clone(),
+        }
+    }
+
     /// Add the specified index to the table.
-    pub fn add_index(&self, root: u64, cols: Vec<usize>, id: i64) {
+    pub fn add_index(&self, root: u64, cols: Vec<usize>, id: i64, unique: bool) {
         let key_size = self.info.index_key_size(&cols) + 8;
         let file = Rc::new(SortedFile::new(key_size, key_size, root));
         let list = &mut self.ixlist.borrow_mut();
@@ -221,6 +524,7 @@ impl Table {
             file,
             cols: Rc::new(cols),
             id,
+            unique,
         });
     }
 
@@ -242,6 +546,7 @@ impl Table {
         for (pp, off) in self.scan(db) {
             let p = pp.borrow();
             row.load(db, &p.data[off..]);
+            self.check_unique(db, ix, &row);
             let ixr = IndexRow::new(self, ix.cols.clone(), &row);
             ix.file.insert(db, &ixr);
         }
@@ -395,6 +700,8 @@ pub struct ColInfo {
     pub typ: Vec<DataType>,
     /// Column offsets.
     pub off: Vec<usize>,
+    /// Position of the bit in the null bitmap for each column ( usize::MAX if the column is not nullable ).
+    pub nullbit: Vec<usize>,
     /// Total data size, including Id.
     pub total: usize,
 }
@@ -408,6 +715,7 @@ impl ColInfo {
             typ: Vec::new(),
             colnames: Vec::new(),
             off: Vec::new(),
+            nullbit: Vec::new(),
             total: 8,
         }
     }
@@ -428,6 +736,19 @@ impl ColInfo {
         }
         let cn = self.typ.len();
         self.typ.push(typ);
+        let bit = if nullable(typ) {
+            // Allocate a bit in the null bitmap, adding a byte to the record if necessary.
+            match self.nullbit.iter().rev().find(|b| **b != usize::MAX) {
+                Some(b) if (b + 1) % 8 != 0 => b + 1,
+                _ => {
+                    self.total += 1;
+                    (self.total - 1) * 8
+                }
+            }
+        } else {
+            usize::MAX
+        };
+        self.nullbit.push(bit);
         let size = data_size(typ);
         self.off.push(self.total);
         self.total += size;
@@ -478,7 +799,37 @@ impl ColInfo {
 
     /// Calculate the total data size for a list of index columns.
     fn index_key_size(&self, cols: &[usize]) -> usize {
-        cols.iter().map(|cnum| self.siz(*cnum)).sum()
+        cols.iter().map(|cnum| key_size(self.typ[*cnum])).sum()
+    }
+
+    /// Is the specified column NULL in the byte data.
+    pub fn is_null(&self, data: &[u8], col: usize) -> bool {
+        let bit = self.nullbit[col];
+        bit != usize::MAX && data[bit / 8] & (1 << (bit % 8)) != 0
+    }
+
+    /// Set all the nullable columns to NULL in the byte data.
+    pub fn set_nulls(&self, data: &mut [u8]) {
+        for bit in &self.nullbit {
+            if *bit != usize::MAX {
+                data[bit / 8] |= 1 << (bit % 8);
+            }
+        }
+    }
+
+    /// Get the value and code of the specified column from byte data.
+    pub fn load(&self, db: &DB, data: &[u8], col: usize) -> (Value, Code) {
+        if self.is_null(data, col) {
+            (
+                Value::Null,
+                Code {
+                    id: u64::MAX,
+                    ft: 0,
+                },
+            )
+        } else {
+            Value::load(db, self.typ[col], data, self.off[col])
+        }
     }
 } // impl ColInfo
 
@@ -491,6 +842,82 @@ pub struct IndexInfo {
     pub iname: String,
     ///
     pub cols: Vec<usize>,
+    ///
+    pub unique: bool,
+}
+
+/// Action taken when a row referenced by a foreign key is deleted.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RefAction {
+    /// The delete fails.
+    Restrict = 0,
+    /// Referencing rows are also deleted.
+    Cascade = 1,
+    /// Referencing column is set to the default value.
+    SetDefault = 2,
+}
+
+/// Foreign key: a column which refers to the Id of a row in another table.
+#[non_exhaustive]
+pub struct ForeignKey {
+    /// Referencing table.
+    pub table: ObjRef,
+    /// Referencing column.
+    pub col: usize,
+    /// Referenced table.
+    pub rtable: ObjRef,
+    /// ON DELETE action.
+    pub action: RefAction,
+}
+
+/// Kind of constraint.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConstraintKind {
+    /// Column default value.
+    Default = 0,
+    /// Condition which must be true for every row.
+    Check = 1,
+}
+
+/// Column DEFAULT or CHECK constraint.
+#[non_exhaustive]
+pub struct Constraint {
+    /// Table.
+    pub table: ObjRef,
+    /// Column name ( empty for a table CHECK ).
+    pub col: String,
+    /// Kind of constraint.
+    pub kind: ConstraintKind,
+    /// Expression source.
+    pub source: Rc<String>,
+}
+
+/// Event which fires a trigger.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TriggerEvent {
+    ///
+    Insert = 0,
+    ///
+    Update = 1,
+    ///
+    Delete = 2,
+}
+
+/// Trigger: a function which is called after a row is inserted, updated or deleted.
+/// The function parameters are the Id and column values of the old row ( UPDATE and DELETE ) then the new row ( INSERT and UPDATE ).
+#[non_exhaustive]
+pub struct Trigger {
+    /// Trigger name.
+    pub name: String,
+    /// Table.
+    pub table: ObjRef,
+    /// Event.
+    pub event: TriggerEvent,
+    /// Function.
+    pub func: ObjRef,
 }
 
 /// Row of Values, with type information.
@@ -547,12 +974,10 @@ impl Row {
         self.values.clear();
         self.codes.clear();
         self.id = util::getu64(data, 0) as i64;
-        let mut off = 8;
-        for typ in &self.info.typ {
-            let (val, code) = Value::load(db, *typ, data, off);
+        for col in 0..self.info.typ.len() {
+            let (val, code) = self.info.load(db, data, col);
             self.values.push(val);
             self.codes.push(code);
-            off += data_size(*typ);
         }
     }
 }
@@ -561,10 +986,19 @@ impl Record for Row {
     fn save(&self, data: &mut [u8]) {
         util::setu64(data, self.id as u64);
         let t = &self.info;
-        let mut off = 8;
         for (i, typ) in t.typ.iter().enumerate() {
-            self.values[i].save(*typ, data, off, self.codes[i]);
-            off += data_size(*typ);
+            let bit = t.nullbit[i];
+            if bit != usize::MAX {
+                let mask = 1 << (bit % 8);
+                if self.values[i].is_null() {
+                    data[bit / 8] |= mask;
+                    let off = t.off[i];
+                    data[off..off + data_size(*typ)].fill(0);
+                    continue;
+                }
+                data[bit / 8] &= !mask;
+            }
+            self.values[i].save(*typ, data, t.off[i], self.codes[i]);
         }
     }
 
@@ -611,12 +1045,11 @@ impl IndexRow {
         let mut off = 8;
         for col in &*self.cols {
             let typ = self.tinfo.typ[*col];
-            let val = Value::load(db, typ, data, off).0;
-            let size = data_size(typ);
-            let code = db.encode(&val, size);
+            let val = load_key(db, typ, data, off).0;
+            let code = db.encode(&val, data_size(typ));
             self.keys.push(val);
             self.codes.push(code);
-            off += size;
+            off += key_size(typ);
         }
     }
 }
@@ -628,13 +1061,13 @@ impl Record for IndexRow {
         loop {
             let typ = self.tinfo.typ[self.cols[ix]];
             // Could have special purpose Value method which compares instead of loading to save heap allocations.
-            let val = Value::load(db, typ, data, off).0;
+            let val = load_key(db, typ, data, off).0;
             let cf = val.cmp(&self.keys[ix]);
             if cf != Ordering::Equal {
                 return cf;
             }
             ix += 1;
-            off += data_size(typ);
+            off += key_size(typ);
             if ix == self.cols.len() {
                 let rowid = util::getu64(data, 0) as i64;
                 return self.rowid.cmp(&rowid);
@@ -647,8 +1080,8 @@ impl Record for IndexRow {
         let mut off = 8;
         for (ix, k) in self.keys.iter().enumerate() {
             let typ = self.tinfo.typ[self.cols[ix]];
-            k.save(typ, data, off, self.codes[ix]);
-            off += data_size(typ);
+            save_key(k, typ, data, off, self.codes[ix]);
+            off += key_size(typ);
         }
     }
 
@@ -669,11 +1102,11 @@ impl Record for IndexRow {
         let mut off = 8;
         for col in &*self.cols {
             let typ = self.tinfo.typ[*col];
-            let code = Value::load(db, typ, data, off).1;
+            let code = load_key(db, typ, data, off).1;
             if code.id != u64::MAX {
                 db.delcode(code);
             }
-            off += data_size(typ);
+            off += key_size(typ);
         }
     }
 }
@@ -710,13 +1143,13 @@ impl Record for IndexKey {
                 return self.def;
             }
             let typ = self.tinfo.typ[self.cols[ix]];
-            let val = Value::load(db, typ, data, off).0;
+            let val = load_key(db, typ, data, off).0;
             let cf = val.cmp(&self.key[ix]);
             if cf != Ordering::Equal {
                 return cf;
             }
             ix += 1;
-            off += data_size(typ);
+            off += key_size(typ);
         }
     }
 }
@@ -735,12 +1168,12 @@ impl IndexScan {
         let mut off = 8;
         for (ix, k) in self.keys.iter().enumerate() {
             let typ = self.table.info.typ[self.cols[ix]];
-            let val = Value::load(&self.db, typ, data, off).0;
+            let val = load_key(&self.db, typ, data, off).0;
             let cf = val.cmp(k);
             if cf != Ordering::Equal {
                 return false;
             }
-            off += data_size(typ);
+            off += key_size(typ);
         }
         true
     }
@@ -763,6 +1196,59 @@ impl Iterator for IndexScan {
     }
 }
 
+/// State for fetching records using a range of index keys.
+pub struct IndexRange {
+    ixs: DataSource,
+    table: Rc<Table>,
+    db: DB,
+    cols: Rc<Vec<usize>>,
+    keys: Vec<Value>,
+    /// Bound where the scan finishes.
+    end: Option<(Value, bool)>,
+    desc: bool,
+    /// NULL values are excluded.
+    bounded: bool,
+}
+
+impl Iterator for IndexRange {
+    type Item = (PagePtr, usize);
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        for (pp, off) in self.ixs.by_ref() {
+            let p = pp.borrow();
+            let data = &p.data[off..];
+            let mut off = 8;
+            for (ix, k) in self.keys.iter().enumerate() {
+                let typ = self.table.info.typ[self.cols[ix]];
+                if load_key(&self.db, typ, data, off).0.cmp(k) != Ordering::Equal {
+                    return None;
+                }
+                off += key_size(typ);
+            }
+            let typ = self.table.info.typ[self.cols[self.keys.len()]];
+            let val = load_key(&self.db, typ, data, off).0;
+            if val.is_null() && self.bounded {
+                // NULL is not in any range.
+                continue;
+            }
+            if let Some((end, inclusive)) = &self.end {
+                let past = if self.desc {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                };
+                let cf = val.cmp(end);
+                if cf == past || cf == Ordering::Equal && !inclusive {
+                    return None;
+                }
+            }
+            let id = util::getu64(data, 0);
+            return self.table.id_get(&self.db, id);
+        }
+        None
+    }
+}
+
 /// State for fetching record with specified id.
 pub struct IdScan {
     id: i64,
@@ -783,28 +1269,324 @@ impl Iterator for IdScan {
     }
 }
 
-/// Gets the list of columns that are known from a WHERE condition.
-fn get_known_cols(we: &Expr, kc: &mut SmallSet) {
+/// Records of a derived table, each holds a row number followed by zero bytes, size bytes in total.
+pub fn row_numbers(n: u64, size: usize) -> DataSource {
+    Box::new((0..n).map(move |ix| {
+        let mut data = vec![0; size];
+        data[0..8].copy_from_slice(&ix.to_le_bytes());
+        let p = Page::new(0, 0, Arc::new(data), u64::MAX);
+        (Rc::new(RefCell::new(p)), 0)
+    }))
+}
+
+/// State for fetching joined records. Each record is the concatenation of a record from each table.
+pub struct JoinScan {
+    db: DB,
+    join: Rc<CJoin>,
+    /// Values of [JoinKey::Const] keys.
+    consts: Vec<Value>,
+    /// Number of rows of each view ( see [CJoin]::derived ).
+    derived: Vec<Option<u64>>,
+    /// Record source for each table.
+    sources: Vec<Option<DataSource>>,
+    /// Whether a record has been found for each table ( for LEFT JOIN ).
+    matched: Vec<bool>,
+    /// Current table.
+    depth: usize,
+    /// Joined record.
+    rec: Vec<u8>,
+}
+
+impl JoinScan {
+    /// Construct a JoinScan, first is the source of records for the first table.
+    pub fn new(
+        db: &DB,
+        join: Rc<CJoin>,
+        consts: Vec<Value>,
+        derived: Vec<Option<u64>>,
+        first: DataSource,
+    ) -> Self {
+        let n = join.tables.len();
+        let mut sources = Vec::with_capacity(n);
+        sources.push(Some(first));
+        sources.resize_with(n, || None);
+        let rec = vec![0; join.total];
+        Self {
+            db: db.clone(),
+            join,
+            consts,
+            derived,
+            sources,
+            matched: vec![false; n],
+            depth: 0,
+            rec,
+        }
+    }
+
+    /// Get value of join key from the joined record.
+    fn key(&self, k: &JoinKey) -> Value {
+        match k {
+            JoinKey::Col(off, typ) => Value::load(&self.db, *typ, &self.rec, *off).0,
+            JoinKey::Const(x) => self.consts[*x].clone(),
+        }
+    }
+
+    /// Start fetching records for table t, using the records already fetched for preceding tables.
+    fn open(&mut self, t: usize) {
+        let table = &self.join.tables[t].1;
+        if let Some(n) = self.derived[t] {
+            self.sources[t] = Some(row_numbers(n, table.info.total));
+            self.matched[t] = false;
+            return;
+        }
+        let source: DataSource = match &self.join.steps[t - 1].access {
+            JoinAccess::Id(k) => {
+                let id = self.key(k).int();
+                Box::new(table.scan_id(&self.db, id))
+            }
+            JoinAccess::Index(index, keys) => {
+                let keys = keys.iter().map(|k| self.key(k)).collect();
+                Box::new(table.scan_keys(&self.db, keys, *index))
+            }
+            JoinAccess::Scan => Box::new(table.scan(&self.db)),
+        };
+        self.sources[t] = Some(source);
+        self.matched[t] = false;
+    }
+
+    /// Check record satisfies the join filter for table t.
+    fn filter_ok(&self, t: usize, data: &[u8]) -> bool {
+        let info = &self.join.tables[t].1.info;
+        for (col, k) in &self.join.steps[t - 1].filter {
+            let val = if *col == usize::MAX {
+                Value::Int(util::getu64(data, 0) as i64)
+            } else {
+                info.load(&self.db, data, *col).0
+            };
+            if val != self.key(k) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Check the current joined record satisfies the other ON conditions of a LEFT JOIN for table t.
+    fn pred_ok(&self, t: usize, ee: Option<&mut EvalEnv>) -> bool {
+        match &self.join.steps[t - 1].pred {
+            Some(pred) => pred.eval(ee.unwrap(), &self.rec),
+            None => true,
+        }
+    }
+
+    /// Set the byte which flags that table t has no matching record ( if t is a LEFT JOIN table ).
+    fn set_missing(&mut self, t: usize, flag: u8) {
+        let m = self.join.missing[t];
+        if m != usize::MAX {
+            self.rec[m] = flag;
+        }
+    }
+
+    /// Make a page holding the current joined record.
+    fn result(&self) -> (PagePtr, usize) {
+        let p = Page::new(0, 0, Arc::new(self.rec.clone()), u64::MAX);
+        (Rc::new(RefCell::new(p)), 0)
+    }
+}
+
+impl JoinScan {
+    /// Get the next joined record. ee is needed if a LEFT JOIN has other ON conditions ( [JoinStep]::pred ).
+    pub fn fetch(&mut self, mut ee: Option<&mut EvalEnv>) -> Option<(PagePtr, usize)> {
+        let last = self.join.tables.len() - 1;
+        loop {
+            let t = self.depth;
+            let (_, table, off) = &self.join.tables[t];
+            let (table, off) = (table.clone(), *off);
+            let size = table.info.total;
+            let mut found = false;
+            while let Some((pp, poff)) = self.sources[t].as_mut().unwrap().next() {
+                let p = pp.borrow();
+                let data = &p.data[poff..poff + size];
+                if t == 0 || self.filter_ok(t, data) {
+                    self.rec[off..off + size].copy_from_slice(data);
+                    self.set_missing(t, 0);
+                    if t == 0 || self.pred_ok(t, ee.as_deref_mut()) {
+                        found = true;
+                        break;
+                    }
+                }
+            }
+            if !found {
+                if t > 0 && !self.matched[t] && self.join.steps[t - 1].outer {
+                    // LEFT JOIN with no matching record.
+                    self.rec[off..off + size].fill(0);
+                    table.info.set_nulls(&mut self.rec[off..off + size]);
+                    self.set_missing(t, 1);
+                } else if t == 0 {
+                    return None;
+                } else {
+                    self.depth -= 1;
+                    continue;
+                }
+            }
+            self.matched[t] = true;
+            if t == last {
+                if !found {
+                    self.depth -= 1;
+                }
+                return Some(self.result());
+            }
+            if !found {
+                // Exhausted : return to preceding table once the following tables are done.
+                self.sources[t] = Some(Box::new(std::iter::empty()));
+            }
+            self.depth += 1;
+            self.open(t + 1);
+        }
+    }
+}
+
+impl Iterator for JoinScan {
+    type Item = (PagePtr, usize);
+
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        self.fetch(None)
+    }
+}
+
+/// Size of index key column. A nullable column has an extra leading byte, which is zero for NULL.
+fn key_size(typ: DataType) -> usize {
+    data_size(typ) + nullable(typ) as usize
+}
+
+/// Get index key Value from byte data.
+fn load_key(db: &DB, typ: DataType, data: &[u8], off: usize) -> (Value, Code) {
+    if !nullable(typ) {
+        Value::load(db, typ, data, off)
+    } else if data[off] == 0 {
+        (
+            Value::Null,
+            Code {
+                id: u64::MAX,
+                ft: 0,
+            },
+        )
+    } else {
+        Value::load(db, typ, data, off + 1)
+    }
+}
+
+/// Save index key Value to byte data.
+fn save_key(val: &Value, typ: DataType, data: &mut [u8], off: usize, code: Code) {
+    if !nullable(typ) {
+        val.save(typ, data, off, code);
+    } else if val.is_null() {
+        data[off..off + key_size(typ)].fill(0);
+    } else {
+        data[off] = 1;
+        val.save(typ, data, off + 1, code);
+    }
+}
+
+/// Gets the list of columns that are known from a WHERE condition, and the list of columns with a range condition.
+fn get_known_cols(we: &Expr, kc: &mut SmallSet, rc: &mut SmallSet) {
     match &we.exp {
         ExprIs::Binary(Token::Equal, e1, e2) => {
-            if e2.is_constant {
+            if key_const(e2) {
                 if let ExprIs::ColName(_) = &e1.exp {
                     kc.insert(e1.col);
                 }
-            } else if e1.is_constant {
+            } else if key_const(e1) {
                 if let ExprIs::ColName(_) = &e2.exp {
                     kc.insert(e2.col);
                 }
             }
         }
         ExprIs::Binary(Token::And, e1, e2) => {
-            get_known_cols(e1, kc);
-            get_known_cols(e2, kc);
+            get_known_cols(e1, kc, rc);
+            get_known_cols(e2, kc, rc);
+        }
+        ExprIs::Binary(op, e1, e2) if is_range_op(*op) => {
+            if let Some((col, _, _)) = range_cond(*op, e1, e2) {
+                rc.insert(col);
+            }
+        }
+        ExprIs::Like(e1, e2, escape, false) => {
+            if let Some((col, _)) = like_cond(e1, e2, *escape) {
+                rc.insert(col);
+            }
         }
         _ => {}
     }
 }
 
+/// Check whether WHERE expression has an IN list or OR condition which may be satisfied by a list of lookups.
+fn key_list_cond(we: &Expr) -> bool {
+    match &we.exp {
+        ExprIs::Binary(Token::And, e1, e2) => key_list_cond(e1) || key_list_cond(e2),
+        ExprIs::Binary(Token::Or, _, _) => true,
+        ExprIs::Binary(Token::In, e1, _) => matches!(e1.exp, ExprIs::ColName(_)),
+        _ => false,
+    }
+}
+
+/// Comparison operator which can be satisfied by an index range.
+fn is_range_op(op: Token) -> bool {
+    matches!(
+        op,
+        Token::Less | Token::LessEqual | Token::GreaterEqual | Token::Greater
+    )
+}
+
+/// Analyse range condition. Result is column number, whether the constant is a lower bound, and whether the bound is inclusive.
+fn range_cond(op: Token, e1: &Expr, e2: &Expr) -> Option<(usize, bool, bool)> {
+    let inclusive = op == Token::LessEqual || op == Token::GreaterEqual;
+    let upper = op == Token::Less || op == Token::LessEqual;
+    if key_const(e2) {
+        if let ExprIs::ColName(_) = &e1.exp {
+            if e1.col != usize::MAX {
+                return Some((e1.col, !upper, inclusive));
+            }
+        }
+    } else if key_const(e1) {
+        if let ExprIs::ColName(_) = &e2.exp {
+            if e2.col != usize::MAX {
+                return Some((e2.col, upper, inclusive));
+            }
+        }
+    }
+    None
+}
+
+/// Analyse LIKE condition. Result is column number and the literal prefix of a constant pattern ( which must not be empty ).
+fn like_cond(e1: &Expr, e2: &Expr, escape: Option<char>) -> Option<(usize, String)> {
+    if let (ExprIs::ColName(_), ExprIs::Const(Value::String(p))) = (&e1.exp, &e2.exp) {
+        if e1.col != usize::MAX {
+            let prefix = cexp::LikeMatcher::new(p, escape).prefix;
+            if !prefix.is_empty() {
+                return Some((e1.col, prefix));
+            }
+        }
+    }
+    None
+}
+
+/// Smallest string greater than every string that starts with prefix ( None if there is no such string ).
+fn prefix_upper(prefix: &str) -> Option<String> {
+    let mut chars: Vec<char> = prefix.chars().collect();
+    while let Some(c) = chars.pop() {
+        if let Some(n) = (c as u32 + 1..=char::MAX as u32).find_map(char::from_u32) {
+            chars.push(n);
+            return Some(chars.into_iter().collect());
+        }
+    }
+    None
+}
+
+/// Check expression can be used as a key ( is constant and is not NULL ).
+fn key_const(e: &Expr) -> bool {
+    e.is_constant && !nullable(e.data_type)
+}
+
 /// Count the number of index columns that are known.
 fn covered(clist: &[usize], kc: &SmallSet) -> usize {
     let mut result = 0;
@@ -817,23 +1599,25 @@ fn covered(clist: &[usize], kc: &SmallSet) -> usize {
     result
 }
 
-/// Get keys. Returns compiled bool expression ( taking into account conditions satisfied by index ).
+/// Get keys, and bounds for range column rcol. Returns compiled bool expression ( taking into account conditions satisfied by index ).
 fn get_keys(
     b: &Block,
     we: &mut Expr,
     cols: &mut SmallSet,
     keys: &mut BTreeMap<usize, CExpPtr<Value>>,
+    rcol: usize,
+    bounds: &mut [Option<(CExpPtr<Value>, bool)>; 2],
 ) -> Option<CExpPtr<bool>> {
     match &mut we.exp {
         ExprIs::Binary(Token::Equal, e1, e2) => {
-            if e2.is_constant {
+            if key_const(e2) {
                 if let ExprIs::ColName(_) = &e1.exp {
                     if cols.remove(e1.col) {
                         keys.insert(e1.col, c_value(b, e2));
                         return None;
                     }
                 }
-            } else if e1.is_constant {
+            } else if key_const(e1) {
                 if let ExprIs::ColName(_) = &e2.exp {
                     if cols.remove(e2.col) {
                         keys.insert(e2.col, c_value(b, e1));
@@ -842,9 +1626,33 @@ fn get_keys(
                 }
             }
         }
+        ExprIs::Binary(op, e1, e2) if is_range_op(*op) => {
+            if let Some((col, lower, inclusive)) = range_cond(*op, e1, e2) {
+                let bound = &mut bounds[if lower { 0 } else { 1 }];
+                if col == rcol && bound.is_none() {
+                    let k = if key_const(e2) { e2 } else { e1 };
+                    *bound = Some((c_value(b, k), inclusive));
+                    return None;
+                }
+            }
+        }
+        ExprIs::Like(e1, e2, escape, false) => {
+            // The range is the strings which start with the prefix, the LIKE test is still needed.
+            if let Some((col, prefix)) = like_cond(e1, e2, *escape) {
+                if col == rcol && bounds.iter().all(|b| b.is_none()) {
+                    let bound = |s| -> CExpPtr<Value> {
+                        Box::new(cexp::Const {
+                            value: Value::String(Rc::new(s)),
+                        })
+                    };
+                    bounds[1] = prefix_upper(&prefix).map(|u| (bound(u), false));
+                    bounds[0] = Some((bound(prefix), true));
+                }
+            }
+        }
         ExprIs::Binary(Token::And, e1, e2) => {
-            let x1 = get_keys(b, e1, cols, keys);
-            let x2 = get_keys(b, e2, cols, keys);
+            let x1 = get_keys(b, e1, cols, keys, rcol, bounds);
+            let x2 = get_keys(b, e2, cols, keys, rcol, bounds);
 
             return if let Some(c1) = x1 {
                 if let Some(c2) = x2 {
@@ -882,3 +1690,31 @@ pub fn row_compare(a: &[Value], b: &[Value], desc: &[bool]) -> Ordering {
         }
     }
 }
+
+/// Row with sort keys, ordered using [row_compare] ( for use in a BinaryHeap ).
+pub struct SortRow<'a> {
+    /// Sort keys followed by other values.
+    pub values: Vec<Value>,
+    /// Descending flag for each sort key.
+    pub desc: &'a [bool],
+}
+
+impl<'a> Ord for SortRow<'a> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        row_compare(&self.values, &other.values, self.desc)
+    }
+}
+
+impl<'a> PartialOrd for SortRow<'a> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> PartialEq for SortRow<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<'a> Eq for SortRow<'a> {}
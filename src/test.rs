@@ -256,3 +256,1861 @@ pub fn insert_delete() {
     db.save();
     assert_eq!(tr.get_error(), "");
 }
+
+#[test]
+pub fn join() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE SCHEMA test
+      CREATE TABLE test.Cust(Name string)
+      CREATE TABLE test.Order(Cust int, Item string, Qty int)
+      GO
+      CREATE INDEX ByCust ON test.Order(Cust)
+      GO
+      INSERT INTO test.Cust(Name) VALUES ('Alice'),('Bob'),('Carol')
+      INSERT INTO test.Order(Cust,Item,Qty) VALUES (1,'apple',2),(1,'pear',1),(3,'plum',5)
+      GO
+      SELECT c.Name, ':', o.Item, ' ' FROM test.Cust AS c INNER JOIN test.Order AS o ON o.Cust = c.Id
+      SELECT '|'
+      SELECT c.Name, ':', o.Item, ' ' FROM test.Cust AS c LEFT JOIN test.Order AS o ON o.Cust = c.Id
+      SELECT '|'
+      SELECT Name, ':', Item, ' ' FROM test.Order AS o JOIN test.Cust AS c ON c.Id = o.Cust AND Qty > 1
+      SELECT '|'
+      SELECT c.Name FROM test.Cust AS c WHERE c.Id = 2
+      SELECT '|'
+      SELECT c.Name, ':', o.Item, ' ' FROM test.Cust AS c LEFT JOIN test.Order AS o ON o.Cust = c.Id AND o.Qty > 1
+      SELECT '|'
+      SELECT c.Name, ':', o.Item, ' ' FROM test.Cust AS c LEFT JOIN test.Order AS o ON o.Qty < 2 AND c.Name = 'Alice'
+      SELECT '|'
+      SELECT c.Name, ':', o.Qty, ':', o.Cust + 1 IS NULL, ' ' FROM test.Cust AS c LEFT JOIN test.Order AS o ON o.Cust = c.Id WHERE o.Id IS NULL
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "Alice:apple Alice:pear Carol:plum |Alice:apple Alice:pear Bob: Carol:plum |Alice:apple Carol:plum |Bob|\
+         Alice:apple Bob: Carol:plum |Alice:pear Bob: Carol: |Bob::true "
+    );
+}
+
+#[test]
+pub fn nulls() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE SCHEMA test
+      CREATE TABLE test.Person(Name string, Age int NULL, Email string NULL, Ok bool NULL)
+      GO
+      CREATE INDEX ByAge ON test.Person(Age)
+      GO
+      INSERT INTO test.Person(Name,Age,Email,Ok) VALUES ('Alice',30,'a@x.com',true),('Bob',NULL,NULL,NULL)
+      INSERT INTO test.Person(Name) VALUES ('Carol')
+      GO
+      SELECT Name, ':', Age, ':', Email, ' ' FROM test.Person
+      SELECT '|'
+      SELECT Name FROM test.Person WHERE Age IS NULL
+      SELECT '|'
+      SELECT Name FROM test.Person WHERE Age IS NOT NULL AND NOT Ok
+      SELECT '|'
+      SELECT Name FROM test.Person WHERE Age = 30
+      SELECT '|'
+      SELECT Name FROM test.Person WHERE NOT Age = 30
+      SELECT '|'
+      SELECT Name, ' ' FROM test.Person WHERE Ok OR Age IS NULL ORDER BY Age DESC
+      SELECT '|'
+      SELECT CASE WHEN Age + 1 IS NULL THEN 'null' ELSE 'age' | (Age+1) END, ' ' FROM test.Person
+      SELECT '|'
+      DECLARE x int NULL, y int
+      SET x = NULL
+      SELECT x IS NULL, ' ', x + 1 IS NULL
+      SET x = 5, y = 2
+      SELECT ' ', x * y
+      UPDATE test.Person SET Age = NULL WHERE Name = 'Alice'
+      SELECT ' ', Name FROM test.Person WHERE Age IS NULL ORDER BY Name
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "Alice:30:a@x.com Bob:: Carol:: |BobCarol||Alice||Alice Bob Carol |age31 null null |true true 10 Alice Bob Carol"
+    );
+
+    let mut tr = GenTransaction::default();
+    db.run("INSERT INTO test.Person(Name) VALUES (NULL)", &mut tr);
+    assert!(tr
+        .get_error()
+        .starts_with("NULL value for non-nullable column Name"));
+
+    let mut tr = GenTransaction::default();
+    db.run("DECLARE y int SET y = Age FROM test.Person", &mut tr);
+    assert!(tr.get_error().contains("not nullable"));
+}
+
+#[test]
+pub fn limit() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE SCHEMA test
+      CREATE TABLE test.T(x int)
+      GO
+      DECLARE i int
+      WHILE i < 20
+      BEGIN
+        INSERT INTO test.T(x) VALUES ( ( i * 7 ) % 20 )
+        SET i += 1
+      END
+      SELECT x, ' ' FROM test.T LIMIT 3
+      SELECT '|'
+      SELECT x, ' ' FROM test.T LIMIT 3 OFFSET 2
+      SELECT '|'
+      SELECT x, ' ' FROM test.T ORDER BY x DESC LIMIT 4
+      SELECT '|'
+      SELECT x, ' ' FROM test.T WHERE x % 2 = 0 ORDER BY x LIMIT 3 OFFSET 1
+      SELECT '|'
+      SELECT x, ' ' FROM test.T ORDER BY x OFFSET 17
+      SELECT '|'
+      SELECT x FROM test.T LIMIT 0
+      SELECT '|'
+      DECLARE n int, s string
+      SET n = 2
+      FOR i = x FROM test.T WHERE x > 10 LIMIT n SET s |= i | ' '
+      FOR i = x FROM test.T ORDER BY x LIMIT n + 1 OFFSET n SET s |= i | ' '
+      SELECT s
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "0 7 14 |14 1 8 |19 18 17 16 |2 4 6 |17 18 19 ||14 15 2 3 4 "
+    );
+}
+
+#[test]
+pub fn range() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE SCHEMA test
+      CREATE TABLE test.T(x int, y int, z int NULL)
+      GO
+      CREATE INDEX ByX ON test.T(x)
+      CREATE INDEX ByYX ON test.T(y,x)
+      CREATE INDEX ByZ ON test.T(z)
+      GO
+      DECLARE i int
+      WHILE i < 20
+      BEGIN
+        INSERT INTO test.T(x,y,z) VALUES ( ( i * 7 ) % 20, i % 3, CASE WHEN i % 5 = 0 THEN NULL ELSE i END )
+        SET i += 1
+      END
+      SELECT x, ' ' FROM test.T WHERE x BETWEEN 5 AND 9
+      SELECT '|'
+      SELECT x, ' ' FROM test.T WHERE x > 15
+      SELECT '|'
+      SELECT x, ' ' FROM test.T WHERE 3 >= x
+      SELECT '|'
+      SELECT x, ' ' FROM test.T WHERE x < 12 AND x >= 10 AND x <> 11
+      SELECT '|'
+      SELECT x, ' ' FROM test.T WHERE y = 1 AND x > 10
+      SELECT '|'
+      SELECT z, ' ' FROM test.T WHERE z < 5
+      SELECT '|'
+      SELECT x, ' ' FROM test.T WHERE x > 5 AND x > 16
+      SELECT '|'
+      SELECT x, ' ' FROM test.T WHERE x > 9 AND x < 3
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "5 6 7 8 9 |16 17 18 19 |0 1 2 3 |10 |11 12 13 |1 2 3 4 |17 18 19 |"
+    );
+}
+
+#[test]
+pub fn index_order() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE SCHEMA test
+      CREATE TABLE test.T(x int, y int, z int NULL)
+      GO
+      CREATE INDEX ByX ON test.T(x)
+      CREATE INDEX ByYX ON test.T(y,x)
+      CREATE INDEX ByZ ON test.T(z)
+      GO
+      DECLARE i int
+      WHILE i < 20
+      BEGIN
+        INSERT INTO test.T(x,y,z) VALUES ( ( i * 7 ) % 20, i % 3, CASE WHEN i % 5 = 0 THEN NULL ELSE i % 4 END )
+        SET i += 1
+      END
+      SELECT x, ' ' FROM test.T ORDER BY x DESC LIMIT 5
+      SELECT '|'
+      SELECT x, ' ' FROM test.T WHERE x < 8 ORDER BY x DESC
+      SELECT '|'
+      SELECT x, ' ' FROM test.T WHERE x >= 15 ORDER BY x
+      SELECT '|'
+      SELECT x, ' ' FROM test.T WHERE y = 2 ORDER BY x DESC
+      SELECT '|'
+      SELECT y, ':', x, ' ' FROM test.T WHERE y >= 1 ORDER BY y, x LIMIT 4
+      SELECT '|'
+      SELECT z, ' ' FROM test.T ORDER BY z
+      SELECT '|'
+      SELECT z, ' ' FROM test.T WHERE z <= 1 ORDER BY z DESC
+      SELECT '|'
+      DECLARE s string
+      FOR i = x FROM test.T WHERE x > 3 ORDER BY x DESC LIMIT 3 OFFSET 1 SET s |= i | ' '
+      SELECT s
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "19 18 17 16 15 |7 6 5 4 3 2 1 0 |15 16 17 18 19 |19 18 17 16 15 14 |1:7 1:8 1:9 1:10 |    0 0 0 0 1 1 1 1 2 2 2 2 3 3 3 3 |1 1 1 1 0 0 0 0 |18 17 16 "
+    );
+}
+
+#[test]
+pub fn in_list() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE SCHEMA test
+      CREATE TABLE test.T(x int, y int, s string)
+      GO
+      CREATE INDEX ByX ON test.T(x)
+      CREATE INDEX ByS ON test.T(s)
+      GO
+      DECLARE i int
+      WHILE i < 20
+      BEGIN
+        INSERT INTO test.T(x,y,s) VALUES ( ( i * 7 ) % 20, i % 3, 'S' | i )
+        SET i += 1
+      END
+      SELECT Id, ' ' FROM test.T WHERE Id IN ( 3, 17, 5, 3, 99 ) ORDER BY Id
+      SELECT '|'
+      SELECT x, ' ' FROM test.T WHERE x IN ( 4, 2, 4, 19 ) ORDER BY x
+      SELECT '|'
+      SELECT x, ' ' FROM test.T WHERE x = 1 OR x = 3 OR s = 'S1' ORDER BY x
+      SELECT '|'
+      SELECT x, ' ' FROM test.T WHERE x IN ( 1, 2, 3, 4, 5 ) AND y = 0 ORDER BY x
+      SELECT '|'
+      SELECT x, ' ' FROM test.T WHERE y IN ( 2 ) AND x < 15 ORDER BY x
+      SELECT '|'
+      SELECT x, ' ' FROM test.T WHERE x = 3 OR y = 5
+      SELECT '|'
+      SELECT x, ' ' FROM test.T WHERE x IN ( 5, 6 ) ORDER BY x DESC
+      SELECT '|'
+      SELECT 2 IN ( 1, 2 ), ' ', 'a' IN ( 'b' )
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "3 5 17 |2 4 19 |1 3 7 |1 2 3 4 5 |14 |3 |6 5 |true false"
+    );
+}
+
+#[test]
+pub fn insert_select() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE SCHEMA test
+      CREATE TABLE test.T(x int, s string)
+      CREATE TABLE test.A(x int, s string NULL)
+      GO
+      INSERT INTO test.T(x,s) VALUES (1,'one'),(2,'two'),(3,'three'),(4,'four')
+      INSERT INTO test.A(x,s) SELECT x * 10, s FROM test.T WHERE x > 1 ORDER BY x DESC LIMIT 2
+      INSERT INTO test.A(s,x) SELECT 'five', 50
+      INSERT INTO test.T(x,s) SELECT x + 100, s | '!' FROM test.T
+      SELECT x, ':', s, ' ' FROM test.A
+      SELECT '|'
+      SELECT x, ':', s, ' ' FROM test.T
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "40:four 30:three 50:five |1:one 2:two 3:three 4:four 101:one! 102:two! 103:three! 104:four! "
+    );
+
+    let mut tr = GenTransaction::default();
+    db.run("INSERT INTO test.T(x,s) SELECT s, x FROM test.A", &mut tr);
+    assert!(tr
+        .get_error()
+        .starts_with("inserted value type mismatch expected Int got String"));
+
+    let mut tr = GenTransaction::default();
+    db.run("INSERT INTO test.T(x,s) SELECT x, s FROM test.A", &mut tr);
+    assert!(tr.get_error().starts_with("inserted value is not nullable"));
+}
+
+#[test]
+pub fn derived() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE SCHEMA test
+      CREATE TABLE test.T(x int, s string, f double, n int NULL)
+      GO
+      INSERT INTO test.T(x,s,f,n) VALUES (1,'one',PARSEFLOAT('1.5'),NULL),(2,'two',PARSEFLOAT('2.5'),20),(3,'three',PARSEFLOAT('3.5'),30),(4,'four',PARSEFLOAT('4.5'),NULL)
+      SELECT d.y, ':', d.t, ' ' FROM ( SELECT x * 10 AS y, s | '!' AS t FROM test.T WHERE x > 1 ) AS d WHERE d.y < 40 ORDER BY y DESC
+      SELECT '|'
+      SELECT t, ' ' FROM ( SELECT s AS t, x FROM test.T ORDER BY s LIMIT 3 ) AS d WHERE x <> 4
+      SELECT '|'
+      SELECT g, ' ', n IS NULL, ' ' FROM ( SELECT f AS g, n, x > 2 AS b FROM test.T ) AS d WHERE b
+      SELECT '|'
+      SELECT v FROM ( SELECT 'single' AS v ) AS d
+      SELECT '|'
+      DECLARE r string, a string
+      FOR r = z FROM ( SELECT s AS z, x FROM ( SELECT s, x FROM test.T WHERE x % 2 = 0 ) AS i ) AS o SET a |= r | ' '
+      FOR r = z FROM ( SELECT s AS z FROM test.T ) AS o WHERE z = 'two' SET a |= r
+      SELECT a
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "30:three! 20:two! |one three |3.5 false 4.5 true |single|two four two"
+    );
+}
+
+#[test]
+pub fn view() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE SCHEMA test
+      CREATE TABLE test.T(x int, s string)
+      GO
+      CREATE VIEW test.Big AS SELECT x, s | '!' AS t FROM test.T WHERE x > 2
+      GO
+      CREATE FN test.CountBig() RETURNS int AS
+      BEGIN
+        DECLARE x int
+        FOR x = x FROM test.Big SET result += 1
+      END
+      GO
+      INSERT INTO test.T(x,s) VALUES (1,'one'),(2,'two'),(3,'three'),(4,'four')
+      SELECT x, ':', t, ' ' FROM test.Big ORDER BY x DESC
+      SELECT '|'
+      SELECT b.t, ' ' FROM test.Big AS b WHERE b.x = 3
+      SELECT '|', test.CountBig()
+      DECLARE v string
+      SET v = t FROM test.Big WHERE x = 4
+      SELECT '|', v
+      SELECT '|'
+      SELECT T.s, ':', b.t, ' ' FROM test.T AS T JOIN test.Big AS b ON b.x = T.x
+      SELECT '|'
+      SELECT T.s, ':', b.t, ' ' FROM test.T AS T LEFT JOIN test.Big AS b ON b.x = T.x AND b.x < 4
+      SELECT '|'
+      SELECT b.t, ':', T.s, ' ' FROM test.Big AS b JOIN test.T AS T ON T.Id = b.x - 2
+      SELECT '|'
+      SELECT d.t, ' ' FROM ( SELECT t FROM test.Big WHERE x = 3 ) AS d
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "4:four! 3:three! |three! |2|four!|three:three! four:four! |\
+         one: two: three:three! four: |three!:one four!:two |three! "
+    );
+
+    for sql in [
+        "CREATE VIEW test.T AS SELECT 1 AS x",
+        "CREATE TABLE test.Big(y int)",
+    ] {
+        let mut tr = GenTransaction::default();
+        db.run(sql, &mut tr);
+        assert!(
+            tr.get_error().starts_with("table or view [test]."),
+            "{}",
+            tr.get_error()
+        );
+    }
+
+    let mut tr = GenTransaction::default();
+    db.run("DROP VIEW test.Big", &mut tr);
+    assert_eq!(tr.get_error(), "");
+    let mut tr = GenTransaction::default();
+    db.run("SELECT x FROM test.Big", &mut tr);
+    assert!(tr.get_error().starts_with("table [test].[Big] not found"));
+}
+
+#[test]
+pub fn unique() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE SCHEMA test
+      CREATE TABLE test.T(x int, s string)
+      GO
+      CREATE UNIQUE INDEX ByX ON test.T(x)
+      GO
+      INSERT INTO test.T(x,s) VALUES (1,'one'),(2,'two')
+      UPDATE test.T SET s = 'TWO' WHERE x = 2
+      UPDATE test.T SET x = 3 WHERE x = 2
+      SELECT x, s, ' ' FROM test.T
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(std::str::from_utf8(&tr.rp.output).unwrap(), "1one 3TWO ");
+    db.save();
+
+    let mut tr = GenTransaction::default();
+    db.run(
+        "INSERT INTO test.T(x,s) VALUES (4,'four'),(1,'uno')",
+        &mut tr,
+    );
+    assert!(tr
+        .get_error()
+        .starts_with("duplicate key (1) in unique index ByX of table [test].[T]"));
+    db.save(); // Rolls back the failed batch.
+
+    let mut tr = GenTransaction::default();
+    db.run("UPDATE test.T SET x = 1 WHERE x = 3", &mut tr);
+    assert!(tr
+        .get_error()
+        .starts_with("duplicate key (1) in unique index ByX of table [test].[T]"));
+    db.save(); // Rolls back the failed batch.
+
+    let mut tr = GenTransaction::default();
+    db.run("SELECT x, s, ' ' FROM test.T", &mut tr);
+    assert_eq!(std::str::from_utf8(&tr.rp.output).unwrap(), "1one 3TWO ");
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE TABLE test.U(x int)
+      GO
+      INSERT INTO test.U(x) VALUES (5),(5)
+      GO
+      CREATE UNIQUE INDEX ByX ON test.U(x)
+    ";
+    db.run(&sql, &mut tr);
+    assert!(tr.get_error().starts_with("duplicate key (5)"));
+}
+
+#[test]
+pub fn old_catalog() {
+    use crate::*;
+
+    let path = std::env::temp_dir().join("rustdb_old_catalog.rustdb");
+    let path = path.to_str().unwrap();
+    let _ = std::fs::remove_file(path);
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    {
+        // Remove the system tables which databases created by older versions do not have.
+        let spd = SharedPagedData::new(SimpleFileStorage::new(path));
+        let wapd = AccessPagedData::new_writer(spd.clone());
+        let db = Database::new(wapd, "", bmap.clone());
+        let mut tr = GenTransaction::default();
+        let sql = "
+          DECLARE t int, ix int
+          FOR t = Id FROM sys.Table WHERE Id > 6
+          BEGIN
+            FOR ix = Id FROM sys.Index WHERE Table = t
+            BEGIN
+              DELETE FROM sys.IndexColumn WHERE Index = ix
+            END
+            DELETE FROM sys.Index WHERE Table = t
+            DELETE FROM sys.Column WHERE Table = t
+          END
+          DELETE FROM sys.Table WHERE Id > 6
+        ";
+        db.run(&sql, &mut tr);
+        assert_eq!(tr.get_error(), "");
+        db.save();
+    }
+    {
+        let spd = SharedPagedData::new(SimpleFileStorage::new(path));
+        let wapd = AccessPagedData::new_writer(spd.clone());
+        let db = Database::new(wapd, "", bmap.clone());
+        let mut tr = GenTransaction::default();
+        let sql = "
+          CREATE SCHEMA test
+          CREATE TABLE test.T(x int)
+          CREATE TABLE test.U(x int)
+          GO
+          CREATE INDEX ByX ON test.T(x)
+          CREATE UNIQUE INDEX ByX ON test.U(x)
+          GO
+          INSERT INTO test.T(x) VALUES (1),(1)
+          INSERT INTO test.U(x) VALUES (1),(2)
+        ";
+        db.run(&sql, &mut tr);
+        assert_eq!(tr.get_error(), "");
+        db.save();
+    }
+    let spd = SharedPagedData::new(SimpleFileStorage::new(path));
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+    let mut tr = GenTransaction::default();
+    let sql = "
+      INSERT INTO test.T(x) VALUES (2),(2)
+      SELECT x FROM test.T
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(std::str::from_utf8(&tr.rp.output).unwrap(), "1122");
+
+    let mut tr = GenTransaction::default();
+    db.run("INSERT INTO test.U(x) VALUES (2)", &mut tr);
+    assert!(tr
+        .get_error()
+        .starts_with("duplicate key (2) in unique index ByX of table [test].[U]"));
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+pub fn foreign_key() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE SCHEMA test
+      CREATE TABLE test.Cust(Name string)
+      CREATE TABLE test.Order(Cust int REFERENCES test.Cust ON DELETE CASCADE, Item string)
+      CREATE TABLE test.Note(Cust int NULL REFERENCES test.Cust ON DELETE SET DEFAULT, Text string)
+      CREATE TABLE test.Invoice(Cust int REFERENCES test.Cust, Amount int)
+      GO
+      INSERT INTO test.Cust(Name) VALUES ('Ann'),('Bob'),('Cat')
+      INSERT INTO test.Order(Cust,Item) VALUES (1,'pen'),(2,'ink'),(1,'pad')
+      INSERT INTO test.Note(Cust,Text) VALUES (2,'late')
+      INSERT INTO test.Invoice(Cust,Amount) VALUES (3,10)
+      DELETE FROM test.Cust WHERE Id = 1
+      DELETE FROM test.Cust WHERE Id = 2
+      SELECT Cust, Item, ' ' FROM test.Order
+      SELECT '|', Cust, Text
+      FROM test.Note
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(std::str::from_utf8(&tr.rp.output).unwrap(), "|late");
+    db.save();
+
+    let mut tr = GenTransaction::default();
+    db.run(
+        "INSERT INTO test.Order(Cust,Item) VALUES (5,'pen')",
+        &mut tr,
+    );
+    assert!(tr
+        .get_error()
+        .starts_with("foreign key violation: Cust 5 not found in [test].[Cust]"));
+    db.save();
+
+    let mut tr = GenTransaction::default();
+    db.run("UPDATE test.Invoice SET Cust = 2 WHERE Id = 1", &mut tr);
+    assert!(tr
+        .get_error()
+        .starts_with("foreign key violation: Cust 2 not found in [test].[Cust]"));
+    db.save();
+
+    let mut tr = GenTransaction::default();
+    db.run("DELETE FROM test.Cust WHERE Id = 3", &mut tr);
+    assert!(tr
+        .get_error()
+        .starts_with("delete of [test].[Cust] Id 3 violates foreign key [test].[Invoice].Cust"));
+    db.save();
+
+    let mut tr = GenTransaction::default();
+    db.run("SELECT Name FROM test.Cust", &mut tr);
+    assert_eq!(std::str::from_utf8(&tr.rp.output).unwrap(), "Cat");
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE FN sys.DropTable( t int ) AS
+      BEGIN
+        DECLARE id int
+        FOR id = Id FROM sys.Index WHERE Table = t
+        BEGIN
+          DELETE FROM sys.IndexColumn WHERE Index = id
+        END
+        DELETE FROM sys.Index WHERE Table = t
+        DELETE FROM sys.Column WHERE Table = t
+        DELETE FROM sys.Table WHERE Id = t
+      END
+      GO
+      CREATE TABLE test.Memo(Cust int DEFAULT 3 REFERENCES test.Cust ON DELETE SET DEFAULT, Text string)
+      GO
+      INSERT INTO test.Cust(Name) VALUES ('Dan')
+      INSERT INTO test.Memo(Cust,Text) VALUES (4,'new')
+      DROP TABLE test.Note
+      GO
+      DELETE FROM test.Cust WHERE Id = 4
+      SELECT Cust, Text FROM test.Memo
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(std::str::from_utf8(&tr.rp.output).unwrap(), "3new");
+    db.save();
+
+    let mut tr = GenTransaction::default();
+    db.run(
+        "INSERT INTO test.Cust(Name) VALUES ('Eve') SELECT Name, ' ' FROM test.Cust",
+        &mut tr,
+    );
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(std::str::from_utf8(&tr.rp.output).unwrap(), "Cat Eve ");
+
+    for (sql, err) in [
+        (
+            "CREATE TABLE test.Bad(Cust int REFERENCES test.Cust ON DELETE SET DEFAULT)",
+            "ON DELETE SET DEFAULT column Cust must be nullable or have a DEFAULT",
+        ),
+        (
+            "CREATE TABLE test.Bad(Cust int DEFAULT 9 REFERENCES test.Cust ON DELETE SET DEFAULT)
+             GO
+             INSERT INTO test.Bad(Cust) VALUES (3)
+             DELETE FROM test.Invoice WHERE true
+             DELETE FROM test.Memo WHERE true
+             DELETE FROM test.Cust WHERE Id = 3",
+            "foreign key violation: Cust 9 not found in [test].[Cust]",
+        ),
+    ] {
+        let mut tr = GenTransaction::default();
+        db.run(sql, &mut tr);
+        assert!(tr.get_error().starts_with(err));
+        db.save();
+    }
+}
+
+#[test]
+pub fn constraints() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE FN sys.ClearTable(t int) AS
+      BEGIN
+        DELETE FROM test.T WHERE true
+      END
+      CREATE SCHEMA test
+      CREATE TABLE test.T(x int DEFAULT 7 CHECK x >= 0, y int, s string DEFAULT 'none', CHECK x <= y)
+      GO
+      INSERT INTO test.T(y) VALUES (10)
+      INSERT INTO test.T(x,y,s) VALUES (1,2,'one')
+      UPDATE test.T SET y = 5 WHERE x = 1
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    db.save();
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      ALTER TABLE test.T ADD z int DEFAULT 3 CHECK z < 100
+      GO
+      SELECT x, y, s, z, ' ' FROM test.T
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "710none3 15one3 "
+    );
+    db.save();
+
+    let mut tr = GenTransaction::default();
+    db.run("INSERT INTO test.T(x,y) VALUES (-1,5)", &mut tr);
+    assert!(tr
+        .get_error()
+        .starts_with("CHECK constraint x >= 0 failed for [test].[T] Id 3"));
+    db.save();
+
+    let mut tr = GenTransaction::default();
+    db.run("UPDATE test.T SET y = 0 WHERE x = 1", &mut tr);
+    assert!(tr
+        .get_error()
+        .starts_with("CHECK constraint x <= y failed for [test].[T] Id 2"));
+    db.save();
+
+    let mut tr = GenTransaction::default();
+    db.run("ALTER TABLE test.T ADD CHECK z > 5", &mut tr);
+    assert!(tr.get_error().starts_with("CHECK constraint z > 5 failed"));
+    db.save();
+
+    let mut tr = GenTransaction::default();
+    db.run("SELECT x, y, z, ' ' FROM test.T", &mut tr);
+    assert_eq!(std::str::from_utf8(&tr.rp.output).unwrap(), "7103 153 ");
+}
+
+#[test]
+pub fn trigger() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE SCHEMA test
+      CREATE TABLE test.T(x int, s string)
+      CREATE TABLE test.Log(Msg string, N int)
+      GO
+      CREATE FN test.OnInsert(id int, x int, s string) AS
+      BEGIN
+        INSERT INTO test.Log(Msg, N) VALUES ('+' | s, x)
+      END
+      CREATE FN test.OnUpdate(oid int, ox int, os string, nid int, nx int, ns string) AS
+      BEGIN
+        INSERT INTO test.Log(Msg, N) VALUES (os | '>' | ns, nx - ox)
+      END
+      CREATE FN test.OnDelete(id int, x int, s string) AS
+      BEGIN
+        INSERT INTO test.Log(Msg, N) VALUES ('-' | s, id)
+      END
+      GO
+      CREATE TRIGGER Ins AFTER INSERT ON test.T AS test.OnInsert
+      CREATE TRIGGER Upd AFTER UPDATE ON test.T AS test.OnUpdate
+      CREATE TRIGGER Del AFTER DELETE ON test.T AS test.OnDelete
+      GO
+      INSERT INTO test.T(x,s) VALUES (1,'a'),(2,'b')
+      UPDATE test.T SET x = x + 10, s = 'c' WHERE Id = 2
+      DELETE FROM test.T WHERE Id = 1
+      GO
+      DROP TRIGGER Ins ON test.T
+      GO
+      INSERT INTO test.T(x,s) VALUES (3,'d')
+      SELECT Msg, ':', N, ' ' FROM test.Log
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "+a:1 +b:2 b>c:10 -a:1 "
+    );
+    db.save();
+
+    let mut tr = GenTransaction::default();
+    db.run(
+        "CREATE TRIGGER Bad AFTER UPDATE ON test.T AS test.OnInsert",
+        &mut tr,
+    );
+    assert!(tr.get_error().starts_with("param count mismatch"));
+    db.save();
+
+    let mut tr = GenTransaction::default();
+    db.run(
+        "ALTER FN test.OnDelete(id int, x int) AS BEGIN END",
+        &mut tr,
+    );
+    assert_eq!(tr.get_error(), "");
+    db.save();
+
+    for (sql, err) in [
+        (
+            "ALTER TABLE test.T ADD y int",
+            "alter table triggers have to be dropped first",
+        ),
+        (
+            "DELETE FROM test.T WHERE true",
+            "trigger Del function [test].[OnDelete] parameters do not match table [test].[T]",
+        ),
+    ] {
+        let mut tr = GenTransaction::default();
+        db.run(sql, &mut tr);
+        assert!(tr.get_error().starts_with(err));
+        db.save();
+    }
+}
+
+#[test]
+pub fn try_catch() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE SCHEMA test
+      GO
+      CREATE FN test.ToInt(s string) RETURNS int AS
+      BEGIN
+        BEGIN TRY
+          RETURN PARSEINT(s)
+        END TRY
+        BEGIN CATCH
+          RETURN -1
+        END CATCH
+      END
+      CREATE FN test.Check(x int) AS
+      BEGIN
+        IF x > 10 THROW 'too big'
+      END
+      GO
+      SELECT test.ToInt('42'), ' ', test.ToInt('x'), ' '
+      DECLARE i int
+      SET i = 0
+      WHILE i < 20
+      BEGIN
+        SET i += 7
+        BEGIN TRY
+          EXEC test.Check(i)
+          SELECT i, ' '
+        END TRY
+        BEGIN CATCH
+          SELECT EXCEPTION(), ' '
+          BREAK
+        END CATCH
+      END
+      SELECT 'done'
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "42 -1 7 too big done"
+    );
+
+    // Changes made by a statement which fails in a TRY block are undone.
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE TABLE test.T(x int CHECK x < 10, s string)
+      GO
+      CREATE UNIQUE INDEX ByX ON test.T(x)
+      GO
+      INSERT INTO test.T(x,s) VALUES (1,'a'),(2,'b')
+      BEGIN TRY
+        INSERT INTO test.T(x,s) VALUES (3,'c'),(20,'d')
+      END TRY
+      BEGIN CATCH
+        SELECT EXCEPTION(), '|'
+      END CATCH
+      BEGIN TRY
+        UPDATE test.T SET x = x + 1, s = 'e' WHERE true
+      END TRY
+      BEGIN CATCH
+        SELECT EXCEPTION(), '|'
+      END CATCH
+      BEGIN TRY
+        INSERT INTO test.T(x,s) VALUES (5,'f')
+      END TRY
+      BEGIN CATCH
+      END CATCH
+      SELECT x, s, ' ' FROM test.T
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "CHECK constraint x < 10 failed for [test].[T] Id 4|duplicate key (2) in unique index ByX of table [test].[T]|1a 2b 5f "
+    );
+    db.save();
+
+    let mut tr = GenTransaction::default();
+    db.run("SELECT x, s, ' ' FROM test.T", &mut tr);
+    assert_eq!(std::str::from_utf8(&tr.rp.output).unwrap(), "1a 2b 5f ");
+}
+
+#[test]
+pub fn savepoint() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE SCHEMA test
+      CREATE TABLE test.Cust(Name string)
+      GO
+      INSERT INTO test.Cust(Name) VALUES ('Alice'),('Bob')
+      SAVEPOINT a
+      DECLARE i int
+      SET i = 0
+      WHILE i < 1000
+      BEGIN
+        INSERT INTO test.Cust(Name) VALUES ('Customer ' | i | ' with a fairly long name')
+        SET i += 1
+      END
+      CREATE TABLE test.Order(Cust int)
+      BEGIN TRY
+        DELETE FROM test.Cust WHERE Id = 1
+        THROW 'failed'
+      END TRY
+      BEGIN CATCH
+        ROLLBACK TO SAVEPOINT a
+      END CATCH
+      SAVEPOINT b
+      UPDATE test.Cust SET Name = 'Robert' WHERE Id = 2
+      SAVEPOINT c
+      DELETE FROM test.Cust WHERE Id = 1
+      ROLLBACK TO b
+      INSERT INTO test.Cust(Name) VALUES ('Carol')
+      SELECT Name | ' ' FROM test.Cust
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "Alice Bob Carol "
+    );
+    db.save();
+
+    // Rust API, and check the saved state.
+    db.savepoint("x");
+    let mut tr = GenTransaction::default();
+    db.run("DELETE FROM test.Cust WHERE Id = 3", &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        db.rollback_to("y"),
+        Err("savepoint y not found".to_string())
+    );
+    assert_eq!(db.rollback_to("x"), Ok(()));
+    db.save();
+
+    let rapd = AccessPagedData::new_reader(spd.clone());
+    let db = Database::new(rapd, "", bmap.clone());
+    let mut tr = GenTransaction::default();
+    db.run("SELECT Name | ' ' FROM test.Cust", &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "Alice Bob Carol "
+    );
+    tr = GenTransaction::default();
+    db.run("SELECT Name FROM test.Order", &mut tr);
+    assert!(tr.get_error().starts_with("table [test].[Order] not found"));
+}
+
+#[test]
+pub fn prepare() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE FN sys.ClearTable(t int) AS
+      BEGIN
+        DELETE FROM test.Cust WHERE true
+      END
+      CREATE SCHEMA test
+      CREATE TABLE test.Cust(Name string, Age int)
+      GO
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    db.save();
+
+    let ins = db
+        .prepare("DECLARE name string, age int INSERT INTO test.Cust(Name,Age) VALUES (name,age)")
+        .unwrap();
+    let sel = db
+        .prepare("DECLARE age int NULL SELECT Name | ' ' FROM test.Cust WHERE Age >= age")
+        .unwrap();
+    for (name, age) in [("Alice", 30), ("Bob'); DROP TABLE test.Cust --", 20)] {
+        ins.execute(
+            &[Value::String(Rc::new(name.to_string())), Value::Int(age)],
+            &mut tr,
+        );
+        assert_eq!(tr.get_error(), "");
+    }
+    sel.execute(&[Value::Int(25)], &mut tr);
+    assert_eq!(tr.get_error(), "");
+    sel.execute(&[Value::Int(10)], &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "Alice Alice Bob'); DROP TABLE test.Cust -- "
+    );
+    db.save();
+
+    let mut tr = GenTransaction::default();
+    sel.execute(&[Value::String(Rc::new("x".to_string()))], &mut tr);
+    assert_eq!(
+        tr.get_error(),
+        "parameter 1 value does not match type in prepared statement."
+    );
+    let mut tr = GenTransaction::default();
+    sel.execute(&[], &mut tr);
+    assert_eq!(
+        tr.get_error(),
+        "expected 1 parameter values, got 0 in prepared statement."
+    );
+    assert!(db.prepare("SELECT x").is_err());
+    db.save();
+
+    // Statements prepared before the schema changes cannot be executed.
+    let mut tr = GenTransaction::default();
+    db.run("ALTER TABLE test.Cust ADD Email string", &mut tr);
+    assert_eq!(tr.get_error(), "");
+    db.save();
+    sel.execute(&[Value::Int(25)], &mut tr);
+    assert_eq!(
+        tr.get_error(),
+        "schema changed since statement was prepared in prepared statement."
+    );
+    db.save();
+    let sel = db
+        .prepare("DECLARE age int NULL SELECT Name | Email | ' ' FROM test.Cust WHERE Age >= age")
+        .unwrap();
+    sel.execute(&[Value::Int(25)], &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(std::str::from_utf8(&tr.rp.output).unwrap(), "Alice ");
+}
+
+#[test]
+pub fn explain() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE SCHEMA test
+      CREATE TABLE test.Cust(Name string, Age int)
+      CREATE TABLE test.Order(Cust int, Total int)
+      GO
+      CREATE INDEX ByName ON test.Cust(Name,Age)
+      CREATE INDEX ByCust ON test.Order(Cust)
+      GO
+      EXPLAIN SELECT Age FROM test.Cust WHERE Id = 1
+      EXPLAIN SELECT Age FROM test.Cust WHERE Name = 'Alice' AND Age > 20
+      EXPLAIN SELECT Name FROM test.Cust WHERE Name = 'Alice'
+      EXPLAIN SELECT Name FROM test.Cust WHERE Age = 20 ORDER BY Name
+      EXPLAIN SELECT Total FROM test.Cust AS C INNER JOIN test.Order AS O ON O.Cust = C.Id
+      DECLARE x int
+      EXPLAIN FOR x = Age FROM test.Cust WHERE Name = 'Bob' SELECT x
+      EXPLAIN DELETE FROM test.Order WHERE Cust = 1
+      EXPLAIN SELECT Total FROM test.Order ORDER BY Total
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "0: Select 1 columns
+  Id lookup [test].[Cust]
+0: Select 1 columns
+  Index range [test].[Cust] index ByName key (Name) range Age
+0: Select 1 columns
+  Index lookup [test].[Cust] index ByName key (Name)
+0: Select 1 columns
+  Index range [test].[Cust] index ByName
+0: Select 1 columns
+  Join
+    Scan [test].[Cust] C
+    Index lookup [test].[Order] O index ByCust key (Cust)
+0: ForInit 1
+  Index lookup [test].[Cust] index ByName key (Name)
+1: ForNext 1 exit at 4
+2: Select 1 columns
+3: Jump 1
+0: DataOp Delete
+  Index lookup [test].[Order] index ByCust key (Cust)
+0: Select 1 columns
+  Sort by 1 keys
+  Scan [test].[Order]
+"
+    );
+}
+
+#[test]
+pub fn profile() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE SCHEMA test
+      CREATE TABLE test.T(x int)
+      GO
+      INSERT INTO test.T(x) VALUES (1),(2),(3)
+      GO
+      CREATE FN test.Sum() RETURNS int AS
+      BEGIN
+        DECLARE s int, i int
+        FOR i = x FROM test.T
+          SET s += i
+        RETURN s
+      END
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+
+    struct ProfileTransaction {
+        entries: Vec<ProfileEntry>,
+    }
+    impl Transaction for ProfileTransaction {
+        fn selected(&mut self, _values: &[Value]) {}
+        fn set_error(&mut self, err: String) {
+            panic!("{}", err);
+        }
+        fn profile(&mut self, entries: &[ProfileEntry]) {
+            self.entries = entries.to_vec();
+        }
+    }
+
+    db.set_profile(true);
+    let mut tr = ProfileTransaction {
+        entries: Vec::new(),
+    };
+    db.run(
+        "SELECT test.Sum()\nSELECT test.Sum()\nGO\nSELECT test.Sum()",
+        &mut tr,
+    );
+    db.set_profile(false);
+
+    let list: Vec<_> = tr
+        .entries
+        .iter()
+        .map(|e| (e.name.as_str(), e.section, e.ip, e.line, e.count, e.rows))
+        .collect();
+    assert_eq!(
+        list,
+        vec![
+            ("batch", 1, Some(0), 1, 1, 3),
+            ("batch", 1, Some(1), 2, 1, 3),
+            ("batch", 2, Some(0), 4, 1, 3),
+            ("test.Sum", 0, None, 0, 3, 9),
+            ("test.Sum", 0, Some(0), 4, 3, 0),  // ForInit
+            ("test.Sum", 0, Some(1), 4, 12, 9), // ForNext
+            ("test.Sum", 0, Some(2), 5, 9, 0),  // SET s += i
+            ("test.Sum", 0, Some(3), 4, 9, 0),  // Jump
+            ("test.Sum", 0, Some(4), 6, 3, 0),  // RETURN s
+            ("test.Sum", 0, Some(5), 6, 3, 0),
+        ]
+    );
+    assert!(tr.entries[3].pages > 0);
+    assert_eq!(db.get_profile().len(), 10);
+}
+
+#[test]
+pub fn datetime() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE SCHEMA test
+      CREATE TABLE test.Event(Name string, At datetime, Done datetime NULL)
+      GO
+      CREATE INDEX ByAt ON test.Event(At)
+      GO
+      INSERT INTO test.Event(Name,At) VALUES
+        ('c', PARSEDATETIME('2024-03-01T09:30:00Z')),
+        ('a', PARSEDATETIME('1969-12-31 23:59:59.5')),
+        ('b', PARSEDATETIME('2024-02-29T10:15:00+01:00'))
+      SELECT Name, ' ', At, ' ' FROM test.Event WHERE At > PARSEDATETIME('1970-01-01') ORDER BY At
+      SELECT Name | ':' | At | ' ' FROM test.Event WHERE At < PARSEDATETIME('2000-01-01')
+      DECLARE d datetime
+      SET d = PARSEDATETIME('2024-01-31T12:00:00')
+      SELECT DATEADD('month', 1, d), ' ', DATEADD('year', 1, DATEADD('day', 29, d)), ' '
+      SELECT DATETRUNC('month', d), ' ', DATETRUNC('day', d), ' ', DATETRUNC('week', d), ' '
+      SELECT DATEPART('year', d), ' ', DATEPART('month', d), ' ', DATEPART('weekday', d), ' ', DATEPART('dayofyear', d), ' '
+      SELECT DATEDIFF('day', d, PARSEDATETIME('2024-03-01')), ' ', DATEDIFF('month', d, PARSEDATETIME('2024-03-01')), ' '
+      SELECT FORMATDATETIME(d, '%d/%m/%Y %H:%M'), ' ', DATETIMESTR(TODATETIME(0)), ' '
+      SELECT Name | ' ' FROM test.Event WHERE Done IS NULL AND At = PARSEDATETIME('2024-02-29T09:15:00')
+      UPDATE test.Event SET Done = At WHERE Name = 'a'
+      SELECT Name | ':' | Done | ' ' FROM test.Event WHERE Name < 'c' ORDER BY Name
+      SELECT Name, ':', DATEPART('year', Done), ':', DATEADD('day', 1, Done), ':', FORMATDATETIME(Done, '%Y'), ' ' FROM test.Event WHERE Name < 'c' ORDER BY Name
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "b 2024-02-29T09:15:00 c 2024-03-01T09:30:00 a:1969-12-31T23:59:59.500000 \
+         2024-02-29T12:00:00 2025-02-28T12:00:00 \
+         2024-01-01T00:00:00 2024-01-31T00:00:00 2024-01-29T00:00:00 \
+         2024 1 3 31 30 2 31/01/2024 12:00 1970-01-01T00:00:00 b \
+         a:1969-12-31T23:59:59.500000 a:1969:1970-01-01T23:59:59.500000:1969 b::: "
+    );
+
+    for (sql, err) in [
+        (
+            "SELECT PARSEDATETIME('2024-02-30')",
+            "invalid datetime '2024-02-30'",
+        ),
+        (
+            "SELECT DATEADD('day', 1, 5)",
+            "Builtin function arg 3 type mismatch expected DateTime got Int",
+        ),
+        ("DECLARE d datetime SELECT d + 1", "binary op type mismatch"),
+        (
+            "SELECT PARSEDATETIME('2020-01-01T10:00:00.')",
+            "invalid datetime",
+        ),
+        (
+            "SELECT PARSEDATETIME('2020-01-01T10:00:00+25:00')",
+            "invalid datetime",
+        ),
+        (
+            "SELECT PARSEDATETIME('2020-01-01T10:00:00+10:99')",
+            "invalid datetime",
+        ),
+        (
+            "SELECT DATEADD('year', 100000000, TODATETIME(0))",
+            "datetime out of range",
+        ),
+        (
+            "SELECT DATEADD('month', 9223372036854775807, TODATETIME(0))",
+            "datetime out of range",
+        ),
+        (
+            "SELECT DATEADD('day', 9223372036854775807, TODATETIME(0))",
+            "datetime out of range",
+        ),
+        (
+            "DECLARE d datetime NULL, y int SET y = DATEPART('year', d)",
+            "assigned variable is not nullable",
+        ),
+    ] {
+        let mut tr = GenTransaction::default();
+        db.run(sql, &mut tr);
+        assert!(tr.get_error().starts_with(err), "{}", tr.get_error());
+    }
+}
+
+#[test]
+pub fn decimal() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE SCHEMA test
+      CREATE TABLE test.Line(Item string, Price decimal(10,2), Qty int, Discount decimal(4,3) NULL)
+      GO
+      CREATE INDEX ByPrice ON test.Line(Price)
+      GO
+      INSERT INTO test.Line(Item,Price,Qty,Discount) VALUES
+        ('pen', 0.1, 3, NULL),
+        ('ink', 0.2, 1, 0.125),
+        ('pad', 2.005, 2, NULL),
+        ('bag', -1.995, 1, NULL),
+        ('box', 12, 1, 0.5)
+      DECLARE total decimal(12,2), avg decimal(10,2), p decimal(10,2), q int
+      FOR p = Price, q = Qty FROM test.Line SET total += p * q
+      SELECT total, ' ', 0.1 + 0.2 = 0.3, ' ', 10.00 / 3, ' ', -1.5 * 1.5, ' ', 7.5 % 2, ' '
+      SET avg = total / 3
+      SELECT avg, ' '
+      SELECT Item | ' ' FROM test.Line WHERE Price >= 0.2 AND Price < 12 ORDER BY Price
+      SELECT Item, '=', Price, ' ' FROM test.Line WHERE Price = 2.01
+      SELECT Item, ':', -Price * (1 - Discount), ' ' FROM test.Line WHERE Discount IS NOT NULL ORDER BY Price
+      SELECT Item | ' ' FROM test.Line WHERE Price < 0
+      GO
+      CREATE FN test.Tax(x decimal(10,2)) RETURNS decimal(10,2) AS
+      BEGIN
+        RETURN x * 0.2
+      END
+      CREATE FN test.Show(x decimal(10,2)) AS
+      BEGIN
+        SELECT x, ' '
+      END
+      GO
+      SELECT test.Tax(5), ' ', test.Tax(2.5), ' '
+      EXEC test.Show(3)
+      EXEC test.Show(1.005)
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "14.52 true 3.333333 -2.25 1.5 4.84 \
+         ink pad pad=2.01 ink:-0.17500 box:-6.00000 bag 1.00 0.50 3.00 1.01 "
+    );
+
+    for (sql, err) in [
+        (
+            "INSERT INTO test.Line(Item,Price,Qty) VALUES ('car', 123456789.99, 1)",
+            "decimal value too large for decimal(10,2)",
+        ),
+        ("SELECT 1.5 / 0", "division by zero"),
+        (
+            "DECLARE x decimal(19,2)",
+            "decimal precision must be 1 to 18",
+        ),
+        ("SELECT 1.5 + 'a'", "binary op type mismatch"),
+        (
+            "DECLARE x decimal(4,2) SET x = 123.4",
+            "decimal value too large for decimal(4,2)",
+        ),
+        (
+            "DECLARE x decimal(3,2), n int FOR x = Price FROM test.Line SET n += 1",
+            "decimal value too large for decimal(3,2)",
+        ),
+        (
+            "SELECT test.Tax(123456789.5)",
+            "decimal value too large for decimal(10,2)",
+        ),
+    ] {
+        let mut tr = GenTransaction::default();
+        db.run(sql, &mut tr);
+        assert!(tr.get_error().starts_with(err), "{}", tr.get_error());
+    }
+}
+
+#[test]
+pub fn json() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = r#"
+      CREATE SCHEMA test
+      CREATE TABLE test.Cust(Name string, Age int NULL, Since datetime, Balance decimal(8,2))
+      GO
+      INSERT INTO test.Cust(Name,Age,Since,Balance) VALUES
+        ('Ann "A"', 31, PARSEDATETIME('2020-05-01'), 10.5),
+        ('Bob', NULL, PARSEDATETIME('2021-01-02T03:04:05'), -2)
+      SELECT Name, Age, Since, Balance, Age > 30 AS Old FROM test.Cust ORDER BY Name FOR JSON
+      SELECT ' '
+      SELECT Name AS n FROM test.Cust WHERE Name = 'x' FOR JSON
+      DECLARE doc string
+      SET doc = '{ "id": 7, "tags": ["a", "b\"c"], "owner": { "name": "Zoë", "ok": true, "x": null } }'
+      SELECT ' ', JSONVALUE(doc, '$.id'), ' ', JSONVALUE(doc, '$.tags[1]'), ' ', JSONVALUE(doc, 'owner.name')
+      SELECT ' ', JSONVALUE(doc, '$.owner.ok'), ' [', JSONVALUE(doc, '$.owner.x'), JSONVALUE(doc, '$.nope[2]'), '] '
+      SELECT JSONVALUE(doc, '$.tags'), ' ', JSONESCAPE('a"b\c' | '
+'), ' '
+      SELECT JSONOBJECT('id', 1, 'tags', JSONARRAY('x', 2.50, true, NULL), 'note', '[1]')
+      SELECT ' '
+      SELECT Name, Balance * 2, Age AS col, Balance + 1 FROM test.Cust WHERE Name = 'Bob' FOR JSON
+      SELECT ' ', JSONVALUE('[-0.5e+3, 1E2, 0]', '$[0]'), ' ', JSONVALUE('[-0.5e+3, 1E2, 0]', '$[1]')
+    "#;
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        r#"[{"Name":"Ann \"A\"","Age":31,"Since":"2020-05-01T00:00:00","Balance":10.50,"Old":true},"#.to_string()
+            + r#"{"Name":"Bob","Age":null,"Since":"2021-01-02T03:04:05","Balance":-2.00,"Old":null}] [] "#
+            + r#"7 b"c Zoë true [] ["a", "b\"c"] a\"b\\c\n {"id":1,"tags":["x",2.50,true,null],"note":"[1]"} "#
+            + r#"[{"Name":"Bob","col2":-4.00,"col":null,"col4":-1.00}] -0.5e+3 1E2"#
+    );
+
+    for (sql, err) in [
+        (
+            "SELECT JSONVALUE('{\"a\":1}', 'a..b')",
+            "invalid JSON path 'a..b'",
+        ),
+        (
+            "SELECT JSONVALUE('{\"a\" 1}', 'a')",
+            "invalid JSON at position 5",
+        ),
+        (
+            "SELECT JSONVALUE('[1,2', '$[0]')",
+            "invalid JSON at position 4",
+        ),
+        (
+            "SELECT JSONVALUE('[1] x', '$[0]')",
+            "invalid JSON at position 4",
+        ),
+        (
+            "SELECT JSONVALUE('{\"a\":tru}', 'a')",
+            "invalid JSON at position 5",
+        ),
+        (
+            "SELECT JSONVALUE('{\"a\":+1}', 'a')",
+            "invalid JSON at position 5",
+        ),
+        (
+            "SELECT JSONVALUE('[01]', '$[0]')",
+            "invalid JSON at position 1",
+        ),
+        (
+            "SELECT JSONVALUE('[.5]', '$[0]')",
+            "invalid JSON at position 1",
+        ),
+        (
+            "SELECT JSONVALUE('[1.]', '$[0]')",
+            "invalid JSON at position 1",
+        ),
+        (
+            "SELECT JSONVALUE('[1e]', '$[0]')",
+            "invalid JSON at position 1",
+        ),
+        (
+            "SELECT JSONVALUE('[-]', '$[0]')",
+            "invalid JSON at position 1",
+        ),
+        (
+            "SELECT JSONOBJECT('a')",
+            "JSONOBJECT needs name and value pairs",
+        ),
+    ] {
+        let mut tr = GenTransaction::default();
+        db.run(sql, &mut tr);
+        assert!(tr.get_error().starts_with(err), "{}", tr.get_error());
+    }
+}
+
+#[test]
+pub fn like() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE SCHEMA test
+      CREATE TABLE test.Cust(Name string, Code string NULL)
+      GO
+      CREATE INDEX ByName ON test.Cust(Name)
+      GO
+      INSERT INTO test.Cust(Name,Code) VALUES
+        ('Smith', 'A_1'), ('Smyth', 'A%2'), ('Smithers', NULL), ('Jones', 'B_1'),
+        ('Sm', 'x'), ('Zoë', 'Zo'), ('Sn', 'y')
+      SELECT Name | ' ' FROM test.Cust WHERE Name LIKE 'Sm%' ORDER BY Name
+      SELECT '| '
+      SELECT Name | ' ' FROM test.Cust WHERE Name LIKE 'Sm_th' ORDER BY Name
+      SELECT '| '
+      SELECT Name | ' ' FROM test.Cust WHERE Name LIKE '%th%s' OR Name LIKE '_o%' ORDER BY Name
+      SELECT '| '
+      SELECT Name | ' ' FROM test.Cust WHERE Code LIKE 'A!_%' ESCAPE '!' ORDER BY Name
+      SELECT '| '
+      SELECT Name | ' ' FROM test.Cust WHERE Code NOT LIKE '%1' ORDER BY Name
+      SELECT '| '
+      SELECT Name | ' ' FROM test.Cust WHERE Name NOT LIKE 'Sm%' ORDER BY Name
+      SELECT '| '
+      SELECT Name | ' ' FROM test.Cust WHERE Name NOT LIKE Code ORDER BY Name
+      SELECT '| '
+      DECLARE p string
+      SET p = 'Z_ë'
+      SELECT Name | ' ' FROM test.Cust WHERE Name LIKE p
+      SELECT Name | ' ' FROM test.Cust WHERE Name LIKE 'Sm' AND Name LIKE '%'
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "Sm Smith Smithers Smyth | Smith Smyth | Jones Smithers Zoë | Smith | Sm Smyth Sn Zoë | Jones Sn Zoë | ".to_string()
+            + "Jones Sm Smith Smyth Sn Zoë | Zoë Sm "
+    );
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      EXPLAIN SELECT Name FROM test.Cust WHERE Name LIKE 'Sm%'
+      EXPLAIN SELECT Name FROM test.Cust WHERE Name LIKE '%m'
+      EXPLAIN SELECT Name FROM test.Cust WHERE Name NOT LIKE 'Sm%'
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    let out = std::str::from_utf8(&tr.rp.output).unwrap();
+    assert!(
+        out.contains("Index range [test].[Cust] index ByName range Name"),
+        "{}",
+        out
+    );
+    assert!(out.contains("Scan [test].[Cust]"), "{}", out);
+    assert_eq!(out.matches("Index range").count(), 1, "{}", out);
+
+    for (sql, err) in [
+        ("SELECT 1 LIKE 'a'", "LIKE needs string operands"),
+        (
+            "SELECT 'a' LIKE 'a' ESCAPE 'ab'",
+            "ESCAPE must be a single character string",
+        ),
+        (
+            "SELECT 'a' LIKE 'a!' ESCAPE '!'",
+            "invalid escape in LIKE pattern",
+        ),
+    ] {
+        let mut tr = GenTransaction::default();
+        db.run(sql, &mut tr);
+        assert!(tr.get_error().starts_with(err), "{}", tr.get_error());
+    }
+}
+
+#[cfg(feature = "regex")]
+#[test]
+pub fn regex() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = r"
+      CREATE SCHEMA test
+      CREATE TABLE test.Contact(Email string, Phone string)
+      GO
+      INSERT INTO test.Contact(Email,Phone) VALUES
+        ('ann@example.com', '(01234) 567 890'), ('bob@', '+44 1234-567890'), ('Cy@Mail.org', '12 34')
+      SELECT Email | ' ' FROM test.Contact WHERE REGEXMATCH(Email, '^[^@\s]+@[^@\s]+\.[a-z]+$')
+      SELECT '| '
+      SELECT REGEXREPLACE(Phone, '[^0-9]', '') | ' ' FROM test.Contact
+      SELECT '| '
+      SELECT REGEXEXTRACT(Email, '@([^.]+)\.(\w+)', 2) | ',' | REGEXEXTRACT(Email, '@([^.]+)', 1) | ' ' FROM test.Contact
+      SELECT '| '
+      DECLARE p string, n int
+      FOR p = Email FROM test.Contact
+      BEGIN
+        IF REGEXMATCH(p, '(?i)^' | SUBSTRING(p, 1, 1) | '.*m') SET n += 1
+      END
+      SELECT n, ' ', REGEXREPLACE('2024-02-29', '(\d+)-(\d+)-(\d+)', '$3/$2/$1'), ' ', REGEXEXTRACT('abc', 'x', 0), '.'
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "ann@example.com Cy@Mail.org | 01234567890 441234567890 1234 | com,example , org,Mail | 2 29/02/2024 ."
+    );
+
+    for (sql, err) in [
+        (
+            "SELECT REGEXMATCH('a', '(')",
+            "invalid regular expression '('",
+        ),
+        (
+            "DECLARE p string SET p = '[' SELECT REGEXMATCH('a', p)",
+            "invalid regular expression '['",
+        ),
+    ] {
+        let mut tr = GenTransaction::default();
+        db.run(sql, &mut tr);
+        assert!(tr.get_error().starts_with(err), "{}", tr.get_error());
+    }
+}
+
+#[test]
+pub fn string_functions() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      DECLARE s string
+      SET s = 'Zoë Ångström'
+      SELECT LEN(s), ' ', CHARLEN(s), ' ', CHARSUBSTRING(s, 3, 4), ' ', CHARSUBSTRING(s, 0, 2), ' ', CHARSUBSTRING(s, 11, 5), '|'
+      SELECT UPPER(s), ' ', LOWER(s), ' ', UPPER('straße'), '|'
+      SELECT '[', TRIM('  a b  '), '][', LTRIM('  a '), '][', RTRIM(' a  '), ']|'
+      SELECT LEFT(s, 3), ' ', RIGHT(s, 8), ' ', LEFT(s, 50), ' ', RIGHT(s, 0), LEFT(s, -1), '|'
+      SELECT INDEXOF(s, 'ö'), ' ', INDEXOF(s, 'x'), ' ', INDEXOF(s, 'Z'), '|'
+      SELECT PADLEFT('7', 3, '0'), ' ', PADLEFT('1234', 3, '0'), ' ', PADLEFT('é', 4, 'ab'), '|'
+      SELECT SPLITPART('a,b,,d', ',', 2), ' ', SPLITPART('a,b,,d', ',', 3), ' ', SPLITPART('a,b,,d', ',', 4), ' ', SPLITPART('a', ',', 2), '|'
+      SELECT REVERSE(s), ' ', REPEAT('ab', 3), ' ', REPEAT('ab', 0), '|'
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "15 12 ë Ån Z öm|ZOË ÅNGSTRÖM zoë ångström STRASSE|[a b][a ][ a]|\
+         Zoë Ångström Zoë Ångström |11 0 1|007 1234 abaé|b  d |mörtsgnÅ ëoZ ababab |"
+    );
+
+    for (sql, err) in [
+        (
+            "SELECT REPEAT('ab', 1000000000)",
+            "REPEAT result is too long",
+        ),
+        (
+            "SELECT PADLEFT('a', 9223372036854775807, 'x')",
+            "PADLEFT result is too long",
+        ),
+    ] {
+        let mut tr = GenTransaction::default();
+        db.run(sql, &mut tr);
+        assert!(tr.get_error().starts_with(err), "{}", tr.get_error());
+    }
+}
+
+#[test]
+pub fn cast() {
+    use crate::*;
+
+    let stg = AtomicFile::new(MemFile::new(), MemFile::new());
+
+    let mut bmap = BuiltinMap::default();
+    standard_builtins(&mut bmap);
+    let bmap = Arc::new(bmap);
+
+    let spd = SharedPagedData::new(stg);
+    let wapd = AccessPagedData::new_writer(spd.clone());
+    let db = Database::new(wapd, "", bmap.clone());
+
+    let mut tr = GenTransaction::default();
+    let sql = "
+      CREATE SCHEMA test GO
+      CREATE TABLE test.M(Name string, Amount decimal(8,2) NULL) GO
+      CREATE TABLE test.F(Name string, X float NULL) GO
+      INSERT INTO test.M(Name,Amount) VALUES ('a', -12.35), ('b', NULL)
+      INSERT INTO test.F(Name,X) VALUES ('a', CAST(16 AS float)), ('b', NULL)
+      DECLARE d datetime, i int
+      SET d = CAST(' 2024-02-29T12:00:00 ' AS datetime)
+      SET i = CAST('42' AS int) + CAST(7.9 AS int) + CAST(-7.9 AS int) + CAST(true AS int)
+      SELECT i, ' ', CAST(d AS string), ' ', CAST(CAST(d AS int) + 1 AS datetime), '|'
+      SELECT CAST('2.5' AS float) * CAST(2 AS float), ' ', CAST(3 AS float) / CAST(2 AS float), ' ', CAST(1.255 AS decimal(5,2)), ' ', CAST(2.675 AS decimal(5,2)), '|'
+      SELECT CAST(0x414243 AS string), ' ', CAST('AB' AS binary), ' ', CAST(CAST(258 AS binary) AS int), ' ', CAST('false' AS bool), ' ', CAST(0.0 AS bool), '|'
+      SELECT TRY_CAST('x' AS int), ' ', TRY_CAST('x' AS decimal(4,1)), ' ', TRY_CAST(123456.0 AS decimal(4,1)), ' ', TRY_CAST(CAST('1e30' AS float) AS int), '|'
+      SELECT Name, ':', TRY_CAST(Amount AS int), ':', TRY_CAST('x' AS int NULL), ':', CAST(NULL AS string) FROM test.M
+      SELECT '|'
+      SELECT ABS(-3), ' ', ABS(-2.5), ' ', SIGN(-4.00), ' ', SIGN(0), ' ', FLOOR(-2.5), ' ', CEILING(2.01), ' ', FLOOR(7), ' ', CEILING(-1.5), '|'
+      SELECT ROUND(2.345, 2), ' ', ROUND(-2.5), ' ', ROUND(1250, -2), ' ', ROUND(CAST(2.5 AS float)), ' ', ROUND(1.2345678, 3), '|'
+      SELECT MOD(-7, 3), ' ', MOD(7.5, 2), ' ', MOD(CAST(7.5 AS float), CAST(2 AS float)), ' ', ABS(-5) * 2, '|'
+      SELECT POWER(2, 10), ' ', SQRT(16), ' ', SQRT(2.25), ' ', LOG(1), ' ', LOG(1000, 10), '|'
+      SELECT Name, ':', ABS(Amount), ':', ROUND(Amount, 1), ':', MOD(Amount, 5) FROM test.M
+      SELECT '|[', ABS(NULL), ROUND(NULL, 1), ROUND(2, NULL), MOD(NULL, 2), '] ', ROUND(-15, -1), ' ', ROUND(4999999999999999999, -19)
+      SELECT '|[', SQRT(NULL), POWER(2, NULL), LOG(NULL, 10), ']'
+      SELECT Name, ':', SQRT(X), ':', POWER(X, 2), ':', LOG(X, 2), ':', POWER(2, X), ':' FROM test.F
+    ";
+    db.run(&sql, &mut tr);
+    assert_eq!(tr.get_error(), "");
+    assert_eq!(
+        std::str::from_utf8(&tr.rp.output).unwrap(),
+        "43 2024-02-29T12:00:00 2024-02-29T12:00:00.000001|5 1.5 1.26 2.68|ABC AB 258 false false|\
+         0 0.0 0.0 0|a:-12::b:::|3 2.5 -1.00 0 -3.0 3.00 7 -1.0|2.350 -3.0 1300 3 1.2350000|\
+         -1 1.5 1.5 10|1024 4 1.5 0 3|a:12.35:-12.40:-2.35b:::|[] -20 0|[]a:4:256:4:65536:b:::::"
+    );
+
+    for (sql, err) in [
+        ("SELECT CAST('x' AS int)", "cannot convert 'x' to Int"),
+        (
+            "SELECT CAST(1234.5 AS decimal(4,1))",
+            "cannot convert '1234.5' to Decimal",
+        ),
+        ("SELECT CAST(0x01 AS float)", "cannot convert"),
+        ("SELECT CAST(1 AS thing)", "datatype expected"),
+        ("SELECT ABS('a')", "Builtin function arg 1 type mismatch"),
+        ("SELECT MOD(5, 0)", "division by zero"),
+        ("SELECT MOD(5, 2.5)", "MOD argument type mismatch"),
+        ("SELECT SQRT(-1)", "SQRT argument out of range"),
+        ("SELECT SQRT('a')", "Builtin function arg 1 type mismatch"),
+        (
+            "DECLARE f float NULL, g float SET g = SQRT(f)",
+            "assigned variable is not nullable",
+        ),
+        ("SELECT ROUND(9223372036854775807, -1)", "integer overflow"),
+        (
+            "SELECT ROUND(-9000000000000000000, -19)",
+            "integer overflow",
+        ),
+    ] {
+        let mut tr = GenTransaction::default();
+        db.run(sql, &mut tr);
+        assert!(tr.get_error().starts_with(err), "{}", tr.get_error());
+    }
+}
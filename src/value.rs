@@ -10,7 +10,7 @@ pub struct Code {
 }
 
 #[derive(Clone)]
-/// Simple value ( Binary, String, Int, Float, Bool ).
+/// Simple value ( Binary, String, Int, Float, Bool, Decimal ).
 ///
 /// When stored in a database record, binary(n) and string(n) values are allocated (n+1) bytes (8<=n<=249).
 /// If the value is more than n bytes, the length and the first (n-8) bytes are stored inline, and the rest are coded.
@@ -30,20 +30,28 @@ pub enum Value {
     Float(f64),
     ///
     Bool(bool),
+    /// Scaled value and scale ( number of digits after the decimal point ).
+    Decimal(i64, u8),
     ///
     For(Rc<RefCell<run::ForState>>),
     ///
     ForSort(Rc<RefCell<run::ForSortState>>),
+    /// SQL NULL ( value of nullable type that is unknown ).
+    Null,
 }
 
 impl Value {
-    /// Get the default Value for a DataType.
+    /// Get the default Value for a DataType ( NULL for a nullable type ).
     pub fn default(t: DataType) -> Value {
+        if nullable(t) {
+            return Value::Null;
+        }
         match data_kind(t) {
             DataKind::Bool => Value::Bool(false),
             DataKind::Float => Value::Float(0.0),
             DataKind::String => Value::String(Rc::new(String::new())),
             DataKind::Binary => Value::RcBinary(Rc::new(Vec::new())),
+            DataKind::Decimal => Value::Decimal(0, data_scale(t) as u8),
             _ => Value::Int(0),
         }
     }
@@ -76,6 +84,7 @@ impl Value {
                 };
                 Value::Float(f)
             }
+            DataKind::Decimal => Value::Decimal(util::iget(data, off, size), data_scale(typ) as u8),
             _ => Value::Int(util::iget(data, off, size) as i64),
         };
         (val, code)
@@ -88,7 +97,11 @@ impl Value {
             Value::Bool(x) => {
                 data[off] = if *x { 1 } else { 0 };
             }
+            Value::Int(x) if data_kind(typ) == DataKind::Decimal => {
+                util::iset(data, off, decimal::to_column(*x, 0, typ), size)
+            }
             Value::Int(x) => util::iset(data, off, *x, size),
+            Value::Decimal(x, s) => util::iset(data, off, decimal::to_column(*x, *s, typ), size),
             Value::Float(x) => {
                 if size == 8 {
                     let bytes = (*x).to_le_bytes();
@@ -120,8 +133,10 @@ impl Value {
             Value::Int(x) => Rc::new(x.to_string()),
             Value::Bool(x) => Rc::new(x.to_string()),
             Value::Float(x) => Rc::new(x.to_string()),
+            Value::Decimal(x, s) => Rc::new(decimal::to_string(*x, *s)),
             Value::RcBinary(x) => Rc::new(util::to_hex(x)),
             Value::ArcBinary(x) => Rc::new(util::to_hex(x)),
+            Value::Null => Rc::new(String::new()),
             _ => panic!("str not implemented"),
         }
     }
@@ -130,6 +145,7 @@ impl Value {
     pub fn int(&self) -> i64 {
         match self {
             Value::Int(x) => *x,
+            Value::Null => panic!("unexpected NULL value"),
             _ => panic!(),
         }
     }
@@ -138,13 +154,21 @@ impl Value {
     pub fn float(&self) -> f64 {
         match self {
             Value::Float(x) => *x,
+            Value::Null => panic!("unexpected NULL value"),
             _ => panic!(),
         }
     }
 
+    /// Is the value NULL.
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
     /// Append to a String.
     pub fn append(&mut self, val: &Value) {
-        if let Value::String(s) = self {
+        if self.is_null() || val.is_null() {
+            *self = Value::Null;
+        } else if let Value::String(s) = self {
             let val = val.str();
             if let Some(ms) = Rc::get_mut(s) {
                 ms.push_str(&val);
@@ -161,22 +185,48 @@ impl Value {
 
     /// Inc an integer or float.
     pub fn inc(&mut self, val: &Value) {
+        if val.is_null() {
+            *self = Value::Null;
+        }
         match self {
+            Value::Null => {}
             Value::Int(x) => *x += val.int(),
             Value::Float(x) => *x += val.float(),
+            Value::Decimal(_, s) => {
+                let s = *s;
+                *self = decimal::arithmetic(Token::Plus, self, val);
+                self.rescale(s);
+            }
             _ => panic!(),
         }
     }
 
     /// Dec an integer or float.
     pub fn dec(&mut self, val: &Value) {
+        if val.is_null() {
+            *self = Value::Null;
+        }
         match self {
+            Value::Null => {}
             Value::Int(x) => *x -= val.int(),
             Value::Float(x) => *x -= val.float(),
+            Value::Decimal(_, s) => {
+                let s = *s;
+                *self = decimal::arithmetic(Token::Minus, self, val);
+                self.rescale(s);
+            }
             _ => panic!(),
         }
     }
 
+    /// Change the scale of a decimal value ( rounding half away from zero ).
+    fn rescale(&mut self, scale: u8) {
+        if let Value::Decimal(x, s) = self {
+            *x = decimal::rescale(*x, *s, scale);
+            *s = scale;
+        }
+    }
+
     /// Convert a Value to a Binary.
     pub fn bin(&self) -> Rc<Vec<u8>> {
         match self {
@@ -189,20 +239,31 @@ impl Value {
     }
 }
 
-/// Value comparison.
+/// Value comparison. NULL compares equal to NULL and less than any other value.
 impl std::cmp::Ord for Value {
     fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Value::Null, Value::Null) => return Ordering::Equal,
+            (Value::Null, _) => return Ordering::Less,
+            (_, Value::Null) => return Ordering::Greater,
+            _ => {}
+        }
         match self {
             Value::String(s1) => {
                 if let Value::String(s2) = other {
                     return s1.cmp(s2);
                 }
             }
-            Value::Int(x1) => {
-                if let Value::Int(x2) = other {
-                    return x1.cmp(x2);
-                }
-            }
+            Value::Int(x1) => match other {
+                Value::Int(x2) => return x1.cmp(x2),
+                Value::Decimal(x2, s2) => return decimal::cmp(*x1, 0, *x2, *s2),
+                _ => {}
+            },
+            Value::Decimal(x1, s1) => match other {
+                Value::Int(x2) => return decimal::cmp(*x1, *s1, *x2, 0),
+                Value::Decimal(x2, s2) => return decimal::cmp(*x1, *s1, *x2, *s2),
+                _ => {}
+            },
             Value::Float(x1) => {
                 if let Value::Float(x2) = other {
                     return x1.partial_cmp(x2).unwrap();
@@ -213,6 +274,11 @@ impl std::cmp::Ord for Value {
                     return b1.cmp(b2);
                 }
             }
+            Value::Bool(x1) => {
+                if let Value::Bool(x2) = other {
+                    return x1.cmp(x2);
+                }
+            }
             _ => {}
         }
         panic!()